@@ -0,0 +1,119 @@
+#[cfg(not(feature = "std"))]
+fn main() {}
+
+#[cfg(feature = "std")]
+fn main() {
+    use clap::Parser;
+    use libflate::deflate::{self, EncodeOptions, Strategy};
+    use libflate::lz77::{DefaultLz77EncoderBuilder, HuffmanOnlyLz77Encoder};
+    use std::fs;
+    use std::io::{self, Read, Write};
+    use std::time;
+
+    /// Measures the compression ratio and the throughput of the DEFLATE encoder and decoder
+    /// for each combination of options.
+    #[derive(Parser)]
+    struct Args {
+        /// The number of iterations of each measurement (the fastest one is reported).
+        #[clap(short, long, default_value = "3")]
+        iterations: usize,
+
+        input: String,
+    }
+
+    fn measure<F>(iterations: usize, mut f: F) -> time::Duration
+    where
+        F: FnMut(),
+    {
+        (0..iterations.max(1))
+            .map(|_| {
+                let start = time::Instant::now();
+                f();
+                start.elapsed()
+            })
+            .min()
+            .expect("Never fails")
+    }
+
+    fn bench<E>(name: &str, plain: &[u8], options: EncodeOptions<E>, iterations: usize)
+    where
+        E: libflate::lz77::Lz77Encode + Clone,
+    {
+        let mut encoded = Vec::new();
+        let encode_time = measure(iterations, || {
+            let mut encoder = deflate::Encoder::with_options(Vec::new(), options.clone());
+            encoder.write_all(plain).expect("Encoding failed");
+            encoder.try_finish().expect("Encoding failed");
+            assert_eq!(
+                encoder.bytes_written() as usize,
+                encoder.as_inner_ref().len()
+            );
+            encoded = encoder.into_inner();
+        });
+
+        let decode_time = measure(iterations, || {
+            let mut decoder = deflate::Decoder::new(&encoded[..]);
+            let size = io::copy(&mut decoder, &mut io::sink()).expect("Decoding failed");
+            assert_eq!(size as usize, plain.len());
+            let (_, consumed) = decoder.into_inner_with_consumed();
+            assert_eq!(consumed as usize, encoded.len());
+        });
+
+        let mb_per_sec = |d: time::Duration| plain.len() as f64 / d.as_secs_f64() / 1_000_000.0;
+        println!(
+            "| {:<20} | {:>7.3} | {:>15.2} | {:>15.2} |",
+            name,
+            encoded.len() as f64 / plain.len().max(1) as f64,
+            mb_per_sec(encode_time),
+            mb_per_sec(decode_time)
+        );
+    }
+
+    let args = Args::parse();
+    let mut plain = Vec::new();
+    fs::File::open(&args.input)
+        .unwrap_or_else(|_| panic!("Can't open file: {}", args.input))
+        .read_to_end(&mut plain)
+        .expect("Reading input failed");
+    let n = args.iterations;
+
+    println!("# input_size={}", plain.len());
+    println!(
+        "| {:<20} | {:>7} | {:>15} | {:>15} |",
+        "strategy", "ratio", "encode (MB/s)", "decode (MB/s)"
+    );
+    println!("|{:-<22}|{:->9}|{:->17}|{:->17}|", "", "", "", "");
+    bench("stored", &plain, EncodeOptions::new().no_compression(), n);
+    bench(
+        "fixed",
+        &plain,
+        EncodeOptions::new().fixed_huffman_codes(),
+        n,
+    );
+    bench("dynamic", &plain, EncodeOptions::new(), n);
+    for (name, strategy) in [
+        ("dynamic (filtered)", Strategy::Filtered),
+        ("dynamic (huffman)", Strategy::HuffmanOnly),
+        ("dynamic (rle)", Strategy::Rle),
+    ] {
+        bench(name, &plain, EncodeOptions::new().strategy(strategy), n);
+    }
+    bench(
+        "huffman-only lz77",
+        &plain,
+        EncodeOptions::with_lz77(HuffmanOnlyLz77Encoder::new()),
+        n,
+    );
+    bench(
+        "window=4KB",
+        &plain,
+        EncodeOptions::with_lz77(DefaultLz77EncoderBuilder::new().window_size(4096).build()),
+        n,
+    );
+    bench(
+        "min_match=5",
+        &plain,
+        EncodeOptions::with_lz77(DefaultLz77EncoderBuilder::new().min_match(5).build()),
+        n,
+    );
+}
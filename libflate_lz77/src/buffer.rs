@@ -0,0 +1,67 @@
+use alloc::vec::Vec;
+use core::cmp;
+
+/// The input buffered by an LZ77 encoder until it is flushed.
+///
+/// The buffer starts with the preset dictionary (if any), which is followed by the data to be encoded.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InputBuffer {
+    buf: Vec<u8>,
+    dictionary_len: usize,
+}
+impl InputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the buffered bytes, including the preset dictionary.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the length of the preset dictionary at the head of the buffer.
+    pub fn dictionary_len(&self) -> usize {
+        self.dictionary_len
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the size of the input at which an encoder with the given window size flushes the buffer.
+    ///
+    /// The input is flushed at fixed intervals so that the result does not depend on how it is chunked.
+    /// A `window_size` of `0` is treated as `1`, so that the threshold is never zero.
+    pub fn flush_threshold(window_size: u16) -> usize {
+        cmp::max(window_size, 1) as usize * 8
+    }
+
+    /// Appends `buf` up to the flush threshold (see `flush_threshold`).
+    ///
+    /// If the threshold is reached, the buffer should be flushed and
+    /// the rest of `buf` (which has not been appended yet) is returned.
+    pub fn fill<'a>(&mut self, buf: &'a [u8], window_size: u16) -> Option<&'a [u8]> {
+        let threshold = Self::flush_threshold(window_size);
+        let size = cmp::min(buf.len(), threshold.saturating_sub(self.buf.len()));
+        self.buf.extend_from_slice(&buf[..size]);
+        (self.buf.len() >= threshold).then_some(&buf[size..])
+    }
+
+    /// Replaces the preset dictionary with the last `window_size` bytes of `dictionary`.
+    pub fn set_dictionary(&mut self, dictionary: &[u8], window_size: u16) {
+        let dictionary = &dictionary[dictionary.len().saturating_sub(window_size as usize)..];
+        self.buf
+            .splice(0..self.dictionary_len, dictionary.iter().cloned());
+        self.dictionary_len = dictionary.len();
+    }
+
+    /// Discards both the preset dictionary and the data.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.dictionary_len = 0;
+    }
+}
@@ -0,0 +1,96 @@
+use alloc::vec::Vec;
+use core2::io;
+
+use super::Code;
+
+/// Type for constructing a valid sequence of [`Code`]s.
+///
+/// # Examples
+/// ```
+/// use libflate_lz77::{Code, CodeBuilder, Lz77Decoder};
+///
+/// let codes = CodeBuilder::new()
+///     .literal(b'a')
+///     .literal(b'b')
+///     .pointer(4, 2)
+///     .unwrap()
+///     .finish();
+///
+/// let mut decoder = Lz77Decoder::new();
+/// for code in codes {
+///     decoder.decode(code).unwrap();
+/// }
+/// assert_eq!(decoder.buffer(), b"ababab");
+///
+/// // Invalid pointers are rejected
+/// assert!(CodeBuilder::new().literal(b'a').pointer(3, 2).is_err());
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CodeBuilder {
+    codes: Vec<Code>,
+    decoded_len: usize,
+}
+
+impl CodeBuilder {
+    /// Makes a new builder instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a literal byte.
+    pub fn literal(&mut self, b: u8) -> &mut Self {
+        self.codes.push(Code::Literal(b));
+        self.decoded_len += 1;
+        self
+    }
+
+    /// Appends a backward pointer.
+    ///
+    /// If `length` is not between `3` and [`MAX_LENGTH`](crate::MAX_LENGTH), or
+    /// `distance` is not between `1` and [`MAX_DISTANCE`](crate::MAX_DISTANCE) nor
+    /// within the data represented by the preceding codes,
+    /// an `InvalidInput` error is returned.
+    pub fn pointer(&mut self, length: u16, distance: u16) -> io::Result<&mut Self> {
+        if !(3..=super::MAX_LENGTH).contains(&length) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                #[cfg(feature = "std")]
+                format!("Pointer length must be between 3 and 258: length={length}"),
+                #[cfg(not(feature = "std"))]
+                "Pointer length must be between 3 and 258",
+            ));
+        }
+        if distance == 0 || distance > super::MAX_DISTANCE || self.decoded_len < distance as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                #[cfg(feature = "std")]
+                format!(
+                    "Too long backword reference: decoded_len={}, distance={}",
+                    self.decoded_len, distance
+                ),
+                #[cfg(not(feature = "std"))]
+                "Too long backword reference",
+            ));
+        }
+        self.codes.push(Code::Pointer {
+            length,
+            backward_distance: distance,
+        });
+        self.decoded_len += length as usize;
+        Ok(self)
+    }
+
+    /// Returns the built codes.
+    pub fn finish(&self) -> Vec<Code> {
+        self.codes.clone()
+    }
+}
+
+impl IntoIterator for CodeBuilder {
+    type Item = Code;
+    type IntoIter = alloc::vec::IntoIter<Code>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.codes.into_iter()
+    }
+}
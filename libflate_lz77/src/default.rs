@@ -5,16 +5,18 @@ use hashbrown::HashMap;
 #[cfg(feature = "std")]
 use std::collections::HashMap;
 
+use super::buffer::InputBuffer;
 use super::Code;
 use super::Lz77Encode;
 use super::Sink;
 
 /// A [`Lz77Encode`] implementation used by default.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DefaultLz77Encoder {
     window_size: u16,
     max_length: u16,
-    buf: Vec<u8>,
+    min_match: u16,
+    input: InputBuffer,
 }
 
 impl DefaultLz77Encoder {
@@ -56,64 +58,91 @@ impl DefaultLz77Encoder {
             .window_size(cmp::min(size, super::MAX_WINDOW_SIZE))
             .build()
     }
-}
 
-impl Default for DefaultLz77Encoder {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl Lz77Encode for DefaultLz77Encoder {
-    fn encode<S>(&mut self, buf: &[u8], sink: S)
+    /// Encodes `buf`, the first `dictionary_len` bytes of which are the preset dictionary.
+    fn encode_chunk<S>(&self, buf: &[u8], dictionary_len: usize, mut sink: S)
     where
         S: Sink,
     {
-        self.buf.extend_from_slice(buf);
-        if self.buf.len() >= self.window_size as usize * 8 {
-            self.flush(sink);
+        let mut prefix_table = PrefixTable::new(buf.len());
+        let end = cmp::max(3, buf.len()) - 3;
+        for k in 0..cmp::min(dictionary_len, end) {
+            prefix_table.insert(prefix(&buf[k..]), k as u32);
         }
-    }
-    fn flush<S>(&mut self, mut sink: S)
-    where
-        S: Sink,
-    {
-        let mut prefix_table = PrefixTable::new(self.buf.len());
-        let mut i = 0;
-        let end = cmp::max(3, self.buf.len()) - 3;
+        let mut i = dictionary_len;
         while i < end {
-            let key = prefix(&self.buf[i..]);
+            let key = prefix(&buf[i..]);
             let matched = prefix_table.insert(key, i as u32);
             if let Some(j) = matched.map(|j| j as usize) {
                 let distance = i - j;
                 if distance <= self.window_size as usize {
-                    let length = 3 + longest_common_prefix(
-                        &self.buf,
-                        i + 3,
-                        j + 3,
-                        self.max_length as usize,
-                    );
-                    sink.consume(Code::Pointer {
-                        length,
-                        backward_distance: distance as u16,
-                    });
-                    for k in (i..).take(length as usize).skip(1) {
-                        if k >= end {
-                            break;
+                    let length =
+                        3 + longest_common_prefix(buf, i + 3, j + 3, self.max_length as usize);
+                    if length >= self.min_match {
+                        sink.consume(Code::Pointer {
+                            length,
+                            backward_distance: distance as u16,
+                        });
+                        for k in (i..).take(length as usize).skip(1) {
+                            if k >= end {
+                                break;
+                            }
+                            prefix_table.insert(prefix(&buf[k..]), k as u32);
                         }
-                        prefix_table.insert(prefix(&self.buf[k..]), k as u32);
+                        i += length as usize;
+                        continue;
                     }
-                    i += length as usize;
-                    continue;
                 }
             }
-            sink.consume(Code::Literal(self.buf[i]));
+            sink.consume(Code::Literal(buf[i]));
             i += 1;
         }
-        for b in &self.buf[i..] {
+        for b in &buf[i..] {
             sink.consume(Code::Literal(*b));
         }
-        self.buf.clear();
+    }
+}
+
+impl Default for DefaultLz77Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lz77Encode for DefaultLz77Encoder {
+    fn encode<S>(&mut self, mut buf: &[u8], mut sink: S)
+    where
+        S: Sink,
+    {
+        while let Some(rest) = self.input.fill(buf, self.window_size) {
+            self.flush(&mut sink);
+            buf = rest;
+        }
+    }
+    fn encode_borrowed<S>(&mut self, mut buf: &[u8], mut sink: S)
+    where
+        S: Sink,
+    {
+        // While nothing is buffered, each chunk to be flushed is encoded in place (see `encode`)
+        let threshold = InputBuffer::flush_threshold(self.window_size);
+        while self.input.is_empty() && buf.len() >= threshold {
+            self.encode_chunk(&buf[..threshold], 0, &mut sink);
+            buf = &buf[threshold..];
+        }
+        self.encode(buf, sink);
+    }
+    fn flush<S>(&mut self, sink: S)
+    where
+        S: Sink,
+    {
+        self.encode_chunk(self.input.as_bytes(), self.input.dictionary_len(), sink);
+        self.input.clear();
+    }
+    fn set_dictionary(&mut self, dictionary: &[u8]) {
+        self.input.set_dictionary(dictionary, self.window_size);
+    }
+    fn set_min_match(&mut self, min_match: u16) {
+        self.min_match = min_match.clamp(3, super::MAX_LENGTH);
     }
     fn window_size(&self) -> u16 {
         self.window_size
@@ -206,10 +235,11 @@ impl LargePrefixTable {
 ///     .max_length(MAX_LENGTH)
 ///     .build();
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DefaultLz77EncoderBuilder {
     window_size: u16,
     max_length: u16,
+    min_match: u16,
 }
 
 impl DefaultLz77EncoderBuilder {
@@ -218,6 +248,7 @@ impl DefaultLz77EncoderBuilder {
         DefaultLz77EncoderBuilder {
             window_size: super::MAX_WINDOW_SIZE,
             max_length: super::MAX_LENGTH,
+            min_match: 3,
         }
     }
 
@@ -246,12 +277,39 @@ impl DefaultLz77EncoderBuilder {
         }
     }
 
+    /// Set the minimum length of a pointer command this encoder will emit.
+    ///
+    /// Matches shorter than this are encoded as literals,
+    /// which may improve the efficiency of the Huffman coding for some kinds of data.
+    /// The value is clamped to the range from 3 (the minimum length in DEFLATE) to [`MAX_LENGTH`](super::MAX_LENGTH).
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate_lz77::{Code, DefaultLz77EncoderBuilder, Lz77Encode};
+    ///
+    /// let mut encoder = DefaultLz77EncoderBuilder::new().min_match(5).build();
+    /// let mut codes = Vec::new();
+    /// encoder.encode(b"abcd-abcd-abcdef-abcdef", &mut codes);
+    /// encoder.flush(&mut codes);
+    /// assert!(codes.iter().all(|c| match *c {
+    ///     Code::Pointer { length, .. } => length >= 5,
+    ///     Code::Literal(_) => true,
+    /// }));
+    /// ```
+    pub fn min_match(self, min_match: u16) -> Self {
+        DefaultLz77EncoderBuilder {
+            min_match: min_match.clamp(3, super::MAX_LENGTH),
+            ..self
+        }
+    }
+
     /// Build the encoder with the builder state's parameters.
     pub fn build(self) -> DefaultLz77Encoder {
         DefaultLz77Encoder {
             window_size: self.window_size,
             max_length: self.max_length,
-            buf: Vec::new(),
+            min_match: self.min_match,
+            input: InputBuffer::new(),
         }
     }
 }
@@ -0,0 +1,106 @@
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use hashbrown::{HashMap, HashSet};
+
+use crate::MAX_DISTANCE;
+
+/// Length of the substrings whose frequencies are counted.
+const GRAM_LEN: usize = 8;
+
+/// Length of the segments which are the candidates of the parts of a dictionary.
+const SEGMENT_LEN: usize = 32;
+
+/// Builds a preset dictionary from `samples`.
+///
+/// The dictionary consists of the segments of the samples which contain the most common substrings.
+/// The frequency of a substring is the number of the samples which contain it,
+/// so substrings that appear in only one sample are never selected.
+/// The segments are selected greedily, and the most useful ones are placed at the end of the dictionary
+/// because the nearer data can be referred by shorter distances.
+///
+/// The length of the result is at most `max_len` (and [`MAX_DISTANCE`], as farther data can not be referred).
+/// The result may be shorter (or empty) if the samples do not share enough substrings.
+///
+/// # Examples
+/// ```
+/// use libflate::lz77;
+///
+/// let samples = [
+///     &br#"{"id":1,"name":"alice","email":"alice@example.com"}"#[..],
+///     &br#"{"id":2,"name":"bob","email":"bob@example.com"}"#[..],
+///     &br#"{"id":3,"name":"carol","email":"carol@example.com"}"#[..],
+/// ];
+/// let dictionary = lz77::build_dictionary(&samples, 1024);
+/// assert!(!dictionary.is_empty());
+/// assert!(dictionary.len() <= 1024);
+/// ```
+pub fn build_dictionary(samples: &[&[u8]], max_len: usize) -> Vec<u8> {
+    let max_len = max_len.min(MAX_DISTANCE as usize);
+    if max_len == 0 {
+        return Vec::new();
+    }
+
+    let mut frequencies = HashMap::<&[u8], u32>::new();
+    for sample in samples {
+        let grams = sample.windows(GRAM_LEN).collect::<HashSet<_>>();
+        for gram in grams {
+            *frequencies.entry(gram).or_default() += 1;
+        }
+    }
+    frequencies.retain(|_, n| *n > 1);
+
+    let segments = samples
+        .iter()
+        .flat_map(|sample| {
+            (0..sample.len().saturating_sub(GRAM_LEN - 1))
+                .step_by(GRAM_LEN)
+                .map(move |start| &sample[start..sample.len().min(start + SEGMENT_LEN)])
+        })
+        .collect::<Vec<_>>();
+    let score = |frequencies: &HashMap<&[u8], u32>, segment: &[u8]| {
+        segment
+            .windows(GRAM_LEN)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|gram| u64::from(frequencies.get(gram).copied().unwrap_or(0)))
+            .sum::<u64>()
+    };
+
+    // Scores only decrease as grams get covered, so stale entries are re-scored lazily
+    let mut heap = segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| (score(&frequencies, segment), i))
+        .filter(|&(score, _)| score > 0)
+        .collect::<BinaryHeap<_>>();
+    let mut selected = Vec::new();
+    let mut selected_len = 0;
+    while let Some((old_score, i)) = heap.pop() {
+        if selected_len >= max_len {
+            break;
+        }
+        let new_score = score(&frequencies, segments[i]);
+        if new_score == 0 {
+            continue;
+        }
+        if new_score < old_score && heap.peek().is_some_and(|&(top, _)| new_score < top) {
+            heap.push((new_score, i));
+            continue;
+        }
+        for gram in segments[i].windows(GRAM_LEN) {
+            frequencies.remove(gram);
+        }
+        selected.push(segments[i]);
+        selected_len += segments[i].len();
+    }
+
+    let mut dictionary = selected
+        .into_iter()
+        .rev()
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    let excess = dictionary.len().saturating_sub(max_len);
+    dictionary.drain(..excess);
+    dictionary
+}
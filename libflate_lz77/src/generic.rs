@@ -0,0 +1,145 @@
+use super::buffer::InputBuffer;
+use super::Code;
+use super::Lz77Encode;
+use super::Sink;
+
+/// The [`MatchFinder`] trait defines the strategy to search a match of LZ77 compression.
+///
+/// Combined with [`GenericLz77Encoder`], implementors only need to provide the search,
+/// and the emission of literals and pointers is handled by the encoder.
+pub trait MatchFinder {
+    /// Searches a match for the data starting at `data[pos]`.
+    ///
+    /// Returns `Some((length, distance))` if `data[pos..][..length]` equals to `data[pos - distance..][..length]`.
+    /// `length` must be between `3` and [`MAX_LENGTH`](crate::MAX_LENGTH),
+    /// and `distance` must be between `1` and the window size of the encoder.
+    /// Note that the matched region may overlap with the data starting at `pos`.
+    ///
+    /// An invalid match is ignored by [`GenericLz77Encoder`] and the byte is encoded as a literal instead.
+    fn find_match(&self, data: &[u8], pos: usize) -> Option<(u16, u16)>;
+}
+
+/// A [`Lz77Encode`] implementation which uses a user-supplied [`MatchFinder`].
+///
+/// # Examples
+/// ```
+/// use core2::io::{Read, Write};
+/// use libflate::deflate;
+/// use libflate::lz77::{GenericLz77Encoder, MatchFinder, MAX_LENGTH};
+///
+/// // A (very slow) brute-force match finder
+/// struct BruteForce;
+/// impl MatchFinder for BruteForce {
+///     fn find_match(&self, data: &[u8], pos: usize) -> Option<(u16, u16)> {
+///         (1..=pos)
+///             .map(|distance| {
+///                 let length = data[pos..]
+///                     .iter()
+///                     .zip(&data[pos - distance..])
+///                     .take(MAX_LENGTH as usize)
+///                     .take_while(|(a, b)| a == b)
+///                     .count();
+///                 (length as u16, distance as u16)
+///             })
+///             .filter(|&(length, _)| length >= 3)
+///             .max_by_key(|&(length, _)| length)
+///     }
+/// }
+///
+/// let options = deflate::EncodeOptions::with_lz77(GenericLz77Encoder::new(BruteForce));
+/// let mut encoder = deflate::Encoder::with_options(Vec::new(), options);
+/// encoder.write_all(b"Hello World! Hello World!").unwrap();
+/// let encoded_data = encoder.finish().into_result().unwrap();
+///
+/// let mut decoded_data = Vec::new();
+/// deflate::Decoder::new(&encoded_data[..]).read_to_end(&mut decoded_data).unwrap();
+/// assert_eq!(decoded_data, b"Hello World! Hello World!");
+/// ```
+#[derive(Debug, Clone)]
+pub struct GenericLz77Encoder<M> {
+    finder: M,
+    window_size: u16,
+    min_match: u16,
+    input: InputBuffer,
+}
+
+impl<M> GenericLz77Encoder<M>
+where
+    M: MatchFinder,
+{
+    /// Makes a new encoder instance which uses `finder` to search matches.
+    pub fn new(finder: M) -> Self {
+        GenericLz77Encoder {
+            finder,
+            window_size: super::MAX_WINDOW_SIZE,
+            min_match: 3,
+            input: InputBuffer::new(),
+        }
+    }
+
+    /// Returns the reference to the match finder.
+    pub fn finder(&self) -> &M {
+        &self.finder
+    }
+
+    /// Unwraps this `GenericLz77Encoder`, returning the match finder.
+    pub fn into_finder(self) -> M {
+        self.finder
+    }
+
+    fn is_valid_match(&self, pos: usize, length: u16, distance: u16) -> bool {
+        (self.min_match..=super::MAX_LENGTH).contains(&length)
+            && (1..=self.window_size).contains(&distance)
+            && usize::from(distance) <= pos
+            && pos + usize::from(length) <= self.input.len()
+            && self.input.as_bytes()[pos..][..usize::from(length)]
+                == self.input.as_bytes()[pos - usize::from(distance)..][..usize::from(length)]
+    }
+}
+
+impl<M> Lz77Encode for GenericLz77Encoder<M>
+where
+    M: MatchFinder,
+{
+    fn encode<S>(&mut self, mut buf: &[u8], mut sink: S)
+    where
+        S: Sink,
+    {
+        while let Some(rest) = self.input.fill(buf, self.window_size) {
+            self.flush(&mut sink);
+            buf = rest;
+        }
+    }
+    fn flush<S>(&mut self, mut sink: S)
+    where
+        S: Sink,
+    {
+        let buf = self.input.as_bytes();
+        let mut i = self.input.dictionary_len();
+        while i < buf.len() {
+            match self.finder.find_match(buf, i) {
+                Some((length, distance)) if self.is_valid_match(i, length, distance) => {
+                    sink.consume(Code::Pointer {
+                        length,
+                        backward_distance: distance,
+                    });
+                    i += length as usize;
+                }
+                _ => {
+                    sink.consume(Code::Literal(buf[i]));
+                    i += 1;
+                }
+            }
+        }
+        self.input.clear();
+    }
+    fn set_dictionary(&mut self, dictionary: &[u8]) {
+        self.input.set_dictionary(dictionary, self.window_size);
+    }
+    fn set_min_match(&mut self, min_match: u16) {
+        self.min_match = min_match.clamp(3, super::MAX_LENGTH);
+    }
+    fn window_size(&self) -> u16 {
+        self.window_size
+    }
+}
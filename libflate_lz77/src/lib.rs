@@ -6,13 +6,22 @@
 
 extern crate alloc;
 
+pub use self::builder::CodeBuilder;
 pub use self::default::{DefaultLz77Encoder, DefaultLz77EncoderBuilder};
+pub use self::dictionary::build_dictionary;
+pub use self::generic::{GenericLz77Encoder, MatchFinder};
+pub use self::optimal::OptimalLz77Encoder;
 use alloc::vec::Vec;
 use core::cmp;
 use core2::io;
 use rle_decode_fast::rle_decode;
 
+mod buffer;
+mod builder;
 mod default;
+mod dictionary;
+mod generic;
+mod optimal;
 
 /// Maximum length of sharable bytes in a pointer.
 pub const MAX_LENGTH: u16 = 258;
@@ -62,7 +71,7 @@ pub trait Sink {
     /// Consumes a LZ77 encoded `Code`.
     fn consume(&mut self, code: Code);
 }
-impl<'a, T> Sink for &'a mut T
+impl<T> Sink for &mut T
 where
     T: Sink,
 {
@@ -91,6 +100,40 @@ pub trait Lz77Encode {
     where
         S: Sink;
 
+    /// Encodes a buffer like [`Lz77Encode::encode`], producing the same codes.
+    ///
+    /// The difference is that an implementation may process `buf` in place
+    /// instead of copying it into an internal buffer, which is useful for a large borrowed input
+    /// (e.g., a memory-mapped file).
+    ///
+    /// If the implementation is omitted, [`Lz77Encode::encode`] will be called.
+    fn encode_borrowed<S>(&mut self, buf: &[u8], sink: S)
+    where
+        S: Sink,
+    {
+        self.encode(buf, sink);
+    }
+
+    /// Sets a preset dictionary, i.e., the data that precedes the data to be encoded.
+    ///
+    /// Subsequent codes may refer to the dictionary by pointers,
+    /// so the decoder must have decoded (or been given) the same data beforehand.
+    /// The dictionary is used until the next flush, and
+    /// it should be set before encoding any data (or right after a flush).
+    ///
+    /// If the implementation is omitted, the dictionary will be ignored.
+    #[allow(unused_variables)]
+    fn set_dictionary(&mut self, dictionary: &[u8]) {}
+
+    /// Sets the minimum length of the matches which are emitted as pointers.
+    ///
+    /// Shorter matches are emitted as literals instead.
+    /// The value is clamped to the range `3..=MAX_LENGTH`.
+    ///
+    /// If the implementation is omitted, the minimum length will be left unchanged.
+    #[allow(unused_variables)]
+    fn set_min_match(&mut self, min_match: u16) {}
+
     /// Returns the compression level of the encoder.
     ///
     /// If the implementation is omitted, [`CompressionLevel::Balance`] will be returned.
@@ -107,7 +150,7 @@ pub trait Lz77Encode {
 }
 
 /// A no compression implementation of [`Lz77Encode`] trait.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct NoCompressionLz77Encoder;
 impl NoCompressionLz77Encoder {
     /// Makes a new encoder instance.
@@ -147,11 +190,64 @@ impl Lz77Encode for NoCompressionLz77Encoder {
     }
 }
 
+/// An implementation of [`Lz77Encode`] trait which emits only literals.
+///
+/// Unlike [`NoCompressionLz77Encoder`], this is intended to be used with Huffman-coded blocks
+/// (i.e., without `no_compression()`), so the data is compressed by the Huffman coding alone.
+/// This is fast and works well for data whose byte distribution is skewed but which has few repeated strings.
+#[derive(Debug, Default, Clone)]
+pub struct HuffmanOnlyLz77Encoder;
+impl HuffmanOnlyLz77Encoder {
+    /// Makes a new encoder instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate;
+    /// use libflate::lz77::{Lz77Encode, HuffmanOnlyLz77Encoder, CompressionLevel};
+    ///
+    /// let lz77 = HuffmanOnlyLz77Encoder::new();
+    /// assert_eq!(lz77.compression_level(), CompressionLevel::Fast);
+    ///
+    /// let options = deflate::EncodeOptions::with_lz77(lz77).dynamic_huffman_codes();
+    /// let _deflate = deflate::Encoder::with_options(Vec::new(), options);
+    /// ```
+    pub fn new() -> Self {
+        HuffmanOnlyLz77Encoder
+    }
+}
+impl Lz77Encode for HuffmanOnlyLz77Encoder {
+    fn encode<S>(&mut self, buf: &[u8], mut sink: S)
+    where
+        S: Sink,
+    {
+        for c in buf.iter().cloned().map(Code::Literal) {
+            sink.consume(c);
+        }
+    }
+    #[allow(unused_variables)]
+    fn flush<S>(&mut self, sink: S)
+    where
+        S: Sink,
+    {
+    }
+    fn compression_level(&self) -> CompressionLevel {
+        CompressionLevel::Fast
+    }
+}
+
 /// LZ77 decoder.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Lz77Decoder {
     buffer: Vec<u8>,
     offset: usize,
+    windowed: bool,
+    window: usize,
+}
+
+impl Default for Lz77Decoder {
+    fn default() -> Self {
+        Self::with_buffer(Vec::new())
+    }
 }
 
 impl Lz77Decoder {
@@ -160,6 +256,64 @@ impl Lz77Decoder {
         Self::default()
     }
 
+    /// Makes a new [`Lz77Decoder`] instance whose window size (i.e., the maximum backward distance) is `window`.
+    ///
+    /// Only the last `window` bytes of the decoded data are retained as the history
+    /// (instead of [`MAX_DISTANCE`] bytes), and a pointer whose distance exceeds `window` is rejected
+    /// with an `InvalidData` error.
+    /// This saves memory when decoding a format whose window is smaller than the one of DEFLATE.
+    ///
+    /// A `window` of `0` is treated as `1`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate_lz77::{Code, Lz77Decoder};
+    ///
+    /// let mut decoder = Lz77Decoder::with_window(2);
+    /// decoder.decode(Code::Literal(b'a')).unwrap();
+    /// decoder.decode(Code::Literal(b'b')).unwrap();
+    /// decoder.decode(Code::Pointer { length: 4, backward_distance: 2 }).unwrap();
+    /// assert_eq!(decoder.buffer(), b"ababab");
+    ///
+    /// let pointer = Code::Pointer { length: 3, backward_distance: 3 };
+    /// assert!(decoder.decode(pointer).is_err());
+    /// ```
+    pub fn with_window(window: usize) -> Self {
+        let mut this = Self::new();
+        this.set_window(window);
+        this
+    }
+
+    /// Sets the window size (i.e., the maximum backward distance) of the decoder.
+    ///
+    /// See [`Lz77Decoder::with_window`] for details.
+    pub fn set_window(&mut self, window: usize) {
+        self.window = cmp::max(window, 1);
+    }
+
+    /// Returns the window size (i.e., the maximum backward distance) of the decoder.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Makes a new [`Lz77Decoder`] instance which uses `buffer` (after clearing it) as the internal buffer.
+    ///
+    /// This allows reusing the allocation of a buffer reclaimed by [`Lz77Decoder::into_buffer`].
+    pub fn with_buffer(mut buffer: Vec<u8>) -> Self {
+        buffer.clear();
+        Lz77Decoder {
+            buffer,
+            offset: 0,
+            windowed: false,
+            window: MAX_DISTANCE as usize,
+        }
+    }
+
+    /// Unwraps this [`Lz77Decoder`], returning the internal buffer.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buffer
+    }
+
     /// Decodes a [`Code`].
     ///
     /// The decoded bytes are appended to the buffer of [`Lz77Decoder`].
@@ -173,6 +327,18 @@ impl Lz77Decoder {
                 length,
                 backward_distance,
             } => {
+                if backward_distance as usize > self.window {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        #[cfg(feature = "std")]
+                        format!(
+                            "Backward reference beyond the window: window={}, distance={}",
+                            self.window, backward_distance
+                        ),
+                        #[cfg(not(feature = "std"))]
+                        "Backward reference beyond the window",
+                    ));
+                }
                 if self.buffer.len() < backward_distance as usize {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidData,
@@ -213,6 +379,19 @@ impl Lz77Decoder {
         self.offset = 0;
     }
 
+    /// Enables or disables the windowed mode (disabled by default).
+    ///
+    /// In the windowed mode, already-read data is discarded as soon as the read offset exceeds
+    /// twice of the window size ([`MAX_DISTANCE`] by default, see [`Lz77Decoder::with_window`]),
+    /// so that only the last bytes within the window,
+    /// which may be referred by subsequent pointers, are retained in addition to unread data.
+    ///
+    /// This bounds the memory usage when decoding a large stream with partial reads,
+    /// at the cost of more frequent buffer compaction.
+    pub fn set_windowed(&mut self, windowed: bool) {
+        self.windowed = windowed;
+    }
+
     /// Returns the buffer of [`Lz77Decoder`].
     #[inline]
     pub fn buffer(&self) -> &[u8] {
@@ -220,9 +399,16 @@ impl Lz77Decoder {
     }
 
     fn truncate_old_buffer(&mut self) {
-        if self.buffer().is_empty() && self.buffer.len() > MAX_DISTANCE as usize * 4 {
+        if self.windowed {
+            if self.offset > self.window * 2 {
+                let start = self.offset - self.window;
+                self.buffer.copy_within(start.., 0);
+                self.buffer.truncate(self.buffer.len() - start);
+                self.offset = self.window;
+            }
+        } else if self.buffer().is_empty() && self.buffer.len() > self.window * 4 {
             let old_len = self.buffer.len();
-            let new_len = MAX_DISTANCE as usize;
+            let new_len = self.window;
             {
                 // isolation to please borrow checker
                 let (dst, src) = self.buffer.split_at_mut(old_len - new_len);
@@ -250,6 +436,145 @@ mod tests {
     use alloc::vec::Vec;
     use core2::io::Read as _;
 
+    #[test]
+    fn min_match_works() {
+        let text = include_bytes!("../../README.md");
+        for min_match in [3, 5, 8] {
+            let mut codes = Vec::new();
+            let mut encoder = DefaultLz77EncoderBuilder::new()
+                .min_match(min_match)
+                .build();
+            encoder.encode(text, &mut codes);
+            encoder.flush(&mut codes);
+
+            let mut shortest = u16::MAX;
+            let mut decoder = Lz77Decoder::new();
+            for code in codes {
+                if let Code::Pointer { length, .. } = code {
+                    shortest = shortest.min(length);
+                }
+                decoder.decode(code).unwrap();
+            }
+            assert_eq!(shortest, min_match);
+            assert_eq!(decoder.buffer(), &text[..]);
+        }
+    }
+
+    #[test]
+    fn set_min_match_works() {
+        fn shortest_match<E: Lz77Encode>(mut encoder: E, text: &[u8]) -> u16 {
+            encoder.set_min_match(6);
+            let mut codes = Vec::new();
+            encoder.encode(text, &mut codes);
+            encoder.flush(&mut codes);
+
+            let mut shortest = u16::MAX;
+            let mut decoder = Lz77Decoder::new();
+            for code in codes {
+                if let Code::Pointer { length, .. } = code {
+                    shortest = shortest.min(length);
+                }
+                decoder.decode(code).unwrap();
+            }
+            assert_eq!(decoder.buffer(), text);
+            shortest
+        }
+
+        let text = include_bytes!("../../README.md");
+        assert!(shortest_match(DefaultLz77Encoder::new(), text) >= 6);
+        assert!(shortest_match(OptimalLz77Encoder::new(), text) >= 6);
+    }
+
+    #[test]
+    fn zero_window_size_works() {
+        fn encode<E: Lz77Encode>(mut encoder: E, text: &[u8]) -> Vec<u8> {
+            let mut codes = Vec::new();
+            encoder.encode(text, &mut codes);
+            encoder.flush(&mut codes);
+
+            let mut decoder = Lz77Decoder::new();
+            for code in codes {
+                decoder.decode(code).unwrap();
+            }
+            decoder.buffer().to_vec()
+        }
+
+        let text = b"abcabcabcabcabcabcabc";
+        let encoder = DefaultLz77EncoderBuilder::new().window_size(0).build();
+        assert_eq!(encode(encoder, text), text);
+        assert_eq!(encode(OptimalLz77Encoder::with_window_size(0), text), text);
+    }
+
+    #[test]
+    fn decoder_with_window_works() {
+        const WINDOW: usize = 8 * 1024;
+
+        let text = (0..200_000u32)
+            .map(|i| (i % 251) as u8 ^ (i / 4096) as u8)
+            .collect::<Vec<_>>();
+        let mut codes = Vec::new();
+        let mut encoder = DefaultLz77EncoderBuilder::new()
+            .window_size(WINDOW as u16)
+            .build();
+        encoder.encode(&text, &mut codes);
+        encoder.flush(&mut codes);
+
+        for windowed in [false, true] {
+            let mut decoder = Lz77Decoder::with_window(WINDOW);
+            decoder.set_windowed(windowed);
+            let mut decoded = Vec::new();
+            let mut buf = [0; 100];
+            for code in codes.iter().copied() {
+                decoder.decode(code).unwrap();
+                while !decoder.buffer().is_empty() {
+                    let size = decoder.read(&mut buf).unwrap();
+                    decoded.extend_from_slice(&buf[..size]);
+                    assert!(decoder.buffer.len() <= WINDOW * 4 + usize::from(MAX_LENGTH));
+                }
+            }
+            assert_eq!(decoded, text);
+        }
+
+        let mut decoder = Lz77Decoder::with_window(WINDOW);
+        decoder.extend_from_slice(&text);
+        assert!(decoder
+            .decode(Code::Pointer {
+                length: 3,
+                backward_distance: WINDOW as u16,
+            })
+            .is_ok());
+        let e = decoder
+            .decode(Code::Pointer {
+                length: 3,
+                backward_distance: WINDOW as u16 + 1,
+            })
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn encode_borrowed_works() {
+        let text = include_bytes!("../../README.md").repeat(200);
+        for window_size in [1024, MAX_DISTANCE] {
+            let encode = |borrowed: bool| {
+                let mut codes: Vec<Code> = Vec::new();
+                let mut encoder = DefaultLz77EncoderBuilder::new()
+                    .window_size(window_size)
+                    .build();
+                for chunk in [&text[..10], &text[10..]] {
+                    if borrowed {
+                        encoder.encode_borrowed(chunk, &mut codes);
+                    } else {
+                        encoder.encode(chunk, &mut codes);
+                    }
+                    encoder.flush(&mut codes);
+                }
+                codes
+            };
+            assert_eq!(encode(true), encode(false));
+        }
+    }
+
     #[test]
     fn encoder_and_decoder_works() {
         let mut codes = Vec::new();
@@ -269,4 +594,60 @@ mod tests {
         assert_eq!(decoded, b"hello world!");
         assert!(decoder.buffer().is_empty());
     }
+
+    #[test]
+    fn generic_encoder_ignores_invalid_matches() {
+        struct Bogus;
+        impl MatchFinder for Bogus {
+            fn find_match(&self, _data: &[u8], pos: usize) -> Option<(u16, u16)> {
+                Some((3, pos as u16 + 1))
+            }
+        }
+
+        let mut codes = Vec::new();
+        let mut encoder = GenericLz77Encoder::new(Bogus);
+        encoder.encode(b"abcabc", &mut codes);
+        encoder.flush(&mut codes);
+        assert!(codes.iter().all(|c| matches!(c, Code::Literal(_))));
+
+        let mut decoder = Lz77Decoder::new();
+        for code in codes {
+            decoder.decode(code).unwrap();
+        }
+        assert_eq!(decoder.buffer(), b"abcabc");
+    }
+
+    #[test]
+    fn windowed_decoder_works() {
+        fn decode(windowed: bool) -> (Vec<u8>, usize) {
+            let mut decoder = Lz77Decoder::new();
+            decoder.set_windowed(windowed);
+            decoder.decode(Code::Literal(b'a')).unwrap();
+            decoder.decode(Code::Literal(b'b')).unwrap();
+
+            let mut decoded = Vec::new();
+            let mut peak_capacity = 0;
+            let mut buf = [0; 100];
+            for _ in 0..2000 {
+                let code = Code::Pointer {
+                    length: MAX_LENGTH,
+                    backward_distance: 2,
+                };
+                decoder.decode(code).unwrap();
+                peak_capacity = cmp::max(peak_capacity, decoder.buffer.capacity());
+
+                let size = decoder.read(&mut buf).unwrap();
+                decoded.extend_from_slice(&buf[..size]);
+                decoder.read_to_end(&mut decoded).unwrap();
+            }
+            (decoded, peak_capacity)
+        }
+
+        let (expected, peak_capacity) = decode(false);
+        assert!(peak_capacity > MAX_DISTANCE as usize * 4);
+
+        let (decoded, peak_capacity) = decode(true);
+        assert_eq!(decoded, expected);
+        assert!(peak_capacity <= MAX_DISTANCE as usize * 4);
+    }
 }
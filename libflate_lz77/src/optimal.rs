@@ -0,0 +1,363 @@
+use alloc::{vec, vec::Vec};
+use core::cmp;
+
+use super::buffer::InputBuffer;
+use super::Code;
+use super::Lz77Encode;
+use super::Sink;
+
+const HASH_BITS: usize = 15;
+const MAX_CHAIN: usize = 256;
+const ITERATIONS: usize = 2;
+const NO_POSITION: usize = usize::MAX;
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Costs are measured in 1/16 bits.
+const BIT: u32 = 16;
+
+/// A [`Lz77Encode`] implementation which searches a near-optimal parse of the input.
+///
+/// Instead of greedily taking the first match found at each position,
+/// this encoder collects the candidate matches of every position and
+/// chooses the sequence of literals and pointers that minimizes the estimated encoded size
+/// (a shortest path search over the match graph, as done by [zopfli]).
+/// The cost model is refined by re-parsing with the symbol statistics of the previous parse.
+///
+/// It produces smaller output than [`DefaultLz77Encoder`](crate::DefaultLz77Encoder)
+/// at the expense of much more CPU time,
+/// so it is suitable for data that is compressed once and decompressed many times.
+///
+/// [zopfli]: https://github.com/google/zopfli
+#[derive(Debug, Clone)]
+pub struct OptimalLz77Encoder {
+    window_size: u16,
+    min_match: u16,
+    input: InputBuffer,
+}
+
+impl OptimalLz77Encoder {
+    /// Makes a new encoder instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate;
+    /// use libflate::lz77::{self, Lz77Encode, OptimalLz77Encoder};
+    ///
+    /// let lz77 = OptimalLz77Encoder::new();
+    /// assert_eq!(lz77.window_size(), lz77::MAX_WINDOW_SIZE);
+    ///
+    /// let options = deflate::EncodeOptions::with_lz77(lz77);
+    /// let _deflate = deflate::Encoder::with_options(Vec::new(), options);
+    /// ```
+    pub fn new() -> Self {
+        Self::with_window_size(super::MAX_WINDOW_SIZE)
+    }
+
+    /// Makes a new encoder instance with specified window size.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::lz77::{Lz77Encode, OptimalLz77Encoder};
+    ///
+    /// let lz77 = OptimalLz77Encoder::with_window_size(1024);
+    /// assert_eq!(lz77.window_size(), 1024);
+    /// ```
+    pub fn with_window_size(size: u16) -> Self {
+        OptimalLz77Encoder {
+            window_size: cmp::min(size, super::MAX_WINDOW_SIZE),
+            min_match: 3,
+            input: InputBuffer::new(),
+        }
+    }
+}
+
+impl Default for OptimalLz77Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lz77Encode for OptimalLz77Encoder {
+    fn encode<S>(&mut self, mut buf: &[u8], mut sink: S)
+    where
+        S: Sink,
+    {
+        while let Some(rest) = self.input.fill(buf, self.window_size) {
+            self.flush(&mut sink);
+            buf = rest;
+        }
+    }
+    fn flush<S>(&mut self, mut sink: S)
+    where
+        S: Sink,
+    {
+        let buf = self.input.as_bytes();
+        let matches = Matches::find(buf, self.window_size as usize);
+        let mut costs = CostModel::fixed();
+        let mut codes = parse(
+            buf,
+            self.input.dictionary_len(),
+            self.min_match,
+            &matches,
+            &costs,
+        );
+        for _ in 1..ITERATIONS {
+            costs = CostModel::from_codes(&codes);
+            codes = parse(
+                buf,
+                self.input.dictionary_len(),
+                self.min_match,
+                &matches,
+                &costs,
+            );
+        }
+        for code in codes {
+            sink.consume(code);
+        }
+        self.input.clear();
+    }
+    fn set_dictionary(&mut self, dictionary: &[u8]) {
+        self.input.set_dictionary(dictionary, self.window_size);
+    }
+    fn set_min_match(&mut self, min_match: u16) {
+        self.min_match = min_match.clamp(3, super::MAX_LENGTH);
+    }
+    fn compression_level(&self) -> super::CompressionLevel {
+        super::CompressionLevel::Best
+    }
+    fn window_size(&self) -> u16 {
+        self.window_size
+    }
+}
+
+/// Candidate matches of each position.
+///
+/// For each position, `(length, distance)` pairs are stored in increasing order of length,
+/// where `distance` is the nearest one which shares at least `length` bytes.
+#[derive(Debug)]
+struct Matches {
+    starts: Vec<usize>,
+    entries: Vec<(u16, u16)>,
+}
+impl Matches {
+    fn find(buf: &[u8], window_size: usize) -> Self {
+        let mut head = vec![NO_POSITION; 1 << HASH_BITS];
+        let mut prev = vec![NO_POSITION; buf.len()];
+        let mut starts = Vec::with_capacity(buf.len() + 1);
+        let mut entries = Vec::new();
+        for i in 0..buf.len() {
+            starts.push(entries.len());
+            if i + 3 > buf.len() {
+                continue;
+            }
+            let max_length = cmp::min(super::MAX_LENGTH as usize, buf.len() - i);
+            let h = hash(&buf[i..]);
+            let mut best_length = 2;
+            let mut j = head[h];
+            let mut chain = 0;
+            while j != NO_POSITION && i - j <= window_size && chain < MAX_CHAIN {
+                let length = buf[i..i + max_length]
+                    .iter()
+                    .zip(&buf[j..])
+                    .take_while(|&(x, y)| x == y)
+                    .count();
+                if length > best_length {
+                    entries.push((length as u16, (i - j) as u16));
+                    best_length = length;
+                    if length == max_length {
+                        break;
+                    }
+                }
+                j = prev[j];
+                chain += 1;
+            }
+            prev[i] = head[h];
+            head[h] = i;
+        }
+        starts.push(entries.len());
+        Matches { starts, entries }
+    }
+    fn get(&self, i: usize) -> &[(u16, u16)] {
+        &self.entries[self.starts[i]..self.starts[i + 1]]
+    }
+}
+
+#[inline]
+fn hash(buf: &[u8]) -> usize {
+    let h = (usize::from(buf[0]) << 10) ^ (usize::from(buf[1]) << 5) ^ usize::from(buf[2]);
+    h & ((1 << HASH_BITS) - 1)
+}
+
+/// Estimated costs of literals and pointers.
+#[derive(Debug)]
+struct CostModel {
+    literals: [u32; 256],
+    lengths: [u32; 259],
+    distances: [u32; 30],
+}
+impl CostModel {
+    /// Makes a model based on the fixed Huffman codes of DEFLATE.
+    fn fixed() -> Self {
+        let mut literals = [0; 256];
+        for (b, cost) in literals.iter_mut().enumerate() {
+            *cost = if b < 144 { 8 * BIT } else { 9 * BIT };
+        }
+        let mut lengths = [0; 259];
+        for (length, cost) in lengths.iter_mut().enumerate().skip(3) {
+            let (code, extra) = length_code(length as u16);
+            let width = if 257 + code < 280 { 7 } else { 8 };
+            *cost = (width + u32::from(extra)) * BIT;
+        }
+        let mut distances = [0; 30];
+        for (code, cost) in distances.iter_mut().enumerate() {
+            *cost = (5 + u32::from(DISTANCE_EXTRA_BITS[code])) * BIT;
+        }
+        CostModel {
+            literals,
+            lengths,
+            distances,
+        }
+    }
+
+    /// Makes a model based on the symbol statistics of `codes`.
+    fn from_codes(codes: &[Code]) -> Self {
+        let mut literal_counts = [1; 286];
+        let mut distance_counts = [1; 30];
+        for code in codes {
+            match *code {
+                Code::Literal(b) => literal_counts[usize::from(b)] += 1,
+                Code::Pointer {
+                    length,
+                    backward_distance,
+                } => {
+                    literal_counts[257 + length_code(length).0] += 1;
+                    distance_counts[distance_code(backward_distance).0] += 1;
+                }
+            }
+        }
+        let literal_costs = entropies(&literal_counts);
+        let distance_costs = entropies(&distance_counts);
+
+        let mut literals = [0; 256];
+        literals.copy_from_slice(&literal_costs[..256]);
+        let mut lengths = [0; 259];
+        for (length, cost) in lengths.iter_mut().enumerate().skip(3) {
+            let (code, extra) = length_code(length as u16);
+            *cost = literal_costs[257 + code] + u32::from(extra) * BIT;
+        }
+        let mut distances = [0; 30];
+        for (code, cost) in distances.iter_mut().enumerate() {
+            *cost = distance_costs[code] + u32::from(DISTANCE_EXTRA_BITS[code]) * BIT;
+        }
+        CostModel {
+            literals,
+            lengths,
+            distances,
+        }
+    }
+
+    #[inline]
+    fn pointer(&self, length: u16, distance: u16) -> u32 {
+        self.lengths[length as usize] + self.distances[distance_code(distance).0]
+    }
+}
+
+/// Returns the estimated bit width (in 1/16 bits) of each symbol.
+fn entropies(counts: &[u32]) -> Vec<u32> {
+    let total = counts.iter().sum::<u32>();
+    let log2_total = log2(total);
+    counts
+        .iter()
+        .map(|&c| cmp::max(BIT, log2_total - log2(c)))
+        .collect()
+}
+
+/// Approximates `log2(x)` in 1/16 bits.
+fn log2(x: u32) -> u32 {
+    debug_assert!(x > 0);
+    let integer = 31 - x.leading_zeros();
+    let fraction = if integer >= 4 {
+        (x >> (integer - 4)) & 0xF
+    } else {
+        (x << (4 - integer)) & 0xF
+    };
+    integer * BIT + fraction
+}
+
+fn length_code(length: u16) -> (usize, u8) {
+    let code = LENGTH_BASES.partition_point(|&base| base <= length) - 1;
+    (code, LENGTH_EXTRA_BITS[code])
+}
+
+fn distance_code(distance: u16) -> (usize, u8) {
+    let code = DISTANCE_BASES.partition_point(|&base| base <= distance) - 1;
+    (code, DISTANCE_EXTRA_BITS[code])
+}
+
+/// Finds the cheapest sequence of codes representing `buf[start..]` under the cost model.
+///
+/// Matches shorter than `min_match` are not considered.
+fn parse(
+    buf: &[u8],
+    start: usize,
+    min_match: u16,
+    matches: &Matches,
+    costs: &CostModel,
+) -> Vec<Code> {
+    let mut total_costs = vec![u32::MAX; buf.len() + 1];
+    let mut choices = vec![(0, 0); buf.len() + 1];
+    total_costs[start] = 0;
+    for i in start..buf.len() {
+        let base = total_costs[i];
+        let literal = base + costs.literals[usize::from(buf[i])];
+        if literal < total_costs[i + 1] {
+            total_costs[i + 1] = literal;
+            choices[i + 1] = (1, 0);
+        }
+
+        let mut length = min_match;
+        for &(max_length, distance) in matches.get(i) {
+            while length <= max_length {
+                let cost = base + costs.pointer(length, distance);
+                let j = i + length as usize;
+                if cost < total_costs[j] {
+                    total_costs[j] = cost;
+                    choices[j] = (length, distance);
+                }
+                length += 1;
+            }
+        }
+    }
+
+    let mut codes = Vec::new();
+    let mut i = buf.len();
+    while i > start {
+        let (length, distance) = choices[i];
+        if distance == 0 {
+            codes.push(Code::Literal(buf[i - 1]));
+        } else {
+            codes.push(Code::Pointer {
+                length,
+                backward_distance: distance,
+            });
+        }
+        i -= length as usize;
+    }
+    codes.reverse();
+    codes
+}
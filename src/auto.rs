@@ -0,0 +1,333 @@
+use alloc::vec::Vec;
+use core2::io::{self, Read, Write};
+
+use crate::checksum;
+use crate::deflate;
+use crate::gzip;
+use crate::lz77;
+use crate::zlib;
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+#[derive(Debug, Clone, Copy)]
+enum Strategy {
+    Dynamic,
+    Fixed,
+    Stored,
+}
+
+const STRATEGIES: [Strategy; 3] = [Strategy::Dynamic, Strategy::Fixed, Strategy::Stored];
+
+/// Format of the data compressed by [`compress_best`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// ZLIB format (RFC 1950).
+    Zlib,
+
+    /// GZIP format (RFC 1952).
+    Gzip,
+}
+
+/// Compresses `data` into the smallest of the candidate encodings, returning it along with its format.
+///
+/// The DEFLATE data is encoded with dynamic Huffman codes, fixed Huffman codes and no compression,
+/// and the smallest one is framed as a ZLIB stream.
+/// The ZLIB framing (6 bytes) is always smaller than the GZIP one (at least 18 bytes) for the same DEFLATE data,
+/// so [`Format::Gzip`] is never chosen in practice.
+/// The result carries its format in its own header,
+/// so it can be decompressed by [`decompress_auto`].
+///
+/// Raw DEFLATE streams are not considered because their format cannot be detected from the data.
+///
+/// # Examples
+/// ```
+/// use libflate::Format;
+///
+/// let data = b"Hello World! Hello World!";
+/// let (format, compressed) = libflate::compress_best(data);
+/// assert_eq!(format, Format::Zlib);
+/// assert_eq!(libflate::decompress_auto(&compressed).unwrap(), data);
+/// ```
+pub fn compress_best(data: &[u8]) -> (Format, Vec<u8>) {
+    let (header, body) = STRATEGIES
+        .iter()
+        .map(|&strategy| {
+            let (header, options) = zlib_options(strategy).into_parts();
+            let mut encoder = deflate::Encoder::with_options(Vec::new(), options);
+            encoder
+                .write_all(data)
+                .expect("Writing to a `Vec` never fails");
+            let body = encoder
+                .finish()
+                .into_result()
+                .expect("Writing to a `Vec` never fails");
+            (header, body)
+        })
+        .min_by_key(|(_, body)| body.len())
+        .expect("There is at least one candidate");
+
+    let mut compressed = Vec::with_capacity(2 + body.len() + 4);
+    header
+        .write_to(&mut compressed)
+        .expect("Writing to a `Vec` never fails");
+    compressed.extend_from_slice(&body);
+    let mut adler32 = checksum::Adler32::new();
+    adler32.update(data);
+    compressed.extend_from_slice(&adler32.value().to_be_bytes());
+    (Format::Zlib, compressed)
+}
+
+/// Decompresses `data` produced by [`compress_best`] (or any other ZLIB or GZIP encoder).
+///
+/// The format is detected from the header of `data`:
+/// data starting with the GZIP magic number is decoded as a GZIP stream,
+/// and data starting with a valid ZLIB header is decoded as a ZLIB stream.
+/// Otherwise an `InvalidData` error is returned.
+pub fn decompress_auto(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    if data.starts_with(&GZIP_MAGIC) {
+        gzip::Decoder::new(data)?.read_to_end(&mut decoded)?;
+    } else if is_zlib_header(data) {
+        zlib::Decoder::new(data)?.read_to_end(&mut decoded)?;
+    } else {
+        return Err(invalid_data_error!("Unknown compression format"));
+    }
+    Ok(decoded)
+}
+
+/// Decoder which accepts both raw DEFLATE streams and ZLIB streams.
+///
+/// The format is detected from the first two bytes of the input:
+/// if they form a valid ZLIB header (i.e., the compression method is 8 and the check bits are consistent),
+/// the input is decoded as a ZLIB stream, otherwise as a raw DEFLATE stream.
+///
+/// Note that the detection is a heuristic, since a raw DEFLATE stream may happen to start
+/// with bytes that look like a ZLIB header (this is very unlikely for real encoders' outputs).
+///
+/// # Examples
+/// ```
+/// use core2::io::{Read, Write};
+/// use libflate::{deflate, zlib, DeflateOrZlibDecoder};
+///
+/// let mut encoder = deflate::Encoder::new(Vec::new());
+/// encoder.write_all(b"Hello World!").unwrap();
+/// let raw = encoder.finish().into_result().unwrap();
+///
+/// let mut encoder = zlib::Encoder::new(Vec::new()).unwrap();
+/// encoder.write_all(b"Hello World!").unwrap();
+/// let wrapped = encoder.finish().into_result().unwrap();
+///
+/// for (encoded_data, is_zlib) in [(raw, false), (wrapped, true)] {
+///     let mut decoder = DeflateOrZlibDecoder::new(&encoded_data[..]).unwrap();
+///     assert_eq!(decoder.is_zlib(), is_zlib);
+///
+///     let mut decoded_data = Vec::new();
+///     decoder.read_to_end(&mut decoded_data).unwrap();
+///     assert_eq!(decoded_data, b"Hello World!");
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DeflateOrZlibDecoder<R> {
+    inner: FormatDecoder<R>,
+}
+impl<R> DeflateOrZlibDecoder<R>
+where
+    R: Read,
+{
+    /// Makes a new decoder instance.
+    ///
+    /// The first two bytes of `inner` are read to detect the format.
+    /// If the input is a ZLIB stream, its header is also read (and validated) here.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut peeked = [0; 2];
+        let mut len = 0;
+        while len < peeked.len() {
+            match inner.read(&mut peeked[len..]) {
+                Ok(0) => break,
+                Ok(size) => len += size,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let reader = PeekedReader {
+            peeked,
+            offset: 0,
+            len,
+            inner,
+        };
+        let inner = if is_zlib_header(&peeked[..len]) {
+            FormatDecoder::Zlib(zlib::Decoder::new(reader)?)
+        } else {
+            FormatDecoder::Deflate(deflate::Decoder::new(reader))
+        };
+        Ok(DeflateOrZlibDecoder { inner })
+    }
+
+    /// Returns `true` if the input has been detected as a ZLIB stream, otherwise `false`.
+    pub fn is_zlib(&self) -> bool {
+        matches!(self.inner, FormatDecoder::Zlib(_))
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        match self.inner {
+            FormatDecoder::Deflate(ref d) => &d.as_inner_ref().inner,
+            FormatDecoder::Zlib(ref d) => &d.as_inner_ref().inner,
+        }
+    }
+
+    /// Unwraps this `DeflateOrZlibDecoder`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        match self.inner {
+            FormatDecoder::Deflate(d) => d.into_inner().inner,
+            FormatDecoder::Zlib(d) => d.into_inner().inner,
+        }
+    }
+}
+impl<R> Read for DeflateOrZlibDecoder<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner {
+            FormatDecoder::Deflate(ref mut d) => d.read(buf),
+            FormatDecoder::Zlib(ref mut d) => d.read(buf),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum FormatDecoder<R> {
+    Deflate(deflate::Decoder<PeekedReader<R>>),
+    Zlib(zlib::Decoder<PeekedReader<R>>),
+}
+
+/// Reader which yields the bytes peeked for the format detection before the rest of the input.
+#[derive(Debug)]
+struct PeekedReader<R> {
+    peeked: [u8; 2],
+    offset: usize,
+    len: usize,
+    inner: R,
+}
+impl<R> Read for PeekedReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset == self.len {
+            return self.inner.read(buf);
+        }
+        let size = core::cmp::min(buf.len(), self.len - self.offset);
+        buf[..size].copy_from_slice(&self.peeked[self.offset..self.offset + size]);
+        self.offset += size;
+        Ok(size)
+    }
+}
+
+fn is_zlib_header(data: &[u8]) -> bool {
+    match *data {
+        [cmf, flg, ..] => cmf & 0b1111 == 8 && ((u16::from(cmf) << 8) + u16::from(flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+fn zlib_options(strategy: Strategy) -> zlib::EncodeOptions<lz77::DefaultLz77Encoder> {
+    match strategy {
+        Strategy::Dynamic => zlib::EncodeOptions::new(),
+        Strategy::Fixed => zlib::EncodeOptions::new().fixed_huffman_codes(),
+        Strategy::Stored => zlib::EncodeOptions::new().no_compression(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::pseudo_random_bytes;
+
+    fn gzip_encode(data: &[u8], strategy: Strategy) -> io::Result<Vec<u8>> {
+        let options = match strategy {
+            Strategy::Dynamic => gzip::EncodeOptions::new(),
+            Strategy::Fixed => gzip::EncodeOptions::new().fixed_huffman_codes(),
+            Strategy::Stored => gzip::EncodeOptions::new().no_compression(),
+        };
+        let mut encoder = gzip::Encoder::with_options(Vec::new(), options)?;
+        encoder.write_all(data)?;
+        encoder.finish().into_result()
+    }
+
+    fn zlib_encode(data: &[u8], strategy: Strategy) -> io::Result<Vec<u8>> {
+        let mut encoder = zlib::Encoder::with_options(Vec::new(), zlib_options(strategy))?;
+        encoder.write_all(data)?;
+        encoder.finish().into_result()
+    }
+
+    #[test]
+    fn compress_best_works() {
+        let inputs: [Vec<u8>; 4] = [
+            Vec::new(),
+            b"a".to_vec(),
+            include_bytes!("../README.md").to_vec(),
+            pseudo_random_bytes(10000),
+        ];
+        for input in &inputs {
+            let (format, compressed) = compress_best(input);
+            assert_eq!(format, Format::Zlib);
+            for &strategy in &STRATEGIES {
+                assert!(compressed.len() <= zlib_encode(input, strategy).unwrap().len());
+                assert!(compressed.len() <= gzip_encode(input, strategy).unwrap().len());
+            }
+            assert_eq!(decompress_auto(&compressed).unwrap(), *input);
+        }
+
+        let gzipped = gzip_encode(b"foo", Strategy::Fixed).unwrap();
+        assert_eq!(decompress_auto(&gzipped).unwrap(), b"foo");
+
+        assert!(decompress_auto(&[]).is_err());
+        assert!(decompress_auto(&[0; 10]).is_err());
+    }
+
+    #[test]
+    fn deflate_or_zlib_decoder_works() {
+        /// A reader which yields at most one byte at a time.
+        struct OneByteReader<'a>(&'a [u8]);
+        impl Read for OneByteReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let size = core::cmp::min(1, core::cmp::min(buf.len(), self.0.len()));
+                buf[..size].copy_from_slice(&self.0[..size]);
+                self.0 = &self.0[size..];
+                Ok(size)
+            }
+        }
+
+        let inputs: [&[u8]; 3] = [b"", b"a", include_bytes!("../README.md")];
+        for input in inputs {
+            for &strategy in &STRATEGIES {
+                let mut encoder = deflate::Encoder::with_options(
+                    Vec::new(),
+                    match strategy {
+                        Strategy::Dynamic => deflate::EncodeOptions::new(),
+                        Strategy::Fixed => deflate::EncodeOptions::new().fixed_huffman_codes(),
+                        Strategy::Stored => deflate::EncodeOptions::new().no_compression(),
+                    },
+                );
+                encoder.write_all(input).unwrap();
+                let raw = encoder.finish().into_result().unwrap();
+                let wrapped = zlib_encode(input, strategy).unwrap();
+
+                for (encoded, is_zlib) in [(raw, false), (wrapped, true)] {
+                    let mut decoder = DeflateOrZlibDecoder::new(OneByteReader(&encoded)).unwrap();
+                    assert_eq!(decoder.is_zlib(), is_zlib);
+                    let mut decoded = Vec::new();
+                    decoder.read_to_end(&mut decoded).unwrap();
+                    assert_eq!(decoded, input);
+                }
+            }
+        }
+
+        let mut decoder = DeflateOrZlibDecoder::new(&[][..]).unwrap();
+        assert!(!decoder.is_zlib());
+        assert!(decoder.read_to_end(&mut Vec::new()).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use alloc::vec::Vec;
 use core2::io;
 
 #[derive(Debug)]
@@ -5,6 +6,13 @@ pub struct BitWriter<W> {
     inner: W,
     buf: u32,
     end: u8,
+
+    // The bytes which could not be written because the inner writer was full (or would block).
+    // Callers are expected to stop producing output until these are written by `write_pending`
+    // (e.g., `deflate::Encoder` flushes at most one block per `write` call).
+    pending: Vec<u8>,
+
+    bytes_written: u64,
 }
 impl<W> BitWriter<W>
 where
@@ -15,6 +23,8 @@ where
             inner,
             buf: 0,
             end: 0,
+            pending: Vec::new(),
+            bytes_written: 0,
         }
     }
     #[inline(always)]
@@ -29,19 +39,81 @@ where
         self.end += bitwidth;
         self.flush_if_needed()
     }
+    /// Writes the buffered bits which form whole bytes, leaving the remaining (less than 8) bits buffered.
+    pub fn flush_whole_bytes(&mut self) -> io::Result<()> {
+        while self.end >= 8 {
+            self.write_bytes(&[self.buf as u8])?;
+            self.buf >>= 8;
+            self.end -= 8;
+        }
+        Ok(())
+    }
     pub fn flush(&mut self) -> io::Result<()> {
         while self.end > 0 {
-            self.inner.write_all(&[self.buf as u8])?;
+            self.write_bytes(&[self.buf as u8])?;
             self.buf >>= 8;
             self.end = self.end.saturating_sub(8);
         }
         self.inner.flush()?;
         Ok(())
     }
+
+    /// Writes `buf` to the inner writer.
+    ///
+    /// If the inner writer is full (i.e., it returns `Ok(0)`, a `WriteZero` error or a `WouldBlock` error),
+    /// the unwritten bytes are kept as pending so that no output is lost.
+    pub fn write_bytes(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() && self.pending.is_empty() {
+            match self.inner.write(buf) {
+                Ok(0) => break,
+                Ok(size) => {
+                    buf = &buf[size..];
+                    self.bytes_written += size as u64;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WriteZero => break,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.pending.extend_from_slice(buf);
+        Ok(())
+    }
+
+    /// Tries to write the pending bytes to the inner writer.
+    ///
+    /// If some bytes are still pending, a `WriteZero` error (or the `WouldBlock` error of the inner writer) is returned.
+    pub fn write_pending(&mut self) -> io::Result<()> {
+        let mut offset = 0;
+        while offset < self.pending.len() {
+            match self.inner.write(&self.pending[offset..]) {
+                Ok(0) => break,
+                Ok(size) => {
+                    offset += size;
+                    self.bytes_written += size as u64;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WriteZero => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.pending.drain(..offset);
+                    return Err(e);
+                }
+            }
+        }
+        self.pending.drain(..offset);
+        if self.pending.is_empty() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "The inner writer is full",
+            ))
+        }
+    }
     #[inline(always)]
     fn flush_if_needed(&mut self) -> io::Result<()> {
         if self.end >= 16 {
-            self.inner.write_all(&(self.buf as u16).to_le_bytes())?;
+            self.write_bytes(&(self.buf as u16).to_le_bytes())?;
             self.end -= 16;
             self.buf >>= 16;
         }
@@ -49,6 +121,13 @@ where
     }
 }
 impl<W> BitWriter<W> {
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+    /// Returns the number of bytes actually written to the inner writer (excluding the pending ones).
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
     pub fn as_inner_ref(&self) -> &W {
         &self.inner
     }
@@ -65,7 +144,9 @@ pub struct BitReader<R> {
     inner: R,
     last_read: u32,
     offset: u8,
+    padding: u8,
     last_error: Option<io::Error>,
+    bytes_read: u64,
 }
 impl<R> BitReader<R>
 where
@@ -76,7 +157,9 @@ where
             inner,
             last_read: 0,
             offset: 32,
+            padding: 0,
             last_error: None,
+            bytes_read: 0,
         }
     }
 
@@ -114,9 +197,26 @@ where
             if self.last_error.is_some() {
                 return 0;
             }
-            if let Err(e) = self.fill_next_u8() {
-                self.last_error = Some(e);
-                return 0;
+            // Once the end of the inner reader has been reached, it is never read again
+            // so that a reader which returns `Ok(0)` spuriously cannot interleave data after the padding
+            let result = if self.padding > 0 {
+                self.offset -= 8;
+                self.last_read >>= 8;
+                Err(io::Error::from(io::ErrorKind::UnexpectedEof))
+            } else {
+                self.fill_next_u8()
+            };
+            match result {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && self.padding < 32 => {
+                    // Zero-fills the missing bits, as they may not be needed to decode the last code.
+                    // Consuming them is reported as an error by `skip_bits`.
+                    self.padding += 8;
+                }
+                Err(e) => {
+                    self.last_error = Some(e);
+                    return 0;
+                }
             }
         }
         debug_assert!(self.offset < 32 || bitwidth == 0);
@@ -127,6 +227,13 @@ where
     pub fn skip_bits(&mut self, bitwidth: u8) {
         debug_assert!(self.last_error.is_some() || 32 - self.offset >= bitwidth);
         self.offset += bitwidth;
+        if self.padding > 0 && self.offset > 32 - self.padding && self.last_error.is_none() {
+            self.last_error = Some(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+    }
+    /// Skips the unconsumed bits of the current byte, if any.
+    pub fn skip_to_byte_boundary(&mut self) {
+        self.skip_bits((32 - self.offset) % 8);
     }
     #[inline(always)]
     fn fill_next_u8(&mut self) -> io::Result<()> {
@@ -135,26 +242,63 @@ where
 
         let mut buf = [0; 1];
         self.inner.read_exact(&mut buf)?;
+        self.bytes_read += 1;
         let next = u32::from(buf[0]);
         self.last_read |= next << (32 - 8);
         Ok(())
     }
+    /// Returns `true` if the end of the inner reader has been reached and
+    /// no whole byte remains unconsumed (i.e., only the padding bits of the current byte may remain).
+    pub fn is_at_end_of_input(&mut self) -> io::Result<bool> {
+        let partial_bits = (32 - self.offset) % 8;
+        self.peek_bits_unchecked(partial_bits + 8);
+        self.check_last_error()?;
+        Ok(self.reached_eof() && (32 - self.offset).saturating_sub(self.padding) < 8)
+    }
     #[inline]
     pub(crate) fn state(&self) -> BitReaderState {
         BitReaderState {
             last_read: self.last_read,
             offset: self.offset,
+            padding: self.padding,
+            bytes_read: self.bytes_read,
         }
     }
     #[inline]
     pub(crate) fn restore_state(&mut self, state: BitReaderState) {
         self.last_read = state.last_read;
         self.offset = state.offset;
+        self.padding = state.padding;
+        self.bytes_read = state.bytes_read;
     }
 }
 impl<R> BitReader<R> {
+    /// Returns the bytes which have been read from the inner reader but none of whose bits have been consumed yet.
+    pub fn buffered_bytes(&self) -> Vec<u8> {
+        let start = (u32::from(self.offset) + 7) / 8;
+        let end = (32 - u32::from(self.padding)) / 8;
+        (start..end)
+            .map(|i| (self.last_read >> (i * 8)) as u8)
+            .collect()
+    }
     pub fn reset(&mut self) {
         self.offset = 32;
+        self.padding = 0;
+    }
+    /// Returns `true` if the end of the inner reader has been reached (and the missing bits are zero-filled).
+    pub fn reached_eof(&self) -> bool {
+        self.padding > 0
+    }
+    /// Returns the number of bytes which have been read from the inner reader.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+    /// Records that `size` bytes have been read directly from the inner reader (i.e., via `as_inner_mut`).
+    pub fn add_bytes_read(&mut self, size: u64) {
+        self.bytes_read += size;
+    }
+    pub fn reset_bytes_read(&mut self) {
+        self.bytes_read = 0;
     }
     pub fn as_inner_ref(&self) -> &R {
         &self.inner
@@ -171,6 +315,8 @@ impl<R> BitReader<R> {
 pub(crate) struct BitReaderState {
     last_read: u32,
     offset: u8,
+    padding: u8,
+    bytes_read: u64,
 }
 
 #[cfg(test)]
@@ -194,6 +340,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn reader_works() {
         let buf = [0b10100101, 0b11010101];
         let mut reader = BitReader::new(&buf[..]);
@@ -209,4 +356,54 @@ mod tests {
             Err(io::ErrorKind::UnexpectedEof)
         );
     }
+
+    #[test]
+    fn reader_zero_fills_bits_beyond_eof() {
+        let buf = [0b1010_0101];
+        let mut reader = BitReader::new(&buf[..]);
+        assert_eq!(reader.read_bits(6).unwrap(), 0b10_0101);
+        assert_eq!(reader.peek_bits_unchecked(9), 0b10);
+        reader.skip_bits(2);
+        assert!(reader.check_last_error().is_ok());
+
+        reader.skip_bits(1);
+        assert_eq!(
+            reader.check_last_error().map_err(|e| e.kind()),
+            Err(io::ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn buffered_bytes_works() {
+        let buf = [1, 2, 3, 4];
+        let mut reader = BitReader::new(&buf[..]);
+        assert!(reader.buffered_bytes().is_empty());
+
+        assert_eq!(reader.read_bits(4).unwrap(), 1);
+        assert_eq!(reader.peek_bits_unchecked(12), 0x20);
+        assert_eq!(reader.buffered_bytes(), [2]);
+
+        reader.skip_bits(5);
+        assert!(reader.buffered_bytes().is_empty());
+        assert_eq!(reader.into_inner(), [3, 4]);
+    }
+
+    #[test]
+    fn reader_does_not_read_beyond_eof() {
+        let mut reader = BitReader::new(io::Cursor::new(Vec::from([0xAB])));
+        assert_eq!(reader.peek_bits_unchecked(12), 0xAB);
+        reader.skip_bits(4);
+
+        // The inner reader returned `Ok(0)` above, but more data arrives later
+        reader.as_inner_mut().get_mut().extend([0xCD, 0xEF]);
+        assert_eq!(reader.peek_bits_unchecked(15), 0xA);
+        reader.skip_bits(4);
+        assert!(reader.check_last_error().is_ok());
+
+        // The zero-filled bits must not be followed by the data read after the (spurious) end
+        assert_eq!(
+            reader.read_bits(8).map_err(|e| e.kind()),
+            Err(io::ErrorKind::UnexpectedEof)
+        );
+    }
 }
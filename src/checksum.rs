@@ -1,16 +1,98 @@
-use adler32::RollingAdler32;
+//! Checksum algorithms used by the ZLIB and GZIP formats.
 use core::fmt;
+use core2::io;
 
-pub struct Adler32(RollingAdler32);
+/// Behavior of a decoder when the checksum of the decoded data does not match the expected value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumMismatchPolicy {
+    /// Returns an `InvalidData` error (default).
+    #[default]
+    Error,
+
+    /// Ignores the mismatch.
+    ///
+    /// The result of the verification can be retrieved via the `checksum_ok` method of the decoder.
+    Ignore,
+}
+
+/// The `Checksum` trait allows for computing a checksum of a byte stream incrementally.
+pub trait Checksum {
+    /// Updates the checksum with `buf`.
+    fn update(&mut self, buf: &[u8]);
+
+    /// Returns the checksum of the bytes given so far.
+    fn value(&self) -> u32;
+}
+
+/// Adler-32 checksum used by the ZLIB format.
+///
+/// # Examples
+/// ```
+/// use libflate::checksum::Adler32;
+///
+/// let mut adler32 = Adler32::new();
+/// adler32.update(b"abcde");
+/// assert_eq!(adler32.value(), 0x05C801F0);
+/// ```
+/// Adler-32 checksum used by the ZLIB format.
+///
+/// # Examples
+/// ```
+/// use libflate::checksum::Adler32;
+///
+/// let mut adler32 = Adler32::new();
+/// adler32.update(b"abcde");
+/// assert_eq!(adler32.value(), 0x05C801F0);
+/// ```
+pub struct Adler32(simd_adler32::Adler32);
 impl Adler32 {
+    /// Makes a new instance.
     pub fn new() -> Self {
-        Adler32(RollingAdler32::new())
+        Adler32(simd_adler32::Adler32::new())
     }
+    /// Returns the checksum of the bytes given so far.
     pub fn value(&self) -> u32 {
-        self.0.hash()
+        self.0.finish()
     }
+    /// Updates the checksum with `buf`.
     pub fn update(&mut self, buf: &[u8]) {
-        self.0.update_buffer(buf);
+        self.0.write(buf);
+    }
+    /// Returns the running state of the checksum, which can be persisted and resumed by [`Adler32::from_state`].
+    ///
+    /// For Adler-32, the state is equal to the checksum of the bytes given so far.
+    pub fn state(&self) -> u32 {
+        self.value()
+    }
+    /// Makes a new instance which resumes from `state` returned by [`Adler32::state`].
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::checksum::Adler32;
+    ///
+    /// let mut adler32 = Adler32::new();
+    /// adler32.update(b"ab");
+    /// let state = adler32.state();
+    ///
+    /// let mut adler32 = Adler32::from_state(state);
+    /// adler32.update(b"cde");
+    /// assert_eq!(adler32.value(), 0x05C801F0);
+    /// ```
+    pub fn from_state(state: u32) -> Self {
+        Adler32(simd_adler32::Adler32::from_checksum(state))
+    }
+}
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Checksum for Adler32 {
+    fn update(&mut self, buf: &[u8]) {
+        self.update(buf);
+    }
+    fn value(&self) -> u32 {
+        self.value()
     }
 }
 impl fmt::Debug for Adler32 {
@@ -19,17 +101,75 @@ impl fmt::Debug for Adler32 {
     }
 }
 
+/// CRC-32 checksum used by the GZIP format.
+///
+/// # Examples
+/// ```
+/// use libflate::checksum::Crc32;
+///
+/// let mut crc32 = Crc32::new();
+/// crc32.update(b"abcde");
+/// assert_eq!(crc32.value(), 0x8587D865);
+/// ```
 pub struct Crc32(crc32fast::Hasher);
 impl Crc32 {
+    /// Makes a new instance.
     pub fn new() -> Self {
         Crc32(crc32fast::Hasher::new())
     }
+    /// Returns the checksum of the bytes given so far.
     pub fn value(&self) -> u32 {
         self.0.clone().finalize()
     }
+    /// Updates the checksum with `buf`.
     pub fn update(&mut self, buf: &[u8]) {
         self.0.update(buf);
     }
+    /// Updates the checksum as if the bytes whose CRC-32 is `crc32` and length is `len` were given.
+    pub fn combine(&mut self, crc32: u32, len: u64) {
+        self.0
+            .combine(&crc32fast::Hasher::new_with_initial_len(crc32, len));
+    }
+    /// Returns the running state of the checksum, which can be persisted and resumed by [`Crc32::from_state`].
+    ///
+    /// For CRC-32, the state is equal to the checksum of the bytes given so far
+    /// (the length of the bytes is not included).
+    pub fn state(&self) -> u32 {
+        self.value()
+    }
+    /// Makes a new instance which resumes from `state` returned by [`Crc32::state`].
+    ///
+    /// Note that the length of the bytes given before the state was taken is not restored,
+    /// so it must be tracked separately if needed (e.g., for the ISIZE field of GZIP).
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::checksum::Crc32;
+    ///
+    /// let mut crc32 = Crc32::new();
+    /// crc32.update(b"ab");
+    /// let state = crc32.state();
+    ///
+    /// let mut crc32 = Crc32::from_state(state);
+    /// crc32.update(b"cde");
+    /// assert_eq!(crc32.value(), 0x8587D865);
+    /// ```
+    pub fn from_state(state: u32) -> Self {
+        Crc32(crc32fast::Hasher::new_with_initial(state))
+    }
+}
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Checksum for Crc32 {
+    fn update(&mut self, buf: &[u8]) {
+        self.update(buf);
+    }
+    fn value(&self) -> u32 {
+        self.value()
+    }
 }
 impl fmt::Debug for Crc32 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -37,6 +177,81 @@ impl fmt::Debug for Crc32 {
     }
 }
 
+/// A reader which updates a checksum with the bytes read through it.
+///
+/// # Examples
+/// ```
+/// use core2::io::Read;
+/// use libflate::checksum::{Crc32, TeeReader};
+/// use libflate::gzip::{Decoder, Encoder};
+///
+/// # use core2::io::Write;
+/// # let mut encoder = Encoder::new(Vec::new()).unwrap();
+/// # encoder.write_all(b"Hello World!").unwrap();
+/// # let encoded_data = encoder.finish().into_result().unwrap();
+/// let decoder = Decoder::new(&encoded_data[..]).unwrap();
+/// let mut reader = TeeReader::new(decoder, Crc32::new());
+/// let mut decoded_data = Vec::new();
+/// reader.read_to_end(&mut decoded_data).unwrap();
+///
+/// let mut crc32 = Crc32::new();
+/// crc32.update(&decoded_data);
+/// assert_eq!(reader.value(), crc32.value());
+/// ```
+#[derive(Debug)]
+pub struct TeeReader<R, C> {
+    inner: R,
+    checksum: C,
+}
+impl<R, C> TeeReader<R, C>
+where
+    R: io::Read,
+    C: Checksum,
+{
+    /// Makes a new instance which reads from `inner` and updates `checksum`.
+    pub fn new(inner: R, checksum: C) -> Self {
+        TeeReader { inner, checksum }
+    }
+
+    /// Returns the checksum of the bytes read so far.
+    pub fn value(&self) -> u32 {
+        self.checksum.value()
+    }
+
+    /// Returns the reference to the checksum.
+    pub fn checksum(&self) -> &C {
+        &self.checksum
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    ///
+    /// Note that the bytes read directly from the inner stream are not reflected in the checksum.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `TeeReader`, returning the underlying reader and checksum.
+    pub fn into_inner(self) -> (R, C) {
+        (self.inner, self.checksum)
+    }
+}
+impl<R, C> io::Read for TeeReader<R, C>
+where
+    R: io::Read,
+    C: Checksum,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.checksum.update(&buf[..size]);
+        Ok(size)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -48,10 +263,69 @@ mod tests {
         assert_eq!(crc32.value(), 0x8587D865);
     }
 
+    #[test]
+    fn crc32_combine_works() {
+        let mut crc32 = Crc32::new();
+        crc32.update(b"ab");
+        crc32.combine(0x8999971F, 3); // CRC32 of "cde"
+        assert_eq!(crc32.value(), 0x8587D865);
+    }
+
     #[test]
     fn adler32_works() {
         let mut adler32 = Adler32::new();
         adler32.update(b"abcde");
         assert_eq!(adler32.value(), 0x05C801F0);
     }
+
+    #[test]
+    fn resuming_from_state_works() {
+        let data = (0..100_000u32)
+            .map(|i| (i % 253) as u8)
+            .collect::<alloc::vec::Vec<_>>();
+        for split in [0, 1, 5551, 5552, 77_777, data.len()] {
+            let mut crc32 = Crc32::new();
+            let mut adler32 = Adler32::new();
+            crc32.update(&data[..split]);
+            adler32.update(&data[..split]);
+
+            let mut crc32 = Crc32::from_state(crc32.state());
+            let mut adler32 = Adler32::from_state(adler32.state());
+            crc32.update(&data[split..]);
+            adler32.update(&data[split..]);
+
+            let mut expected_crc32 = Crc32::new();
+            let mut expected_adler32 = Adler32::new();
+            expected_crc32.update(&data);
+            expected_adler32.update(&data);
+            assert_eq!(crc32.value(), expected_crc32.value());
+            assert_eq!(adler32.value(), expected_adler32.value());
+        }
+    }
+
+    #[test]
+    fn checksums_of_large_input_match_scalar_implementations() {
+        let data = crate::util::pseudo_random_bytes(3_000_000);
+
+        let mut adler32 = Adler32::new();
+        let mut crc32 = Crc32::new();
+        for chunk in data.chunks(100_000 - 1) {
+            adler32.update(chunk);
+            crc32.update(chunk);
+        }
+
+        let mut scalar_adler32 = adler32::RollingAdler32::new();
+        scalar_adler32.update_buffer(&data);
+        assert_eq!(adler32.value(), scalar_adler32.hash());
+
+        let mut scalar_crc32 = !0u32;
+        for &b in &data {
+            scalar_crc32 ^= u32::from(b);
+            for _ in 0..8 {
+                scalar_crc32 =
+                    (scalar_crc32 >> 1) ^ (0xEDB8_8320 & (scalar_crc32 & 1).wrapping_neg());
+            }
+        }
+        assert_eq!(crc32.value(), !scalar_crc32);
+    }
 }
@@ -1,14 +1,36 @@
-use super::symbol;
+use super::symbol::{self, HuffmanCodec};
+use super::BlockType;
 use crate::bit;
 use crate::lz77;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::{cmp, mem};
 use core2::io::{self, Read};
 
 /// DEFLATE decoder.
+///
+/// # Truncated input
+///
+/// `Ok(0)` from the inner reader is regarded as the end of the input, and it is never read again after that
+/// (except by [`Decoder::reset`] and its variants).
+/// If the end of the input is reached before the end of the final block,
+/// an `UnexpectedEof` error is returned instead of truncated (or corrupted) data.
+/// So a reader which returns `Ok(0)` transiently (e.g., a bridge from an asynchronous source)
+/// should be wrapped to wait for more data instead.
 #[derive(Debug)]
 pub struct Decoder<R> {
     bit_reader: bit::BitReader<R>,
     lz77_decoder: lz77::Lz77Decoder,
+    block: BlockState,
+    windowed: bool,
+    stored_block_chunk: usize,
     eos: bool,
+    last_block_type: Option<BlockType>,
+    pending_error: Option<io::Error>,
+    allow_missing_final_block: bool,
+    match_stats: Option<MatchStats>,
+    caller_window: Option<CallerWindow>,
 }
 impl<R> Decoder<R>
 where
@@ -33,11 +55,81 @@ where
     /// assert_eq!(buf, b"Hello World!");
     /// ```
     pub fn new(inner: R) -> Self {
+        Self::with_buffer(inner, Vec::new())
+    }
+
+    /// Makes a new decoder instance which uses `buffer` as the buffer of decoded data (i.e., the LZ77 window).
+    ///
+    /// `buffer` is cleared before use, but its allocation is retained.
+    /// It can be reclaimed by [`Decoder::into_buffer`] after decoding,
+    /// so that a pool of buffers can be shared among many decoders.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let buffer = Vec::with_capacity(1024);
+    ///
+    /// let mut decoder = Decoder::with_buffer(&encoded_data[..], buffer);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    ///
+    /// let buffer = decoder.into_buffer();
+    /// assert_eq!(buffer.capacity(), 1024);
+    /// ```
+    pub fn with_buffer(inner: R, buffer: Vec<u8>) -> Self {
         Decoder {
             bit_reader: bit::BitReader::new(inner),
-            lz77_decoder: lz77::Lz77Decoder::new(),
+            lz77_decoder: lz77::Lz77Decoder::with_buffer(buffer),
+            block: BlockState::ReadBlockHeader,
+            windowed: false,
+            stored_block_chunk: usize::MAX,
             eos: false,
+            last_block_type: None,
+            pending_error: None,
+            allow_missing_final_block: false,
+            match_stats: None,
+            caller_window: None,
+        }
+    }
+
+    /// Makes a new decoder instance which starts decoding after skipping the first `skip_bits` bits of `inner`.
+    ///
+    /// This is useful to resume decoding from a block boundary which is not byte-aligned
+    /// (e.g., after seeking to a recorded position of a DEFLATE stream).
+    /// Note that the data decoded by the decoder must not refer to the data preceding the position.
+    ///
+    /// If `skip_bits` is greater than `7`, an `InvalidInput` error is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// // Two fixed Huffman blocks ("ab" and "cd"), the second one starts at the bit 2 of the byte 3
+    /// let encoded_data = [74, 76, 2, 44, 57, 5, 0];
+    ///
+    /// let mut decoder = Decoder::new_at_bit_offset(&encoded_data[3..], 2).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"cd");
+    /// ```
+    pub fn new_at_bit_offset(inner: R, skip_bits: u8) -> io::Result<Self> {
+        if skip_bits > 7 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                #[cfg(feature = "std")]
+                format!("skip_bits must be less than 8: skip_bits={skip_bits}"),
+                #[cfg(not(feature = "std"))]
+                "skip_bits must be less than 8",
+            ));
         }
+        let mut this = Self::new(inner);
+        this.bit_reader.read_bits(skip_bits)?;
+        Ok(this)
     }
 
     /// Returns the immutable reference to the inner stream.
@@ -50,8 +142,58 @@ where
         self.bit_reader.as_inner_mut()
     }
 
+    /// Returns the immutable reference to the inner stream.
+    ///
+    /// This is an alias of [`Decoder::as_inner_ref`].
+    pub fn get_ref(&self) -> &R {
+        self.as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    ///
+    /// This is an alias of [`Decoder::as_inner_mut`].
+    pub fn get_mut(&mut self) -> &mut R {
+        self.as_inner_mut()
+    }
+
+    /// Returns the type of the block most recently started.
+    ///
+    /// This is useful to diagnose a malformed stream after an error.
+    /// `None` is returned if no block header has been read yet,
+    /// or if the BTYPE of the last block header is the reserved value `0b11` (which is reported as an `InvalidData` error).
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::{BlockType, Decoder};
+    ///
+    /// // A fixed Huffman block that represents "Hello World!"
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// assert_eq!(decoder.last_block_type(), None);
+    ///
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(decoder.last_block_type(), Some(BlockType::Fixed));
+    ///
+    /// // BFINAL=1 and BTYPE=0b11
+    /// let mut decoder = Decoder::new(&[0b0000_0111][..]);
+    /// assert!(decoder.read_to_end(&mut buf).is_err());
+    /// assert_eq!(decoder.last_block_type(), None);
+    /// ```
+    pub fn last_block_type(&self) -> Option<BlockType> {
+        self.last_block_type
+    }
+
     /// Unwraps this `Decoder`, returning the underlying reader.
     ///
+    /// Once the end of the DEFLATE stream has been reached,
+    /// the reader is positioned right after the last byte of the stream
+    /// (i.e., the padding bits of the last byte are discarded).
+    ///
+    /// The decoder reads the inner stream byte by byte and never reads beyond the last byte of the stream,
+    /// so the data following a DEFLATE stream (e.g., the next frame of a protocol) can be read from the returned reader.
+    ///
     /// # Examples
     /// ```
     /// use core2::io::Cursor;
@@ -65,6 +207,179 @@ where
         self.bit_reader.into_inner()
     }
 
+    /// Unwraps this `Decoder`, returning the buffer of decoded data.
+    ///
+    /// The content of the buffer is unspecified; it is intended to be reused by [`Decoder::with_buffer`].
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.lz77_decoder.into_buffer()
+    }
+
+    /// Unwraps this `Decoder`, returning the underlying reader and
+    /// the number of bytes consumed from it.
+    ///
+    /// The count is the number of compressed bytes read from the inner reader by this decoder
+    /// (since it was made or last reset), not the number of decoded bytes.
+    /// The decoder never reads beyond the last byte of the final block (see [`Decoder::into_inner`]),
+    /// so once the end of the stream has been reached, the count equals the exact byte length of the DEFLATE stream.
+    /// This is useful to advance the position of a container format which embeds a raw DEFLATE stream.
+    ///
+    /// If this is called before the end of the stream, the count is the number of bytes read so far.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let mut encoded_data = vec![243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// encoded_data.extend_from_slice(b"trailing data");
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    ///
+    /// let (rest, consumed) = decoder.into_inner_with_consumed();
+    /// assert_eq!(consumed, 14);
+    /// assert_eq!(rest, b"trailing data");
+    /// ```
+    pub fn into_inner_with_consumed(self) -> (R, u64) {
+        let consumed = self.bit_reader.bytes_read();
+        (self.bit_reader.into_inner(), consumed)
+    }
+
+    /// Unwraps this `Decoder`, returning the underlying reader and
+    /// the bytes which have been read from it but not consumed by the decoder.
+    pub(crate) fn into_inner_with_buffered(self) -> (R, Vec<u8>) {
+        let buffered = self.bit_reader.buffered_bytes();
+        (self.bit_reader.into_inner(), buffered)
+    }
+
+    /// Makes a decoder for the next DEFLATE stream which follows the current one in the same reader.
+    ///
+    /// This should be called after the end of the current stream has been reached
+    /// (i.e., `read` returned `0`), otherwise the remaining data of the current stream are handled as the next stream.
+    /// The next stream is assumed to start at the byte boundary right after the current one.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let hello = [243, 72, 205, 201, 201, 7, 0];
+    /// let world = [11, 207, 47, 202, 73, 81, 4, 0];
+    /// let encoded_data = [&hello[..], &world[..]].concat();
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello");
+    ///
+    /// let mut decoder = decoder.continue_next();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"World!");
+    /// ```
+    pub fn continue_next(mut self) -> Self {
+        self.reset();
+        self
+    }
+
+    /// Decodes data directly into `out`, which is used as the sliding window of the decoder.
+    ///
+    /// `out` is filled from its beginning until it is full or the end of the stream is reached,
+    /// and the number of bytes written is returned (so `0` means the end of the stream).
+    /// Unlike `Read::read`, no internal buffer is involved:
+    /// back-references are resolved against `out` itself,
+    /// and the ones reaching before `out[0]` are resolved against the end of `out`
+    /// (i.e., the data written by the previous call).
+    /// A back-reference which does not fit into `out` is continued by the next call.
+    /// If an error occurs after some data has been written to `out`,
+    /// the written size is returned and the error is reported by the next call.
+    ///
+    /// This is useful to decode a large stream with a fixed amount of memory
+    /// (e.g., into a memory region owned by the caller) without copying the decoded data.
+    ///
+    /// # Contract
+    ///
+    /// The caller must pass the same buffer on every call and must not modify it between calls,
+    /// because it holds the history needed to decode the rest of the stream.
+    /// The buffer must be at least as large as the maximum backward distance of the stream
+    /// (`lz77::MAX_DISTANCE` by default, see [`Decoder::set_max_distance`]).
+    ///
+    /// An `InvalidInput` error is returned if the buffer is too small, if its size differs from the previous call,
+    /// or if this method and `Read::read` are mixed in the same stream
+    /// (until the decoder is reset by [`Decoder::reset`] or its variants).
+    /// A modified buffer cannot be detected; it just makes the subsequent output incorrect.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate::Decoder;
+    /// use libflate::lz77;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    ///
+    /// let mut window = vec![0; lz77::MAX_DISTANCE as usize];
+    /// assert_eq!(decoder.read_exact_into(&mut window).unwrap(), 12);
+    /// assert_eq!(&window[..12], b"Hello World!");
+    /// assert_eq!(decoder.read_exact_into(&mut window).unwrap(), 0);
+    /// ```
+    pub fn read_exact_into(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let mut window = match self.caller_window.take() {
+            Some(window) if window.size == out.len() => window,
+            Some(window) => {
+                self.caller_window = Some(window);
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "The size of the window differs from the previous call",
+                ));
+            }
+            None => {
+                if self.last_block_type.is_some() || !self.lz77_decoder.buffer().is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "`read_exact_into` cannot be used after `read`",
+                    ));
+                }
+                if out.len() < self.lz77_decoder.window() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        #[cfg(feature = "std")]
+                        format!(
+                            "The window is too small: size={}, max_distance={}",
+                            out.len(),
+                            self.lz77_decoder.window()
+                        ),
+                        #[cfg(not(feature = "std"))]
+                        "The window is too small",
+                    ));
+                }
+                CallerWindow {
+                    size: out.len(),
+                    decoded: 0,
+                    pending_copy: None,
+                }
+            }
+        };
+        if let Some(e) = self.pending_error.take() {
+            self.caller_window = Some(window);
+            return Err(e);
+        }
+
+        let mut pos = 0;
+        let result = self.decode_into_window(out, &mut pos, &mut window);
+        window.decoded += pos as u64;
+        self.caller_window = Some(window);
+        match result {
+            Ok(()) => Ok(pos),
+            Err(e) if pos == 0 => Err(e),
+            Err(e) => {
+                self.pending_error = Some(e);
+                Ok(pos)
+            }
+        }
+    }
+
     /// Returns the data that has been decoded but has not yet been read.
     ///
     /// This method is useful to retrieve partial decoded data when the decoding process is failed.
@@ -72,19 +387,314 @@ where
         self.lz77_decoder.buffer()
     }
 
-    pub(crate) fn reset(&mut self) {
+    /// Converts this decoder into an iterator over the symbols of the DEFLATE stream.
+    ///
+    /// The symbols are yielded as they are stored in the stream, without reconstructing the decoded data.
+    /// Each byte of a non-compressed block is yielded as a literal, and
+    /// every block (including non-compressed ones) is terminated by `Symbol::EndOfBlock`.
+    ///
+    /// This method should be called before reading any data from the decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate::{Decoder, Symbol};
+    /// use libflate::lz77::Code;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let symbols = Decoder::new(&encoded_data[..])
+    ///     .symbols()
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(symbols.len(), 13);
+    /// assert_eq!(symbols[0], Symbol::Code(Code::Literal(b'H')));
+    /// assert_eq!(symbols[12], Symbol::EndOfBlock);
+    /// ```
+    pub fn symbols(self) -> Symbols<R> {
+        Symbols { decoder: self }
+    }
+
+    /// Enables or disables the windowed mode (disabled by default).
+    ///
+    /// By default, each DEFLATE block is decoded entirely before its data is returned,
+    /// so the memory usage depends on the size of the blocks in the stream.
+    /// In the windowed mode, blocks are decoded incrementally and already-read data is discarded eagerly,
+    /// so the decoder retains roughly up to three times of `lz77::MAX_DISTANCE` bytes
+    /// regardless of the size of the stream and its blocks.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// decoder.set_windowed(true);
+    ///
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn set_windowed(&mut self, windowed: bool) {
+        self.windowed = windowed;
+        self.lz77_decoder.set_windowed(windowed);
+    }
+
+    /// Sets the maximum backward distance allowed in the stream.
+    ///
+    /// This enables a strict mode which verifies that every back-reference stays within
+    /// the window size advertised by the container format (e.g., `CINFO` of a ZLIB header).
+    /// A stream containing a back-reference farther than `max_distance` is rejected
+    /// with an `InvalidData` error.
+    ///
+    /// This sets the window size of the internal LZ77 decoder (see [`lz77::Lz77Decoder::with_window`]),
+    /// so only the last `max_distance` bytes of the decoded data are retained as the history.
+    ///
+    /// The default value is `lz77::MAX_DISTANCE`.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// // "abcabcabcabc" (all but the first "abc" is a back-reference of distance 3)
+    /// let encoded_data = [75, 76, 74, 78, 132, 33, 0];
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// decoder.set_max_distance(3);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"abcabcabcabc");
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// decoder.set_max_distance(2);
+    /// assert!(decoder.read_to_end(&mut Vec::new()).is_err());
+    /// ```
+    pub fn set_max_distance(&mut self, max_distance: u16) {
+        self.lz77_decoder.set_window(max_distance.into());
+    }
+
+    /// Sets the maximum number of bytes read from a non-compressed (stored) block at once.
+    ///
+    /// By default, a stored block (up to 65535 bytes) is read into the internal buffer in one go
+    /// (or in chunks of `lz77::MAX_DISTANCE` bytes in the windowed mode).
+    /// Specifying a small chunk size bounds the growth of the buffer of unread data,
+    /// because the rest of the block is read only after the buffered data has been consumed.
+    ///
+    /// A `size` of `0` is treated as `1`.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, EncodeOptions, Encoder};
+    ///
+    /// let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().no_compression());
+    /// encoder.write_all(&[b'a'; 4096]).unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// decoder.set_stored_block_chunk(256);
+    /// let mut buf = [0; 16];
+    /// decoder.read_exact(&mut buf).unwrap();
+    /// assert_eq!(decoder.unread_decoded_data().len(), 256 - 16);
+    /// ```
+    pub fn set_stored_block_chunk(&mut self, size: usize) {
+        self.stored_block_chunk = cmp::max(size, 1);
+    }
+
+    /// Enables or disables the lenient handling of a stream whose last block is not marked as final
+    /// (disabled by default).
+    ///
+    /// Some producers terminate a DEFLATE stream at a block boundary without setting the BFINAL bit
+    /// (relying on the length of the stream recorded elsewhere).
+    /// By default, such a stream is rejected with an `UnexpectedEof` error
+    /// because the decoder tries to read the header of the next block.
+    /// If this mode is enabled and the inner reader reaches its end right after a complete block
+    /// (only the padding bits of the last byte may remain), the stream is regarded as finished instead.
+    /// The end of the input in the middle of a block is still an error.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// // "Hello" in a non-final block, without any subsequent blocks
+    /// let encoded_data = [242, 72, 205, 201, 201, 7, 0];
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// assert!(decoder.read_to_end(&mut Vec::new()).is_err());
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// decoder.set_allow_missing_final_block(true);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello");
+    /// ```
+    pub fn set_allow_missing_final_block(&mut self, allow: bool) {
+        self.allow_missing_final_block = allow;
+    }
+
+    /// Enables or disables collecting the statistics of the decoded matches (disabled by default).
+    ///
+    /// The statistics can be retrieved via [`Decoder::match_stats`].
+    /// Enabling this again after it has been enabled does not clear the collected statistics.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// // "abcabcabcabc" (literals "abca" followed by a back-reference of length 8 and distance 3)
+    /// let encoded_data = [75, 76, 74, 78, 132, 33, 0];
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// assert!(decoder.match_stats().is_none());
+    ///
+    /// decoder.set_collect_match_stats(true);
+    /// decoder.read_to_end(&mut Vec::new()).unwrap();
+    ///
+    /// let stats = decoder.match_stats().unwrap();
+    /// assert_eq!(stats.literal_count(), 4);
+    /// assert_eq!(stats.match_count(), 1);
+    /// assert_eq!(stats.lengths().get(&8), Some(&1));
+    /// assert_eq!(stats.distances().get(&3), Some(&1));
+    /// ```
+    pub fn set_collect_match_stats(&mut self, collect: bool) {
+        if !collect {
+            self.match_stats = None;
+        } else if self.match_stats.is_none() {
+            self.match_stats = Some(MatchStats::default());
+        }
+    }
+
+    /// Returns the statistics of the matches decoded so far,
+    /// or `None` if collecting them is disabled (see [`Decoder::set_collect_match_stats`]).
+    pub fn match_stats(&self) -> Option<&MatchStats> {
+        self.match_stats.as_ref()
+    }
+
+    /// Resets the decoder to decode the next DEFLATE stream in the same reader.
+    ///
+    /// This is the in-place version of [`Decoder::continue_next`].
+    /// The buffer of decoded data (i.e., the LZ77 window) is cleared, but its allocation is retained,
+    /// so that many independent streams can be decoded without reallocating it.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let hello = [243, 72, 205, 201, 201, 7, 0];
+    /// let world = [11, 207, 47, 202, 73, 81, 4, 0];
+    /// let encoded_data = [&hello[..], &world[..]].concat();
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello");
+    ///
+    /// decoder.reset();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"World!");
+    /// ```
+    pub fn reset(&mut self) {
         self.bit_reader.reset();
+        self.bit_reader.reset_bytes_read();
         self.lz77_decoder.clear();
-        self.eos = false
+        self.block = BlockState::ReadBlockHeader;
+        self.eos = false;
+        self.last_block_type = None;
+        self.pending_error = None;
+        self.caller_window = None;
+        if let Some(stats) = &mut self.match_stats {
+            *stats = MatchStats::default();
+        }
     }
 
-    fn read_non_compressed_block(&mut self) -> io::Result<()> {
+    /// Resets the decoder to decode a new DEFLATE stream from `inner`, returning the previous reader.
+    ///
+    /// Like [`Decoder::reset`], the allocation of the buffer of decoded data is retained.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let hello = [243, 72, 205, 201, 201, 7, 0];
+    /// let world = [11, 207, 47, 202, 73, 81, 4, 0];
+    ///
+    /// let mut decoder = Decoder::new(&hello[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello");
+    ///
+    /// decoder.reset_with(&world[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"World!");
+    /// ```
+    pub fn reset_with(&mut self, inner: R) -> R {
+        let previous = mem::replace(self.bit_reader.as_inner_mut(), inner);
+        self.reset();
+        previous
+    }
+
+    /// Defers reporting `e` until the data decoded before the error has been read.
+    fn defer_error(&mut self, e: io::Error) -> io::Result<()> {
+        if self.lz77_decoder.buffer().is_empty() {
+            Err(e)
+        } else {
+            self.pending_error = Some(e);
+            Ok(())
+        }
+    }
+
+    fn output_limit(&self) -> usize {
+        if self.windowed {
+            lz77::MAX_DISTANCE as usize
+        } else {
+            usize::MAX
+        }
+    }
+
+    fn read_block_header(&mut self) -> io::Result<BlockState> {
+        let bfinal = self.bit_reader.read_bit()?;
+        let btype = self.bit_reader.read_bits(2)?;
+        self.eos = bfinal;
+        self.last_block_type = None;
+        let block_type = block_type_from_btype(btype)?;
+        self.last_block_type = Some(block_type);
+        match block_type {
+            BlockType::Raw => {
+                let len = self.read_non_compressed_block_len()?;
+                Ok(BlockState::NonCompressedBlock { len })
+            }
+            BlockType::Fixed => Ok(BlockState::CompressedBlock(
+                symbol::FixedHuffmanCodec.load(&mut self.bit_reader)?,
+            )),
+            BlockType::Dynamic => Ok(BlockState::CompressedBlock(
+                symbol::DynamicHuffmanCodec.load(&mut self.bit_reader)?,
+            )),
+        }
+    }
+
+    fn read_non_compressed_block_len(&mut self) -> io::Result<u16> {
+        // The bit reader may have read ahead some whole bytes, which are the beginning of LEN and NLEN
+        let buffered = self.bit_reader.buffered_bytes();
+        let mut buf = [0; 4];
+        buf[..buffered.len()].copy_from_slice(&buffered);
+        if buffered.len() < buf.len() && self.bit_reader.reached_eof() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
         self.bit_reader.reset();
-        let mut buf = [0; 2];
-        self.bit_reader.as_inner_mut().read_exact(&mut buf)?;
-        let len = u16::from_le_bytes(buf);
-        self.bit_reader.as_inner_mut().read_exact(&mut buf)?;
-        let nlen = u16::from_le_bytes(buf);
+        self.bit_reader
+            .as_inner_mut()
+            .read_exact(&mut buf[buffered.len()..])?;
+        self.bit_reader
+            .add_bytes_read((buf.len() - buffered.len()) as u64);
+        let len = u16::from_le_bytes([buf[0], buf[1]]);
+        let nlen = u16::from_le_bytes([buf[2], buf[3]]);
         if !len != nlen {
             Err(invalid_data_error!(
                 "LEN={} is not the one's complement of NLEN={}",
@@ -92,87 +702,415 @@ where
                 nlen
             ))
         } else {
-            self.lz77_decoder
-                .extend_from_reader(self.bit_reader.as_inner_mut().take(len.into()))
-                .and_then(|used| {
-                    if used != len.into() {
-                        Err(io::Error::new(
-                            io::ErrorKind::UnexpectedEof,
-                            #[cfg(feature = "std")]
-                            format!("The reader has incorrect length: expected {len}, read {used}"),
-                            #[cfg(not(feature = "std"))]
-                            "The reader has incorrect length",
-                        ))
-                    } else {
-                        Ok(())
-                    }
-                })
+            Ok(len)
         }
     }
-    fn read_compressed_block<H>(&mut self, huffman: &H) -> io::Result<()>
-    where
-        H: symbol::HuffmanCodec,
-    {
-        let symbol_decoder = huffman.load(&mut self.bit_reader)?;
-        loop {
+    fn read_non_compressed_block(&mut self, len: u16) -> io::Result<()> {
+        self.lz77_decoder
+            .extend_from_reader(self.bit_reader.as_inner_mut().take(len.into()))
+            .and_then(|used| {
+                self.bit_reader.add_bytes_read(used as u64);
+                if used != len.into() {
+                    Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        #[cfg(feature = "std")]
+                        format!("The reader has incorrect length: expected {len}, read {used}"),
+                        #[cfg(not(feature = "std"))]
+                        "The reader has incorrect length",
+                    ))
+                } else {
+                    Ok(())
+                }
+            })
+    }
+    /// Returns `true` if the end of the block has been reached.
+    fn read_compressed_block(&mut self, symbol_decoder: &symbol::Decoder) -> io::Result<bool> {
+        let limit = self.output_limit();
+        while self.lz77_decoder.buffer().len() < limit {
             let s = symbol_decoder.decode_unchecked(&mut self.bit_reader);
             self.bit_reader.check_last_error()?;
             match s {
                 symbol::Symbol::Code(code) => {
+                    if let Some(stats) = &mut self.match_stats {
+                        stats.record(&code);
+                    }
                     self.lz77_decoder.decode(code)?;
                 }
                 symbol::Symbol::EndOfBlock => {
-                    break;
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn decode_into_window(
+        &mut self,
+        out: &mut [u8],
+        pos: &mut usize,
+        window: &mut CallerWindow,
+    ) -> io::Result<()> {
+        while *pos < out.len() {
+            if let Some((length, backward_distance)) = window.pending_copy.take() {
+                window.pending_copy = copy_match(out, pos, length, backward_distance);
+                continue;
+            }
+            match mem::replace(&mut self.block, BlockState::ReadBlockHeader) {
+                BlockState::ReadBlockHeader => {
+                    if self.eos {
+                        break;
+                    }
+                    if self.allow_missing_final_block
+                        && self.last_block_type.is_some()
+                        && self.bit_reader.is_at_end_of_input()?
+                    {
+                        self.eos = true;
+                        break;
+                    }
+                    self.block = self.read_block_header()?;
+                }
+                BlockState::NonCompressedBlock { len } => {
+                    let size = cmp::min(usize::from(len), out.len() - *pos);
+                    let mut used = 0;
+                    let result = loop {
+                        if used == size {
+                            break Ok(());
+                        }
+                        match self
+                            .bit_reader
+                            .as_inner_mut()
+                            .read(&mut out[*pos + used..*pos + size])
+                        {
+                            Ok(0) => {
+                                break Err(io::Error::new(
+                                    io::ErrorKind::UnexpectedEof,
+                                    "The reader has incorrect length",
+                                ))
+                            }
+                            Ok(n) => used += n,
+                            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                            Err(e) => break Err(e),
+                        }
+                    };
+                    self.bit_reader.add_bytes_read(used as u64);
+                    *pos += used;
+                    if used < usize::from(len) {
+                        self.block = BlockState::NonCompressedBlock {
+                            len: len - used as u16,
+                        };
+                    }
+                    result?;
+                }
+                BlockState::CompressedBlock(symbol_decoder) => {
+                    if !self.decode_symbols_into_window(&symbol_decoder, out, pos, window)? {
+                        self.block = BlockState::CompressedBlock(symbol_decoder);
+                    }
                 }
             }
         }
         Ok(())
     }
+
+    /// Returns `true` if the end of the block has been reached.
+    fn decode_symbols_into_window(
+        &mut self,
+        symbol_decoder: &symbol::Decoder,
+        out: &mut [u8],
+        pos: &mut usize,
+        window: &mut CallerWindow,
+    ) -> io::Result<bool> {
+        while *pos < out.len() {
+            let s = symbol_decoder.decode_unchecked(&mut self.bit_reader);
+            self.bit_reader.check_last_error()?;
+            let code = match s {
+                symbol::Symbol::Code(code) => code,
+                symbol::Symbol::EndOfBlock => return Ok(true),
+            };
+            if let Some(stats) = &mut self.match_stats {
+                stats.record(&code);
+            }
+            match code {
+                lz77::Code::Literal(b) => {
+                    out[*pos] = b;
+                    *pos += 1;
+                }
+                lz77::Code::Pointer {
+                    length,
+                    backward_distance,
+                } => {
+                    if usize::from(backward_distance) > self.lz77_decoder.window() {
+                        return Err(invalid_data_error!(
+                            "Backward reference beyond the window: window={}, distance={}",
+                            self.lz77_decoder.window(),
+                            backward_distance
+                        ));
+                    }
+                    if u64::from(backward_distance) > window.decoded + *pos as u64 {
+                        return Err(invalid_data_error!(
+                            "Too long backward reference: decoded={}, distance={}",
+                            window.decoded + *pos as u64,
+                            backward_distance
+                        ));
+                    }
+                    window.pending_copy = copy_match(out, pos, length, backward_distance);
+                }
+            }
+        }
+        Ok(false)
+    }
 }
 impl<R> Read for Decoder<R>
 where
     R: Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if !self.lz77_decoder.buffer().is_empty() {
-            self.lz77_decoder.read(buf)
-        } else if self.eos {
-            Ok(0)
-        } else {
-            let bfinal = self.bit_reader.read_bit()?;
-            let btype = self.bit_reader.read_bits(2)?;
-            self.eos = bfinal;
-            match btype {
-                0b00 => {
-                    self.read_non_compressed_block()?;
-                    self.read(buf)
+        if self.caller_window.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "`read` cannot be used after `read_exact_into`",
+            ));
+        }
+        loop {
+            if !self.lz77_decoder.buffer().is_empty() {
+                return self.lz77_decoder.read(buf);
+            }
+            if let Some(e) = self.pending_error.take() {
+                return Err(e);
+            }
+            match mem::replace(&mut self.block, BlockState::ReadBlockHeader) {
+                BlockState::ReadBlockHeader => {
+                    if self.eos {
+                        return Ok(0);
+                    }
+                    if self.allow_missing_final_block
+                        && self.last_block_type.is_some()
+                        && self.bit_reader.is_at_end_of_input()?
+                    {
+                        self.eos = true;
+                        return Ok(0);
+                    }
+                    self.block = self.read_block_header()?;
                 }
-                0b01 => {
-                    self.read_compressed_block(&symbol::FixedHuffmanCodec)?;
-                    self.read(buf)
+                BlockState::NonCompressedBlock { len } => {
+                    let limit = cmp::min(self.output_limit(), self.stored_block_chunk);
+                    let size = cmp::min(usize::from(len), limit) as u16;
+                    match self.read_non_compressed_block(size) {
+                        Ok(()) if size < len => {
+                            self.block = BlockState::NonCompressedBlock { len: len - size };
+                        }
+                        Ok(()) => {}
+                        Err(e) => self.defer_error(e)?,
+                    }
                 }
-                0b10 => {
-                    self.read_compressed_block(&symbol::DynamicHuffmanCodec)?;
-                    self.read(buf)
+                BlockState::CompressedBlock(symbol_decoder) => {
+                    match self.read_compressed_block(&symbol_decoder) {
+                        Ok(false) => self.block = BlockState::CompressedBlock(symbol_decoder),
+                        Ok(true) => {}
+                        Err(e) => self.defer_error(e)?,
+                    }
                 }
-                0b11 => Err(invalid_data_error!(
-                    "btype 0x11 of DEFLATE is reserved(error) value"
-                )),
-                _ => unreachable!(),
             }
         }
     }
 }
 
+/// State of the decoding into a caller-owned window (see [`Decoder::read_exact_into`]).
+#[derive(Debug)]
+struct CallerWindow {
+    size: usize,
+    decoded: u64,
+    /// The rest of the back-reference which did not fit into the previous call: `(length, backward_distance)`.
+    pending_copy: Option<(u16, u16)>,
+}
+
+/// Copies the back-reference into `out` (used as a ring buffer) until it is full,
+/// returning the rest of the back-reference if any.
+fn copy_match(
+    out: &mut [u8],
+    pos: &mut usize,
+    length: u16,
+    backward_distance: u16,
+) -> Option<(u16, u16)> {
+    let size = cmp::min(usize::from(length), out.len() - *pos);
+    let distance = usize::from(backward_distance);
+    for _ in 0..size {
+        out[*pos] = out[(*pos + out.len() - distance) % out.len()];
+        *pos += 1;
+    }
+    let rest = length - size as u16;
+    (rest > 0).then_some((rest, backward_distance))
+}
+
+/// Statistics of the symbols decoded from the compressed blocks of a DEFLATE stream.
+///
+/// See [`Decoder::set_collect_match_stats`].
+/// The bytes of non-compressed blocks are not counted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MatchStats {
+    literal_count: u64,
+    lengths: BTreeMap<u16, u64>,
+    distances: BTreeMap<u16, u64>,
+}
+impl MatchStats {
+    /// Returns the number of the decoded literals.
+    pub fn literal_count(&self) -> u64 {
+        self.literal_count
+    }
+
+    /// Returns the number of the decoded matches (i.e., back-references).
+    pub fn match_count(&self) -> u64 {
+        self.lengths.values().sum()
+    }
+
+    /// Returns the histogram of the lengths of the decoded matches (length to count).
+    pub fn lengths(&self) -> &BTreeMap<u16, u64> {
+        &self.lengths
+    }
+
+    /// Returns the histogram of the backward distances of the decoded matches (distance to count).
+    pub fn distances(&self) -> &BTreeMap<u16, u64> {
+        &self.distances
+    }
+
+    fn record(&mut self, code: &lz77::Code) {
+        match *code {
+            lz77::Code::Literal(_) => self.literal_count += 1,
+            lz77::Code::Pointer {
+                length,
+                backward_distance,
+            } => {
+                *self.lengths.entry(length).or_default() += 1;
+                *self.distances.entry(backward_distance).or_default() += 1;
+            }
+        }
+    }
+}
+
+/// Reads the header of the first block of a DEFLATE stream, returning its type and BFINAL flag.
+///
+/// Only the first byte of `reader` is consumed (the header consists of the lowest 3 bits of the byte),
+/// and no symbols are decoded.
+/// This is useful to classify a stream cheaply.
+///
+/// If the BTYPE is the reserved value `0b11`, an `InvalidData` error is returned.
+///
+/// # Examples
+/// ```
+/// use libflate::deflate::{self, BlockType};
+///
+/// // A fixed Huffman block that represents "Hello World!"
+/// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+/// let (block_type, bfinal) = deflate::peek_first_block_type(&encoded_data[..]).unwrap();
+/// assert_eq!(block_type, BlockType::Fixed);
+/// assert!(bfinal);
+/// ```
+pub fn peek_first_block_type<R>(reader: R) -> io::Result<(BlockType, bool)>
+where
+    R: Read,
+{
+    let mut reader = bit::BitReader::new(reader);
+    let bfinal = reader.read_bit()?;
+    let block_type = block_type_from_btype(reader.read_bits(2)?)?;
+    Ok((block_type, bfinal))
+}
+
+fn block_type_from_btype(btype: u16) -> io::Result<BlockType> {
+    match btype {
+        0b00 => Ok(BlockType::Raw),
+        0b01 => Ok(BlockType::Fixed),
+        0b10 => Ok(BlockType::Dynamic),
+        _ => Err(invalid_data_error!(
+            "btype 0x11 of DEFLATE is reserved(error) value"
+        )),
+    }
+}
+
+/// An iterator over the symbols of a DEFLATE stream.
+///
+/// This is created by [`Decoder::symbols`].
+#[derive(Debug)]
+pub struct Symbols<R> {
+    decoder: Decoder<R>,
+}
+impl<R> Symbols<R>
+where
+    R: Read,
+{
+    /// Unwraps this `Symbols`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.decoder.into_inner()
+    }
+
+    fn next_symbol(&mut self) -> io::Result<Option<symbol::Symbol>> {
+        loop {
+            match self.decoder.block {
+                BlockState::ReadBlockHeader => {
+                    if self.decoder.eos {
+                        return Ok(None);
+                    }
+                    self.decoder.block = self.decoder.read_block_header()?;
+                }
+                BlockState::NonCompressedBlock { len: 0 } => {
+                    self.decoder.block = BlockState::ReadBlockHeader;
+                    return Ok(Some(symbol::Symbol::EndOfBlock));
+                }
+                BlockState::NonCompressedBlock { ref mut len } => {
+                    let mut buf = [0; 1];
+                    self.decoder
+                        .bit_reader
+                        .as_inner_mut()
+                        .read_exact(&mut buf)?;
+                    *len -= 1;
+                    return Ok(Some(symbol::Symbol::Code(lz77::Code::Literal(buf[0]))));
+                }
+                BlockState::CompressedBlock(ref symbol_decoder) => {
+                    let s = symbol_decoder.decode_unchecked(&mut self.decoder.bit_reader);
+                    self.decoder.bit_reader.check_last_error()?;
+                    if s == symbol::Symbol::EndOfBlock {
+                        self.decoder.block = BlockState::ReadBlockHeader;
+                    }
+                    return Ok(Some(s));
+                }
+            }
+        }
+    }
+}
+impl<R> Iterator for Symbols<R>
+where
+    R: Read,
+{
+    type Item = io::Result<symbol::Symbol>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_symbol() {
+            Ok(s) => s.map(Ok),
+            Err(e) => {
+                // Stops the iteration after an error
+                self.decoder.eos = true;
+                self.decoder.block = BlockState::ReadBlockHeader;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum BlockState {
+    ReadBlockHeader,
+    NonCompressedBlock { len: u16 },
+    CompressedBlock(Cow<'static, symbol::Decoder>),
+}
+
 #[cfg(test)]
 mod tests {
-    #[cfg(feature = "std")]
     use super::*;
-    use crate::deflate::symbol::{DynamicHuffmanCodec, HuffmanCodec};
+    use crate::deflate::symbol::DynamicHuffmanCodec;
+    use crate::util::{deflate_encode_options, pseudo_random_bytes};
     #[cfg(feature = "std")]
     use std::io;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_issues_3() {
         // see: https://github.com/sile/libflate/issues/3
         let input = [
@@ -211,6 +1149,515 @@ mod tests {
         assert!(error.to_string().starts_with("Too long backword reference"));
     }
 
+    #[test]
+    fn symbols_works() {
+        use crate::deflate::{EncodeOptions, Encoder, Symbol};
+        use crate::lz77::{DefaultLz77Encoder, Lz77Encode};
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let text = b"Hello World! Hello DEFLATE! Hello World!";
+
+        let mut expected = Vec::<Symbol>::new();
+        let mut lz77 = DefaultLz77Encoder::new();
+        lz77.encode(text, &mut expected);
+        lz77.flush(&mut expected);
+        expected.push(Symbol::EndOfBlock);
+
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(text).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        let symbols = Decoder::new(&encoded[..])
+            .symbols()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(symbols, expected);
+
+        // Non-compressed blocks
+        let options = EncodeOptions::new().no_compression();
+        let mut encoder = Encoder::with_options(Vec::new(), options);
+        encoder.write_all(b"abc").unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        let symbols = Decoder::new(&encoded[..])
+            .symbols()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            symbols,
+            [b'a', b'b', b'c']
+                .iter()
+                .map(|&b| Symbol::Code(lz77::Code::Literal(b)))
+                .chain(core::iter::once(Symbol::EndOfBlock))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn windowed_decode_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let plain = (0..200_000u64)
+            .map(|i| (i * i % 251) as u8)
+            .chain(core::iter::repeat(b'a').take(1_000_000))
+            .collect::<Vec<_>>();
+        for options in [EncodeOptions::new(), EncodeOptions::new().no_compression()] {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&plain).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut decoder = Decoder::new(&encoded[..]);
+            decoder.set_windowed(true);
+            let mut decoded = Vec::new();
+            let mut buf = [0; 1000];
+            loop {
+                let size = decoder.read(&mut buf).unwrap();
+                if size == 0 {
+                    break;
+                }
+                decoded.extend_from_slice(&buf[..size]);
+                assert!(
+                    decoder.unread_decoded_data().len()
+                        < lz77::MAX_DISTANCE as usize + lz77::MAX_LENGTH as usize
+                );
+            }
+            assert_eq!(decoded, plain);
+        }
+    }
+
+    #[test]
+    fn max_distance_bounds_history() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        // Repetitions of a pseudo-random sequence of 700 bytes
+        let plain = pseudo_random_bytes(700).repeat(286);
+        let lz77 = lz77::DefaultLz77EncoderBuilder::new()
+            .window_size(1024)
+            .build();
+        let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::with_lz77(lz77));
+        encoder.write_all(&plain).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let mut decoder = Decoder::new(&encoded[..]);
+        decoder.set_windowed(true);
+        decoder.set_max_distance(1024);
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, plain);
+        let history = core::mem::take(&mut decoder.lz77_decoder).into_buffer();
+        assert!(history.len() <= 2 * 1024, "{}", history.len());
+
+        let mut decoder = Decoder::new(&encoded[..]);
+        decoder.set_max_distance(512);
+        let error = decoder.read_to_end(&mut Vec::new()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reserved_distance_code_is_rejected() {
+        use alloc::vec::Vec;
+
+        // A fixed Huffman block consisting of a literal 'a' and a pointer with the distance code 30
+        let mut writer = crate::bit::BitWriter::new(Vec::new());
+        writer.write_bit(true).unwrap(); // BFINAL
+        writer.write_bits(2, 0b01).unwrap(); // BTYPE
+        writer.write_bits(8, 0b1000_1001).unwrap(); // literal 'a' (reversed `0b1001_0001`)
+        writer.write_bits(7, 0b100_0000).unwrap(); // length code 257 (reversed `0b000_0001`)
+        writer.write_bits(5, 0b0_1111).unwrap(); // distance code 30 (reversed `0b1_1110`)
+        writer.write_bits(7, 0).unwrap(); // end of block
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+
+        let mut decoder = Decoder::new(&input[..]);
+        let mut buf = Vec::new();
+        let error = decoder.read_to_end(&mut buf).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        #[cfg(feature = "std")]
+        assert!(error.to_string().contains("distance code 30"));
+    }
+
+    #[test]
+    fn decoded_data_before_error_is_readable() {
+        use alloc::vec::Vec;
+
+        // A fixed Huffman block consisting of literals "ab" and a pointer with the distance 5
+        let mut writer = crate::bit::BitWriter::new(Vec::new());
+        writer.write_bit(true).unwrap(); // BFINAL
+        writer.write_bits(2, 0b01).unwrap(); // BTYPE
+        for &b in b"ab" {
+            let code = 0b0011_0000 + u16::from(b);
+            writer.write_bits(8, code.reverse_bits() >> 8).unwrap();
+        }
+        writer.write_bits(7, 0b100_0000).unwrap(); // length code 257 (reversed `0b000_0001`)
+        writer.write_bits(5, 0b0_0100).unwrap(); // distance code 4 (reversed `0b0_0100`)
+        writer.write_bits(7, 0).unwrap(); // end of block
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+
+        let mut decoder = Decoder::new(&input[..]);
+        let mut buf = [0; 16];
+        assert_eq!(decoder.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ab");
+        let error = decoder.read(&mut buf).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_exact_into_works() {
+        use crate::deflate::Encoder;
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let text = pseudo_random_bytes(200_000)
+            .into_iter()
+            .map(|b| b"abcdefghij"[usize::from(b % 10)])
+            .collect::<Vec<_>>();
+        for options in deflate_encode_options() {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&text).unwrap();
+            let encoded_data = encoder.finish().into_result().unwrap();
+
+            let mut decoder = Decoder::new(&encoded_data[..]);
+            let mut window = [0; lz77::MAX_DISTANCE as usize];
+            let mut decoded_data = Vec::new();
+            loop {
+                let size = decoder.read_exact_into(&mut window).unwrap();
+                decoded_data.extend_from_slice(&window[..size]);
+                if size < window.len() {
+                    break;
+                }
+            }
+            assert_eq!(decoded_data, text);
+
+            // The internal buffer is never used
+            assert_eq!(decoder.into_buffer().capacity(), 0);
+        }
+    }
+
+    #[test]
+    fn read_exact_into_rejects_invalid_usage() {
+        let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+
+        // Too small window
+        let mut decoder = Decoder::new(&encoded_data[..]);
+        let mut window = [0; 1024];
+        let e = decoder.read_exact_into(&mut window).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+        // A smaller window is allowed if the maximum distance is limited
+        decoder.set_max_distance(1024);
+        assert_eq!(decoder.read_exact_into(&mut window).unwrap(), 12);
+        assert_eq!(&window[..12], b"Hello World!");
+
+        // Mixed with `read`
+        let e = decoder.read(&mut [0; 16]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+        let mut decoder = Decoder::new(&encoded_data[..]);
+        decoder.read_exact(&mut [0; 5]).unwrap();
+        let mut window = [0; lz77::MAX_DISTANCE as usize];
+        let e = decoder.read_exact_into(&mut window).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn incomplete_huffman_code_lengths_are_rejected() {
+        use alloc::vec::Vec;
+
+        // A dynamic Huffman block whose literal/length code lengths are all 9 (incomplete)
+        let mut writer = crate::bit::BitWriter::new(Vec::new());
+        writer.write_bit(true).unwrap(); // BFINAL
+        writer.write_bits(2, 0b10).unwrap(); // BTYPE
+        writer.write_bits(5, 0).unwrap(); // HLIT (257 codes)
+        writer.write_bits(5, 0).unwrap(); // HDIST (1 code)
+        writer.write_bits(4, 14).unwrap(); // HCLEN (18 codes)
+        for i in 0..18 {
+            // Only the code length symbols `9` (6th) and `1` (18th) are used
+            let width = if i == 6 || i == 17 { 1 } else { 0 };
+            writer.write_bits(3, width).unwrap();
+        }
+        for _ in 0..257 {
+            writer.write_bit(true).unwrap(); // code length 9
+        }
+        writer.write_bit(false).unwrap(); // code length 1
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+
+        let mut decoder = Decoder::new(&input[..]);
+        let mut buf = Vec::new();
+        let error = decoder.read_to_end(&mut buf).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        #[cfg(feature = "std")]
+        assert!(error.to_string().contains("Incomplete"));
+    }
+
+    #[test]
+    fn new_at_bit_offset_works() {
+        use alloc::vec::Vec;
+
+        // Two fixed Huffman blocks: "ab" (26 bits) and "cd" (26 bits)
+        let mut writer = crate::bit::BitWriter::new(Vec::new());
+        for (bfinal, text) in [(false, b"ab"), (true, b"cd")] {
+            writer.write_bit(bfinal).unwrap();
+            writer.write_bits(2, 0b01).unwrap();
+            for &b in text {
+                // Literals 0..144 are coded as `0b0011_0000 + b` in 8 bits (reversed)
+                let code = 0b0011_0000 + u16::from(b);
+                writer.write_bits(8, code.reverse_bits() >> 8).unwrap();
+            }
+            writer.write_bits(7, 0).unwrap(); // end of block
+        }
+        writer.flush().unwrap();
+        let input = writer.into_inner();
+        assert_eq!(input, [74, 76, 2, 44, 57, 5, 0]);
+
+        let mut buf = Vec::new();
+        Decoder::new(&input[..]).read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"abcd");
+
+        let mut buf = Vec::new();
+        Decoder::new_at_bit_offset(&input[3..], 2)
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, b"cd");
+
+        assert!(Decoder::new_at_bit_offset(&input[3..], 8).is_err());
+    }
+
+    #[test]
+    fn continue_next_works() {
+        use crate::deflate::Encoder;
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let texts: [&[u8]; 3] = [b"Hello World!", b"foo bar baz", b"Hello Hello Hello!"];
+        let mut encoded = Vec::new();
+        for (text, options) in texts.iter().zip(deflate_encode_options()) {
+            let mut encoder = Encoder::with_options(&mut encoded, options);
+            encoder.write_all(text).unwrap();
+            encoder.finish().into_result().unwrap();
+        }
+        encoded.extend_from_slice(b"tail");
+
+        let mut decoder = Decoder::new(&encoded[..]);
+        for text in texts {
+            let mut buf = Vec::new();
+            decoder.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf, text);
+            decoder = decoder.continue_next();
+        }
+        assert_eq!(decoder.into_inner(), b"tail");
+    }
+
+    #[test]
+    fn decoder_does_not_read_past_end_of_stream() {
+        use crate::deflate::Encoder;
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let sentinel = [0xAB, 0xCD];
+        let inputs: [Vec<u8>; 4] = [
+            Vec::new(),
+            b"a".to_vec(),
+            include_bytes!("../../README.md").to_vec(),
+            pseudo_random_bytes(100_000),
+        ];
+        for input in &inputs {
+            for options in deflate_encode_options() {
+                let mut encoder = Encoder::with_options(Vec::new(), options);
+                encoder.write_all(input).unwrap();
+                let mut encoded = encoder.finish().into_result().unwrap();
+                encoded.extend_from_slice(&sentinel);
+
+                let mut decoder = Decoder::new(&encoded[..]);
+                let mut buf = Vec::new();
+                decoder.read_to_end(&mut buf).unwrap();
+                assert_eq!(buf, *input);
+                assert_eq!(decoder.into_inner(), sentinel);
+            }
+        }
+    }
+
+    #[test]
+    fn decoding_region_delimited_by_take_works() {
+        use crate::deflate::Encoder;
+        use alloc::vec::Vec;
+        use core2::io::{Cursor, Write};
+
+        for input in [
+            &b""[..],
+            b"a",
+            b"Hello World!",
+            include_bytes!("../../README.md"),
+        ] {
+            for options in deflate_encode_options() {
+                let mut encoder = Encoder::with_options(Vec::new(), options);
+                encoder.write_all(input).unwrap();
+                let encoded = encoder.finish().into_result().unwrap();
+
+                let file = [&[0xFF; 3][..], &encoded, &[0xFF; 3]].concat();
+                let mut reader = Cursor::new(&file);
+                reader.set_position(3);
+
+                let mut decoder = Decoder::new((&mut reader).take(encoded.len() as u64));
+                let mut buf = Vec::new();
+                decoder.read_to_end(&mut buf).unwrap();
+                assert_eq!(buf, input);
+                assert_eq!(decoder.into_inner().limit(), 0);
+                assert_eq!(reader.position(), 3 + encoded.len() as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn stored_block_chunk_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let input: Vec<u8> = (0..60 * 1024).map(|i| i as u8).collect();
+        let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().no_compression());
+        encoder.write_all(&input).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let mut decoder = Decoder::new(&encoded[..]);
+        decoder.set_stored_block_chunk(1000);
+        let mut decoded = Vec::new();
+        let mut buf = [0; 100];
+        loop {
+            let size = decoder.read(&mut buf).unwrap();
+            if size == 0 {
+                break;
+            }
+            assert!(decoder.unread_decoded_data().len() < 1000);
+            decoded.extend_from_slice(&buf[..size]);
+        }
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn recycled_buffer_works() {
+        use crate::deflate::Encoder;
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let mut buffer = Vec::with_capacity(64 * 1024);
+        let ptr = buffer.as_ptr();
+        for text in [&b"Hello World!"[..], b"", include_bytes!("../../README.md")] {
+            let mut encoder = Encoder::new(Vec::new());
+            encoder.write_all(text).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut decoder = Decoder::with_buffer(&encoded[..], buffer);
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, text);
+
+            buffer = decoder.into_buffer();
+            assert_eq!(buffer.as_ptr(), ptr);
+        }
+    }
+
+    #[test]
+    fn reset_works() {
+        use crate::deflate::Encoder;
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let texts = [&include_bytes!("../../README.md")[..], b"Hello World!", b""];
+        let mut encoded = Vec::new();
+        for text in texts {
+            let mut encoder = Encoder::new(&mut encoded);
+            encoder.write_all(text).unwrap();
+            encoder.finish().into_result().unwrap();
+        }
+
+        let mut decoder = Decoder::new(&encoded[..]);
+        let mut ptr = None;
+        for text in texts {
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, text);
+
+            decoder.reset();
+            let buffer_ptr = decoder.lz77_decoder.buffer().as_ptr();
+            assert_eq!(*ptr.get_or_insert(buffer_ptr), buffer_ptr);
+        }
+        assert!(decoder.get_ref().is_empty());
+
+        let rest = decoder.reset_with(&encoded[..]);
+        assert!(rest.is_empty());
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, texts[0]);
+    }
+
+    #[test]
+    fn peek_first_block_type_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        for (options, expected) in [
+            (EncodeOptions::new(), BlockType::Dynamic),
+            (EncodeOptions::new().fixed_huffman_codes(), BlockType::Fixed),
+            (EncodeOptions::new().no_compression(), BlockType::Raw),
+        ] {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder
+                .write_all(include_bytes!("../../README.md"))
+                .unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut reader = &encoded[..];
+            let (block_type, _) = peek_first_block_type(&mut reader).unwrap();
+            assert_eq!(block_type, expected);
+            assert_eq!(reader.len(), encoded.len() - 1);
+        }
+
+        // BFINAL=0 and BTYPE=0b01
+        assert_eq!(
+            peek_first_block_type(&[0b010][..]).unwrap(),
+            (BlockType::Fixed, false)
+        );
+        // BFINAL=1 and BTYPE=0b11
+        let e = peek_first_block_type(&[0b111][..]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(peek_first_block_type(&[][..]).is_err());
+    }
+
+    #[test]
+    fn finish_returns_consumed_bytes() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let input = include_bytes!("../../README.md");
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().no_compression().block_size(1000),
+            EncodeOptions::new().fixed_huffman_codes(),
+        ] {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(input).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+            let file = [&encoded[..], &[0xFF; 3]].concat();
+
+            let mut decoder = Decoder::new(&file[..]);
+            let mut buf = Vec::new();
+            decoder.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf, input);
+
+            let (rest, consumed) = decoder.into_inner_with_consumed();
+            assert_eq!(consumed, encoded.len() as u64);
+            assert_eq!(rest, [0xFF; 3]);
+        }
+    }
+
     #[test]
     #[cfg(feature = "std")]
     fn test_issue_64() {
@@ -218,4 +1665,128 @@ mod tests {
         let mut decoder = Decoder::new(&input[..]);
         assert!(io::copy(&mut decoder, &mut io::sink()).is_err());
     }
+
+    #[test]
+    fn spurious_eof_is_not_treated_as_end_of_stream() {
+        use crate::deflate::Encoder;
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        /// A reader which returns `Ok(0)` once at `eof_at`.
+        struct FlakyReader<'a> {
+            data: &'a [u8],
+            position: usize,
+            eof_at: Option<usize>,
+        }
+        impl io::Read for FlakyReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.eof_at == Some(self.position) {
+                    self.eof_at = None;
+                    return Ok(0);
+                }
+                let end = self.eof_at.unwrap_or(self.data.len());
+                let size = buf.len().min(end - self.position);
+                buf[..size].copy_from_slice(&self.data[self.position..][..size]);
+                self.position += size;
+                Ok(size)
+            }
+        }
+
+        let text = b"Hello World! Hello World! Hello Wooooooooooooooorld!";
+        for options in deflate_encode_options() {
+            let mut encoder = Encoder::with_options(Vec::new(), options.block_size(20));
+            encoder.write_all(text).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            for eof_at in 0..encoded.len() {
+                let reader = FlakyReader {
+                    data: &encoded,
+                    position: 0,
+                    eof_at: Some(eof_at),
+                };
+                let mut decoded = Vec::new();
+                let e = Decoder::new(reader)
+                    .read_to_end(&mut decoded)
+                    .expect_err("Truncated data must not be returned");
+                assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof, "eof_at={eof_at}");
+            }
+        }
+    }
+
+    #[test]
+    fn allow_missing_final_block_works() {
+        use crate::deflate::Encoder;
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let text = b"Hello World! Hello World! Hello Wooooooooooooooorld!";
+        for options in deflate_encode_options() {
+            let mut encoder = Encoder::with_options(Vec::new(), options.block_size(20));
+            encoder.write_all(text).unwrap();
+            let encoded = encoder.finish_not_final().unwrap();
+
+            let mut decoder = Decoder::new(&encoded[..]);
+            let e = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+            for len in 0..=encoded.len() {
+                let mut decoder = Decoder::new(&encoded[..len]);
+                decoder.set_allow_missing_final_block(true);
+                let mut decoded = Vec::new();
+                match decoder.read_to_end(&mut decoded) {
+                    Ok(_) => {
+                        assert!(len > 0);
+                        assert!(text.starts_with(&decoded), "len={len}");
+                    }
+                    Err(e) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof, "len={len}"),
+                }
+                if len == encoded.len() {
+                    assert_eq!(decoded, text);
+                    assert_eq!(decoder.into_inner_with_consumed().1, encoded.len() as u64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn match_stats_works() {
+        use crate::deflate::Encoder;
+        use alloc::vec::Vec;
+        use core2::io::Write;
+
+        let text = include_bytes!("../../README.md");
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(text).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        // The matches emitted by the LZ77 encoder which `Encoder` uses by default
+        let mut codes: Vec<lz77::Code> = Vec::new();
+        let mut lz77_encoder = lz77::DefaultLz77Encoder::new();
+        lz77::Lz77Encode::encode(&mut lz77_encoder, text, &mut codes);
+        lz77::Lz77Encode::flush(&mut lz77_encoder, &mut codes);
+        let mut expected = MatchStats::default();
+        for code in &codes {
+            expected.record(code);
+        }
+        assert!(expected.match_count() > 0);
+
+        let mut decoder = Decoder::new(&encoded[..]);
+        decoder.set_collect_match_stats(true);
+        decoder.read_to_end(&mut Vec::new()).unwrap();
+        assert_eq!(decoder.match_stats(), Some(&expected));
+        assert_eq!(
+            expected.literal_count()
+                + expected
+                    .lengths()
+                    .iter()
+                    .map(|(&l, &c)| u64::from(l) * c)
+                    .sum::<u64>(),
+            text.len() as u64
+        );
+
+        decoder.reset();
+        assert_eq!(decoder.match_stats(), Some(&MatchStats::default()));
+        decoder.set_collect_match_stats(false);
+        assert_eq!(decoder.match_stats(), None);
+    }
 }
@@ -3,8 +3,9 @@ use super::BlockType;
 use crate::bit;
 use crate::finish::{Complete, Finish};
 use crate::lz77;
+use alloc::boxed::Box;
 use alloc::vec::Vec;
-use core::cmp;
+use core::{cmp, fmt};
 use core2::io;
 
 /// The default size of a DEFLATE block.
@@ -12,12 +13,43 @@ pub const DEFAULT_BLOCK_SIZE: usize = 1024 * 1024;
 
 const MAX_NON_COMPRESSED_BLOCK_SIZE: usize = 0xFFFF;
 
+/// The minimum match length of the LZ77 encoder in the `Strategy::Filtered` mode.
+const FILTERED_MIN_MATCH_LENGTH: u16 = 6;
+
+/// Compression strategies, which tune the encoder for specific kinds of data.
+///
+/// A strategy only affects the compression ratio and the speed; the output is always a valid DEFLATE stream.
+/// Note that strategies are ignored if the compression is disabled by [`EncodeOptions::no_compression`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// Uses the matches found by the LZ77 encoder as is (default).
+    #[default]
+    Default,
+
+    /// Encodes short matches as literals, relying on the Huffman coding instead.
+    ///
+    /// This raises the minimum match length of the LZ77 encoder (see [`lz77::Lz77Encode::set_min_match`]).
+    ///
+    /// This is suitable for data which consists of small values with somewhat random distribution
+    /// (e.g., filtered PNG images).
+    Filtered,
+
+    /// Disables LZ77 matching, so every byte is encoded as a literal.
+    HuffmanOnly,
+
+    /// Only uses matches whose distance is `1` (i.e., run-length encoding).
+    ///
+    /// This is as fast as `HuffmanOnly` but also compresses runs of the same byte.
+    Rle,
+}
+
 /// Options for a DEFLATE encoder.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct EncodeOptions<E = lz77::DefaultLz77Encoder> {
     block_size: usize,
     dynamic_huffman: bool,
     lz77: Option<E>,
+    strategy: Strategy,
 }
 impl Default for EncodeOptions<lz77::DefaultLz77Encoder> {
     fn default() -> Self {
@@ -39,6 +71,7 @@ impl EncodeOptions<lz77::DefaultLz77Encoder> {
             block_size: DEFAULT_BLOCK_SIZE,
             dynamic_huffman: true,
             lz77: Some(lz77::DefaultLz77Encoder::new()),
+            strategy: Strategy::Default,
         }
     }
 }
@@ -61,6 +94,7 @@ where
             block_size: DEFAULT_BLOCK_SIZE,
             dynamic_huffman: true,
             lz77: Some(lz77),
+            strategy: Strategy::Default,
         }
     }
 
@@ -109,6 +143,38 @@ where
         self
     }
 
+    /// Specifies to compress with dynamic huffman codes (default).
+    ///
+    /// This reverts the effect of [`EncodeOptions::fixed_huffman_codes`].
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::deflate::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().fixed_huffman_codes().dynamic_huffman_codes();
+    /// let encoder = Encoder::with_options(Vec::new(), options);
+    /// ```
+    pub fn dynamic_huffman_codes(mut self) -> Self {
+        self.dynamic_huffman = true;
+        self
+    }
+
+    /// Specifies the compression strategy.
+    ///
+    /// The default value is `Strategy::Default`.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::deflate::{Encoder, EncodeOptions, Strategy};
+    ///
+    /// let options = EncodeOptions::new().strategy(Strategy::Rle);
+    /// let encoder = Encoder::with_options(Vec::new(), options);
+    /// ```
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
     fn get_block_type(&self) -> BlockType {
         if self.lz77.is_none() {
             BlockType::Raw
@@ -127,11 +193,65 @@ where
     }
 }
 
+/// The last bytes of the data written to a DEFLATE encoder.
+///
+/// This is returned by [`Encoder::checkpoint`] and used by [`Encoder::resume`]
+/// to continue the compression of a stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DeflateDictionary {
+    bytes: Vec<u8>,
+}
+impl DeflateDictionary {
+    /// Makes a new dictionary instance.
+    ///
+    /// `bytes` should be the data which has been encoded just before resuming the compression.
+    /// Only the last `lz77::MAX_WINDOW_SIZE` bytes are used.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        let mut this = DeflateDictionary { bytes: Vec::new() };
+        this.extend_from_slice(&bytes);
+        this
+    }
+
+    /// Returns the bytes of the dictionary.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Converts the dictionary into its bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn extend_from_slice(&mut self, buf: &[u8]) {
+        let window_size = lz77::MAX_WINDOW_SIZE as usize;
+        self.bytes
+            .extend_from_slice(&buf[buf.len().saturating_sub(window_size)..]);
+        if self.bytes.len() > window_size * 2 {
+            self.bytes.drain(..self.bytes.len() - window_size);
+        }
+    }
+
+    fn into_window(mut self) -> Self {
+        let window_size = lz77::MAX_WINDOW_SIZE as usize;
+        if self.bytes.len() > window_size {
+            self.bytes.drain(..self.bytes.len() - window_size);
+        }
+        self
+    }
+}
+
 /// DEFLATE encoder.
 #[derive(Debug)]
 pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
     writer: bit::BitWriter<W>,
     block: Block<E>,
+
+    // The last bytes of the input (at most twice of the window size, see `DeflateDictionary::extend_from_slice`)
+    history: DeflateDictionary,
+    finished: bool,
+    bytes_in: u64,
+    progress: Option<ProgressCallback>,
+    reported_blocks: u64,
 }
 impl<W> Encoder<W, lz77::DefaultLz77Encoder>
 where
@@ -156,6 +276,17 @@ where
     pub fn new(inner: W) -> Self {
         Self::with_options(inner, EncodeOptions::default())
     }
+
+    /// Makes a new encoder instance which continues the DEFLATE stream suspended by [`Encoder::checkpoint`].
+    ///
+    /// `inner` should be positioned right after the data written before the checkpoint,
+    /// and `dictionary` should be the one returned by the checkpoint.
+    /// The data written to the resulting encoder may refer to the data in the dictionary.
+    ///
+    /// See [`Encoder::checkpoint`] for an example.
+    pub fn resume(inner: W, dictionary: DeflateDictionary) -> Self {
+        Self::resume_with_options(inner, EncodeOptions::default(), dictionary)
+    }
 }
 impl<W, E> Encoder<W, E>
 where
@@ -183,7 +314,252 @@ where
         Encoder {
             writer: bit::BitWriter::new(inner),
             block: Block::new(options),
+            history: DeflateDictionary::default(),
+            finished: false,
+            bytes_in: 0,
+            progress: None,
+            reported_blocks: 0,
+        }
+    }
+
+    /// Makes a new encoder instance with specified options,
+    /// which continues the DEFLATE stream suspended by [`Encoder::checkpoint`].
+    ///
+    /// See [`Encoder::resume`] for details.
+    pub fn resume_with_options(
+        inner: W,
+        mut options: EncodeOptions<E>,
+        dictionary: DeflateDictionary,
+    ) -> Self {
+        if let Some(lz77) = options.lz77.as_mut() {
+            lz77.set_dictionary(dictionary.as_bytes());
+        }
+        Encoder {
+            writer: bit::BitWriter::new(inner),
+            block: Block::new(options),
+            history: dictionary,
+            finished: false,
+            bytes_in: 0,
+            progress: None,
+            reported_blocks: 0,
+        }
+    }
+
+    /// Suspends the encoding, returning the inner stream and the dictionary needed to resume it later.
+    ///
+    /// All buffered data is flushed and the stream is aligned to a byte boundary,
+    /// but the final block is not written.
+    /// The compression can be continued (e.g., in another process)
+    /// by passing the stream and the dictionary to [`Encoder::resume`],
+    /// and the resulting data can be decoded as one continuous DEFLATE stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, Encoder};
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let (buf, dictionary) = encoder.checkpoint().unwrap();
+    ///
+    /// let mut encoder = Encoder::resume(buf, dictionary);
+    /// encoder.write_all(b" Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World! Hello World!");
+    /// ```
+    pub fn checkpoint(mut self) -> io::Result<(W, DeflateDictionary)> {
+        self.sync_flush()?;
+        self.writer.write_pending()?;
+        Ok((self.writer.into_inner(), self.history.into_window()))
+    }
+
+    /// Flushes all buffered data as non-final blocks and returns the inner stream.
+    ///
+    /// The output ends at a byte boundary (an empty non-compressed block is appended for the alignment),
+    /// so the output of another encoder can be appended to it directly.
+    /// By finishing the last encoder with [`Encoder::finish`], the concatenation becomes a single DEFLATE stream.
+    ///
+    /// Unlike [`Encoder::checkpoint`], the data written to the next encoder cannot refer to the data written to this one.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, Encoder};
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello ").unwrap();
+    /// let mut encoded_data = encoder.finish_not_final().unwrap();
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"World!").unwrap();
+    /// encoded_data.extend(encoder.finish().into_result().unwrap());
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn finish_not_final(mut self) -> io::Result<W> {
+        self.sync_flush()?;
+        self.writer.write_pending()?;
+        Ok(self.writer.into_inner())
+    }
+
+    /// Emits the buffered data as a complete non-final block immediately, regardless of the block size.
+    ///
+    /// Unlike [`Encoder::checkpoint`] and [`Encoder::finish_not_final`], no empty non-compressed block
+    /// (i.e., the `00 00 FF FF` sync marker) is appended, so the output is not aligned to a byte boundary.
+    /// This is useful to align the blocks with the boundaries of records.
+    /// If there is no buffered data, this method does nothing.
+    ///
+    /// Note that the inner stream is not flushed.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, Encoder, Symbol};
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// for record in [&b"foo"[..], b"bar", b"baz"] {
+    ///     encoder.write_all(record).unwrap();
+    ///     encoder.flush_block().unwrap();
+    /// }
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let blocks = Decoder::new(&encoded_data[..])
+    ///     .symbols()
+    ///     .filter(|s| matches!(s, Ok(Symbol::EndOfBlock)))
+    ///     .count();
+    /// assert_eq!(blocks, 4); // Three records and the empty final block
+    /// ```
+    pub fn flush_block(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The final block has already been written",
+            ));
+        }
+        if self.block.block_buf.len() == 0 {
+            return Ok(());
+        }
+        self.block.flush(&mut self.writer, false)?;
+        self.writer.write_pending()?;
+        self.report_progress();
+        Ok(())
+    }
+
+    /// Emits the data written so far as a complete message, keeping the LZ77 window for the next message.
+    ///
+    /// The buffered data is flushed and the output is aligned to a byte boundary
+    /// (as with [`Encoder::finish_not_final`]), so a streaming decoder can decode the whole message
+    /// from the output written so far.
+    /// Unlike an ordinary flush, the data written before is then given to the LZ77 encoder as the dictionary,
+    /// so the next message may refer to the previous messages (up to the window size).
+    /// This improves the compression of a sequence of similar messages.
+    ///
+    /// Note that the inner stream is flushed, too.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, Encoder};
+    ///
+    /// let message = b"{\"id\": 1, \"name\": \"libflate\", \"tags\": [\"deflate\", \"zlib\", \"gzip\"]}";
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(message).unwrap();
+    /// encoder.flush_message().unwrap();
+    /// let first_len = encoder.as_inner_ref().len();
+    ///
+    /// encoder.write_all(message).unwrap();
+    /// encoder.flush_message().unwrap();
+    /// let second_len = encoder.as_inner_ref().len() - first_len;
+    /// assert!(second_len < first_len / 2);
+    ///
+    /// let mut decoder = Decoder::new(&encoder.as_inner_ref()[..]);
+    /// let mut buf = vec![0; message.len() * 2];
+    /// decoder.read_exact(&mut buf).unwrap();
+    /// assert_eq!(buf, [&message[..], &message[..]].concat());
+    /// ```
+    pub fn flush_message(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The final block has already been written",
+            ));
         }
+        self.sync_flush()?;
+        self.writer.write_pending()?;
+        self.block.block_buf.set_dictionary(self.history.as_bytes());
+        Ok(())
+    }
+
+    /// Writes all the chunks in `chunks` in order.
+    ///
+    /// This is a convenience for scatter-gather sources which avoids concatenating the chunks beforehand.
+    /// The output of the encoder is identical regardless of how the input is chunked,
+    /// i.e., writing the chunks one by one (or at once) yields the same bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all_chunks([&b"Hello "[..], b"World!"]).unwrap();
+    /// let chunked = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// assert_eq!(encoder.finish().into_result().unwrap(), chunked);
+    /// ```
+    pub fn write_all_chunks<'a, I>(&mut self, chunks: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        for chunk in chunks {
+            io::Write::write_all(self, chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Compresses the whole of `data`, working on the borrowed slice instead of copying it into internal buffers.
+    ///
+    /// The output is identical to that of `write_all(data)`.
+    /// This avoids copying the input (e.g., a memory-mapped file) into the LZ77 encoder's buffer
+    /// for full windows of input, and non-compressed blocks are written straight from `data`.
+    /// The LZ77 symbols of the current block are still buffered as usual.
+    ///
+    /// Only the `DefaultLz77Encoder` benefits from this;
+    /// other `Lz77Encode` implementations behave the same as `write_all`.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let data = b"Hello World!".repeat(10000);
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.compress_slice(&data).unwrap();
+    /// let compressed = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(&data).unwrap();
+    /// assert_eq!(encoder.finish().into_result().unwrap(), compressed);
+    /// ```
+    pub fn compress_slice(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.finished {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The final block has already been written",
+            ));
+        }
+        self.write_input(data, true)
     }
 
     /// Flushes internal buffer and returns the inner stream.
@@ -200,13 +576,175 @@ where
     ///            [5, 192, 49, 13, 0, 0, 8, 3, 65, 43, 224, 6, 7, 24, 128, 237,
     ///            147, 38, 245, 63, 244, 230, 65, 181, 50, 215, 1]);
     /// ```
+    ///
+    /// # Note
+    ///
+    /// Because `&mut W` also implements `Write`, the encoder can borrow the output stream.
+    /// In that case, this method returns the borrowed reference and
+    /// the underlying buffer can be used again for subsequent encodings.
+    ///
+    /// ```
+    /// use core2::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let mut buf = Vec::new();
+    /// for payload in [&b"foo"[..], &b"bar"[..]] {
+    ///     let mut encoder = Encoder::new(&mut buf);
+    ///     encoder.write_all(payload).unwrap();
+    ///     encoder.finish().into_result().unwrap();
+    /// }
+    /// assert!(!buf.is_empty());
+    /// ```
     pub fn finish(mut self) -> Finish<W, io::Error> {
-        match self.block.finish(&mut self.writer) {
+        match self.try_finish() {
             Ok(_) => Finish::new(self.writer.into_inner(), None),
             Err(e) => Finish::new(self.writer.into_inner(), Some(e)),
         }
     }
 
+    /// Writes the final block, without consuming the encoder.
+    ///
+    /// If the inner stream becomes full (i.e., it returns `Ok(0)` or a `WriteZero` error),
+    /// the encoded data which could not be written is kept in the encoder and
+    /// a `WriteZero` error is returned.
+    /// In that case, the caller can retry after making room in the inner stream
+    /// (e.g., by draining a fixed-size buffer via [`Encoder::as_inner_mut`]).
+    /// Once this method returns `Ok(())`, the whole stream has been written to the inner stream.
+    ///
+    /// `Encoder::write` behaves likewise: if there is encoded data which cannot be written yet,
+    /// it returns a `WriteZero` error without consuming the input.
+    /// So the encoder can be used with a fixed-size output buffer without losing any data.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{self, Cursor, Read, Write};
+    /// use libflate::deflate::{Decoder, Encoder};
+    ///
+    /// let text = b"Hello World! Hello World!";
+    /// let mut buf = [0; 4];
+    /// let mut encoder = Encoder::new(Cursor::new(&mut buf[..]));
+    /// let mut encoded_data = Vec::new();
+    /// let mut drain = |buf: &mut Cursor<&mut [u8]>| {
+    ///     let size = buf.position() as usize;
+    ///     encoded_data.extend_from_slice(&buf.get_ref()[..size]);
+    ///     buf.set_position(0);
+    /// };
+    ///
+    /// let mut input = &text[..];
+    /// while !input.is_empty() {
+    ///     match encoder.write(input) {
+    ///         Ok(size) => input = &input[size..],
+    ///         Err(e) if e.kind() == io::ErrorKind::WriteZero => drain(encoder.as_inner_mut()),
+    ///         Err(e) => panic!("{}", e),
+    ///     }
+    /// }
+    /// loop {
+    ///     match encoder.try_finish() {
+    ///         Ok(()) => break,
+    ///         Err(e) if e.kind() == io::ErrorKind::WriteZero => drain(encoder.as_inner_mut()),
+    ///         Err(e) => panic!("{}", e),
+    ///     }
+    /// }
+    /// drain(encoder.as_inner_mut());
+    ///
+    /// let mut decoded_data = Vec::new();
+    /// Decoder::new(&encoded_data[..]).read_to_end(&mut decoded_data).unwrap();
+    /// assert_eq!(decoded_data, text);
+    /// ```
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        if !self.finished {
+            self.block.flush(&mut self.writer, true)?;
+            self.writer.flush()?;
+            self.finished = true;
+        }
+        self.writer.write_pending()?;
+        self.report_progress();
+        Ok(())
+    }
+
+    /// Returns the number of encoded bytes written to the inner stream so far.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// encoder.try_finish().unwrap();
+    /// assert_eq!(encoder.bytes_written(), encoder.as_inner_ref().len() as u64);
+    /// ```
+    pub fn bytes_written(&self) -> u64 {
+        self.writer.bytes_written()
+    }
+
+    /// Registers `f` to be called whenever a block is flushed.
+    ///
+    /// `f` receives the number of bytes written to the encoder and
+    /// the number of encoded bytes written to the inner stream so far (see [`Encoder::bytes_written`]).
+    /// Because a block is flushed every [`EncodeOptions::block_size`] bytes of input,
+    /// this can be used to report the progress of a long encoding (e.g., `io::copy` of a large file)
+    /// without wrapping the reader.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Write;
+    /// use libflate::deflate::{EncodeOptions, Encoder};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let progress = Arc::new(Mutex::new(Vec::new()));
+    /// let options = EncodeOptions::new().block_size(1024);
+    /// let mut encoder = Encoder::with_options(Vec::new(), options);
+    /// let p = Arc::clone(&progress);
+    /// encoder.on_progress(move |bytes_in, bytes_out| p.lock().unwrap().push((bytes_in, bytes_out)));
+    ///
+    /// encoder.write_all(&[0; 4096]).unwrap();
+    /// encoder.finish().into_result().unwrap();
+    ///
+    /// let progress = progress.lock().unwrap();
+    /// assert!(progress.len() > 1);
+    /// assert_eq!(progress.last().unwrap().0, 4096);
+    /// ```
+    pub fn on_progress<F>(&mut self, f: F)
+    where
+        F: FnMut(u64, u64) + Send + 'static,
+    {
+        self.progress = Some(ProgressCallback(Box::new(f)));
+    }
+
+    /// Returns the size of the encoded data which has not been written to the inner stream yet
+    /// because the stream was full (or returned a `WouldBlock` error).
+    ///
+    /// Once the inner stream becomes full, `write` stops accepting input at the end of the current block
+    /// (returning the number of bytes accepted so far), and then returns the error of the inner stream
+    /// until the pending data is written. So the size is bounded by the encoded size of a block.
+    ///
+    /// See [`Encoder::try_finish`] for details.
+    pub fn pending_output_len(&self) -> usize {
+        self.writer.pending_len()
+    }
+
+    /// Returns the number of input bytes buffered in the current block, which have not been encoded yet.
+    ///
+    /// The block is flushed automatically once this reaches [`EncodeOptions::block_size`].
+    /// This can be used to decide whether to call [`Encoder::flush_block`] proactively.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// assert_eq!(encoder.pending_input(), 12);
+    ///
+    /// encoder.flush_block().unwrap();
+    /// assert_eq!(encoder.pending_input(), 0);
+    /// ```
+    pub fn pending_input(&self) -> usize {
+        self.block.block_buf.len()
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &W {
         self.writer.as_inner_ref()
@@ -218,17 +756,64 @@ where
     }
 
     /// Unwraps the `Encoder`, returning the inner stream.
+    ///
+    /// Note that the encoded data which has not been written to the inner stream yet is discarded.
     pub fn into_inner(self) -> W {
         self.writer.into_inner()
     }
 
-    pub(crate) fn zlib_sync_flush(&mut self) -> io::Result<()> {
+    /// Writes `blocks`, which must be complete non-final DEFLATE blocks ending at a byte boundary, verbatim.
+    pub(crate) fn write_raw_blocks(&mut self, blocks: &[u8]) -> io::Result<()> {
+        self.sync_flush()?;
+        self.writer.write_bytes(blocks)?;
+
+        // The data represented by `blocks` is unknown, so it cannot be used as a dictionary
+        self.history = DeflateDictionary::default();
+        Ok(())
+    }
+
+    fn write_input(&mut self, buf: &[u8], borrowed: bool) -> io::Result<()> {
+        self.writer.write_pending()?;
+        self.block.write(&mut self.writer, buf, borrowed)?;
+        self.history.extend_from_slice(buf);
+        self.bytes_in += buf.len() as u64;
+        self.report_progress();
+        Ok(())
+    }
+
+    fn report_progress(&mut self) {
+        if self.reported_blocks == self.block.flushed_blocks {
+            return;
+        }
+        self.reported_blocks = self.block.flushed_blocks;
+        if let Some(ProgressCallback(f)) = self.progress.as_mut() {
+            f(self.bytes_in, self.writer.bytes_written());
+        }
+    }
+
+    pub(crate) fn partial_flush(&mut self) -> io::Result<()> {
+        self.block.flush(&mut self.writer, false)?;
+
+        // An empty fixed Huffman block (i.e., only the end-of-block code) pushes the preceding bits out
+        self.writer.write_bit(false)?;
+        self.writer.write_bits(2, BlockType::Fixed as u16)?;
+        self.writer.write_bits(7, 0)?;
+        self.writer.flush_whole_bytes()?;
+        self.writer.write_pending()?;
+        self.report_progress();
+
+        self.writer.as_inner_mut().flush()
+    }
+
+    pub(crate) fn sync_flush(&mut self) -> io::Result<()> {
         self.block.flush(&mut self.writer, false)?;
 
         self.writer.write_bit(false)?;
         self.writer.write_bits(2, BlockType::Raw as u16)?;
         self.writer.flush()?;
-        self.writer.as_inner_mut().write_all(&[0, 0, 255, 255])?;
+        self.writer.write_bytes(&[0, 0, 255, 255])?;
+        self.writer.write_pending()?;
+        self.report_progress();
 
         self.writer.as_inner_mut().flush()
     }
@@ -239,11 +824,28 @@ where
     E: lz77::Lz77Encode,
 {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.block.write(&mut self.writer, buf)?;
-        Ok(buf.len())
+        if self.finished {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The final block has already been written",
+            ));
+        }
+        self.writer.write_pending()?;
+
+        // Stops at a block boundary once the inner stream becomes full,
+        // so that the encoded data kept pending is bounded by the size of a block
+        let mut written = 0;
+        while written < buf.len() && self.writer.pending_len() == 0 {
+            let size = cmp::min(buf.len() - written, self.block.remaining());
+            self.write_input(&buf[written..][..size], false)?;
+            written += size;
+        }
+        Ok(written)
     }
     fn flush(&mut self) -> io::Result<()> {
         self.block.flush(&mut self.writer, false)?;
+        self.writer.write_pending()?;
+        self.report_progress();
         self.writer.as_inner_mut().flush()
     }
 }
@@ -257,11 +859,59 @@ where
     }
 }
 
+/// Returns the length of the DEFLATE stream which `data` would be encoded into with `options`.
+///
+/// The encoded bytes are counted and discarded instead of being stored,
+/// so this is useful to choose the options (e.g., a [`Strategy`]) without keeping every candidate output.
+///
+/// # Examples
+/// ```
+/// use core2::io::Write;
+/// use libflate::deflate::{self, EncodeOptions, Encoder};
+///
+/// let data = b"Hello World! Hello World!";
+/// let len = deflate::compressed_len(data, EncodeOptions::new()).unwrap();
+///
+/// let mut encoder = Encoder::new(Vec::new());
+/// encoder.write_all(data).unwrap();
+/// assert_eq!(len, encoder.finish().into_result().unwrap().len());
+/// ```
+pub fn compressed_len<E>(data: &[u8], options: EncodeOptions<E>) -> io::Result<usize>
+where
+    E: lz77::Lz77Encode,
+{
+    let mut encoder = Encoder::with_options(Discard, options);
+    io::Write::write_all(&mut encoder, data)?;
+    encoder.try_finish()?;
+    Ok(encoder.bytes_written() as usize)
+}
+
+/// A writer which discards all the written bytes.
+#[derive(Debug)]
+struct Discard;
+impl io::Write for Discard {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A callback which is called with the number of input and output bytes whenever a block is flushed.
+struct ProgressCallback(Box<dyn FnMut(u64, u64) + Send>);
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
 #[derive(Debug)]
 struct Block<E> {
     block_type: BlockType,
     block_size: usize,
     block_buf: BlockBuf<E>,
+    flushed_blocks: u64,
 }
 impl<E> Block<E>
 where
@@ -271,16 +921,45 @@ where
         Block {
             block_type: options.get_block_type(),
             block_size: options.get_block_size(),
-            block_buf: BlockBuf::new(options.lz77, options.dynamic_huffman),
+            block_buf: BlockBuf::new(options.lz77, options.dynamic_huffman, options.strategy),
+            flushed_blocks: 0,
         }
     }
-    fn write<W>(&mut self, writer: &mut bit::BitWriter<W>, buf: &[u8]) -> io::Result<()>
+    /// Returns the number of input bytes which can be written before the current block is flushed.
+    fn remaining(&self) -> usize {
+        self.block_size - self.block_buf.len()
+    }
+    /// Encodes `buf`.
+    ///
+    /// If `borrowed` is `true`, `buf` is processed in place as much as possible instead of being buffered
+    /// (the output is the same in either case).
+    fn write<W>(
+        &mut self,
+        writer: &mut bit::BitWriter<W>,
+        buf: &[u8],
+        borrowed: bool,
+    ) -> io::Result<()>
     where
         W: io::Write,
     {
-        self.block_buf.append(buf);
-        while self.block_buf.len() >= self.block_size {
-            self.flush(writer, false)?;
+        // Splits the input at block boundaries, so that the blocks do not depend on how the input is chunked
+        // (and large uncompressed writes are not buffered entirely)
+        let mut buf = buf;
+        while !buf.is_empty() {
+            let size = cmp::min(buf.len(), self.block_size - self.block_buf.len());
+            if borrowed && size == self.block_size && matches!(self.block_buf, BlockBuf::Raw(_)) {
+                writer.write_bit(false)?;
+                writer.write_bits(2, self.block_type as u16)?;
+                RawBuf::write_block(writer, &buf[..size])?;
+                self.flushed_blocks += 1;
+                buf = &buf[size..];
+                continue;
+            }
+            self.block_buf.append(&buf[..size], borrowed);
+            buf = &buf[size..];
+            if self.block_buf.len() >= self.block_size {
+                self.flush(writer, false)?;
+            }
         }
         Ok(())
     }
@@ -291,14 +970,7 @@ where
         writer.write_bit(is_final)?;
         writer.write_bits(2, self.block_type as u16)?;
         self.block_buf.flush(writer)?;
-        Ok(())
-    }
-    fn finish<W>(mut self, writer: &mut bit::BitWriter<W>) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        self.flush(writer, true)?;
-        writer.flush()?;
+        self.flushed_blocks += 1;
         Ok(())
     }
 }
@@ -313,22 +985,26 @@ impl<E> BlockBuf<E>
 where
     E: lz77::Lz77Encode,
 {
-    fn new(lz77: Option<E>, dynamic: bool) -> Self {
+    fn new(lz77: Option<E>, dynamic: bool, strategy: Strategy) -> Self {
         if let Some(lz77) = lz77 {
             if dynamic {
-                BlockBuf::Dynamic(CompressBuf::new(symbol::DynamicHuffmanCodec, lz77))
+                BlockBuf::Dynamic(CompressBuf::new(
+                    symbol::DynamicHuffmanCodec,
+                    lz77,
+                    strategy,
+                ))
             } else {
-                BlockBuf::Fixed(CompressBuf::new(symbol::FixedHuffmanCodec, lz77))
+                BlockBuf::Fixed(CompressBuf::new(symbol::FixedHuffmanCodec, lz77, strategy))
             }
         } else {
             BlockBuf::Raw(RawBuf::new())
         }
     }
-    fn append(&mut self, buf: &[u8]) {
+    fn append(&mut self, buf: &[u8], borrowed: bool) {
         match *self {
             BlockBuf::Raw(ref mut b) => b.append(buf),
-            BlockBuf::Fixed(ref mut b) => b.append(buf),
-            BlockBuf::Dynamic(ref mut b) => b.append(buf),
+            BlockBuf::Fixed(ref mut b) => b.append(buf, borrowed),
+            BlockBuf::Dynamic(ref mut b) => b.append(buf, borrowed),
         }
     }
     fn len(&self) -> usize {
@@ -348,6 +1024,13 @@ where
             BlockBuf::Dynamic(ref mut b) => b.flush(writer),
         }
     }
+    fn set_dictionary(&mut self, dictionary: &[u8]) {
+        match *self {
+            BlockBuf::Raw(_) => {}
+            BlockBuf::Fixed(ref mut b) => b.lz77.set_dictionary(dictionary),
+            BlockBuf::Dynamic(ref mut b) => b.lz77.set_dictionary(dictionary),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -369,42 +1052,101 @@ impl RawBuf {
         W: io::Write,
     {
         let size = cmp::min(self.buf.len(), MAX_NON_COMPRESSED_BLOCK_SIZE);
-        writer.flush()?;
-        writer
-            .as_inner_mut()
-            .write_all(&(size as u16).to_le_bytes())?;
-        writer
-            .as_inner_mut()
-            .write_all(&(!size as u16).to_le_bytes())?;
-        writer.as_inner_mut().write_all(&self.buf[..size])?;
+        Self::write_block(writer, &self.buf[..size])?;
         self.buf.drain(0..size);
         Ok(())
     }
+    /// Writes the body (i.e., the part after the block header) of a non-compressed block containing `buf`.
+    fn write_block<W>(writer: &mut bit::BitWriter<W>, buf: &[u8]) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        debug_assert!(buf.len() <= MAX_NON_COMPRESSED_BLOCK_SIZE);
+        let size = buf.len();
+        writer.flush()?;
+        writer.write_bytes(&(size as u16).to_le_bytes())?;
+        writer.write_bytes(&(!size as u16).to_le_bytes())?;
+        writer.write_bytes(buf)
+    }
 }
 
 #[derive(Debug)]
 struct CompressBuf<H, E> {
     huffman: H,
     lz77: E,
+    strategy: Strategy,
     buf: Vec<symbol::Symbol>,
     original_size: usize,
+
+    // The last literal and the number of its repetitions not yet encoded (used by `Strategy::Rle`)
+    run: Option<(u8, u16)>,
 }
 impl<H, E> CompressBuf<H, E>
 where
     H: symbol::HuffmanCodec,
     E: lz77::Lz77Encode,
 {
-    fn new(huffman: H, lz77: E) -> Self {
+    fn new(huffman: H, mut lz77: E, strategy: Strategy) -> Self {
+        if strategy == Strategy::Filtered {
+            lz77.set_min_match(FILTERED_MIN_MATCH_LENGTH);
+        }
         CompressBuf {
             huffman,
             lz77,
+            strategy,
             buf: Vec::new(),
             original_size: 0,
+            run: None,
         }
     }
-    fn append(&mut self, buf: &[u8]) {
+    fn append(&mut self, buf: &[u8], borrowed: bool) {
         self.original_size += buf.len();
-        self.lz77.encode(buf, &mut self.buf);
+        match self.strategy {
+            Strategy::Default | Strategy::Filtered if borrowed => {
+                self.lz77.encode_borrowed(buf, &mut self.buf)
+            }
+            Strategy::Default | Strategy::Filtered => self.lz77.encode(buf, &mut self.buf),
+            Strategy::HuffmanOnly => self.buf.extend(
+                buf.iter()
+                    .map(|&b| symbol::Symbol::Code(lz77::Code::Literal(b))),
+            ),
+            Strategy::Rle => {
+                for &b in buf {
+                    self.append_rle(b);
+                }
+            }
+        }
+    }
+    fn append_rle(&mut self, b: u8) {
+        match self.run {
+            Some((last, ref mut count)) if last == b => {
+                *count += 1;
+                if *count == lz77::MAX_LENGTH {
+                    self.flush_rle_run();
+                }
+            }
+            _ => {
+                self.flush_rle_run();
+                self.buf.push(symbol::Symbol::Code(lz77::Code::Literal(b)));
+                self.run = Some((b, 0));
+            }
+        }
+    }
+    fn flush_rle_run(&mut self) {
+        if let Some((last, ref mut count)) = self.run {
+            if *count >= 3 {
+                self.buf.push(symbol::Symbol::Code(lz77::Code::Pointer {
+                    length: *count,
+                    backward_distance: 1,
+                }));
+            } else {
+                for _ in 0..*count {
+                    self.buf
+                        .push(symbol::Symbol::Code(lz77::Code::Literal(last)));
+                }
+            }
+            *count = 0;
+        }
     }
     fn len(&self) -> usize {
         self.original_size
@@ -413,7 +1155,14 @@ where
     where
         W: io::Write,
     {
-        self.lz77.flush(&mut self.buf);
+        match self.strategy {
+            Strategy::Default | Strategy::Filtered => self.lz77.flush(&mut self.buf),
+            Strategy::HuffmanOnly => {}
+            Strategy::Rle => {
+                self.flush_rle_run();
+                self.run = None;
+            }
+        }
         self.buf.push(symbol::Symbol::EndOfBlock);
         let symbol_encoder = self.huffman.build(&self.buf)?;
         self.huffman.save(writer, &symbol_encoder)?;
@@ -429,8 +1178,32 @@ where
 mod tests {
     use super::super::Decoder;
     use super::*;
+    use crate::util::{deflate_encode_options, pseudo_random_bytes};
     use core2::io::{Read as _, Write as _};
 
+    #[test]
+    fn compressed_len_works() {
+        let text = include_bytes!("../../README.md");
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().fixed_huffman_codes(),
+            EncodeOptions::new().no_compression(),
+            EncodeOptions::new()
+                .strategy(Strategy::Rle)
+                .block_size(1000),
+        ] {
+            for data in [&text[..], b"", b"a"] {
+                let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+                encoder.write_all(data).unwrap();
+                let encoded = encoder.finish().into_result().unwrap();
+                assert_eq!(
+                    compressed_len(data, options.clone()).unwrap(),
+                    encoded.len()
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_issues_52() {
         // see: https://github.com/sile/libflate/issues/52
@@ -457,6 +1230,326 @@ mod tests {
     }
 
     #[test]
+    fn no_compression_splits_large_write_into_stored_blocks() {
+        let input = (0..1_000_000u32).map(|i| i as u8).collect::<Vec<_>>();
+        let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().no_compression());
+        assert_eq!(encoder.write(&input).unwrap(), input.len());
+        let encoded = encoder.finish().into_result().unwrap();
+
+        // Each stored block has a 5 bytes header (BFINAL, BTYPE, LEN and NLEN)
+        let blocks = input.len().div_ceil(MAX_NON_COMPRESSED_BLOCK_SIZE);
+        assert_eq!(blocks, 16);
+        assert_eq!(encoded.len(), input.len() + blocks * 5);
+
+        let mut decoded = Vec::new();
+        Decoder::new(&encoded[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn output_does_not_depend_on_chunking() {
+        let mut input = include_bytes!("../../README.md").to_vec();
+        input.extend(pseudo_random_bytes(300_000));
+
+        for options in deflate_encode_options() {
+            let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+            encoder.write_all(&input).unwrap();
+            let expected = encoder.finish().into_result().unwrap();
+
+            for chunk_size in [1, 7, 1000, 65_537] {
+                let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+                encoder.write_all_chunks(input.chunks(chunk_size)).unwrap();
+                let encoded = encoder.finish().into_result().unwrap();
+                assert_eq!(encoded, expected);
+            }
+
+            let mut decoded = Vec::new();
+            Decoder::new(&expected[..])
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, input);
+        }
+    }
+
+    #[test]
+    fn encode_into_fixed_size_buffer_works() {
+        struct FixedBuf {
+            buf: [u8; 7],
+            len: usize,
+        }
+        impl io::Write for FixedBuf {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let size = cmp::min(buf.len(), self.buf.len() - self.len);
+                self.buf[self.len..][..size].copy_from_slice(&buf[..size]);
+                self.len += size;
+                Ok(size)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        fn drain(inner: &mut FixedBuf, output: &mut Vec<u8>) {
+            output.extend_from_slice(&inner.buf[..inner.len]);
+            inner.len = 0;
+        }
+
+        let mut input = include_bytes!("../../README.md").to_vec();
+        input.extend(pseudo_random_bytes(100_000));
+        for options in deflate_encode_options() {
+            let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+            encoder.write_all(&input).unwrap();
+            let expected = encoder.finish().into_result().unwrap();
+
+            let inner = FixedBuf {
+                buf: [0; 7],
+                len: 0,
+            };
+            let mut encoder = Encoder::with_options(inner, options);
+            let mut encoded = Vec::new();
+            for mut chunk in input.chunks(1000) {
+                while !chunk.is_empty() {
+                    match encoder.write(chunk) {
+                        Ok(size) => chunk = &chunk[size..],
+                        Err(e) => {
+                            assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+                            drain(encoder.as_inner_mut(), &mut encoded);
+                        }
+                    }
+                }
+            }
+            while let Err(e) = encoder.try_finish() {
+                assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+                assert!(encoder.pending_output_len() > 0);
+                drain(encoder.as_inner_mut(), &mut encoded);
+            }
+            assert_eq!(encoder.pending_output_len(), 0);
+            drain(encoder.as_inner_mut(), &mut encoded);
+            assert_eq!(encoded, expected);
+            assert_eq!(encoder.bytes_written(), expected.len() as u64);
+        }
+    }
+
+    #[test]
+    fn pending_output_is_bounded_by_block() {
+        struct WouldBlockWriter {
+            buf: Vec<u8>,
+            capacity: usize,
+        }
+        impl io::Write for WouldBlockWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.buf.len() == self.capacity {
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                let size = cmp::min(buf.len(), self.capacity - self.buf.len());
+                self.buf.extend_from_slice(&buf[..size]);
+                Ok(size)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        const BLOCK_SIZE: usize = 1024;
+        let input = pseudo_random_bytes(100_000);
+        for options in deflate_encode_options() {
+            let inner = WouldBlockWriter {
+                buf: Vec::new(),
+                capacity: 100,
+            };
+            let mut encoder = Encoder::with_options(inner, options.block_size(BLOCK_SIZE));
+            let mut encoded = Vec::new();
+            let mut rest = &input[..];
+            while !rest.is_empty() {
+                match encoder.write(rest) {
+                    Ok(size) => {
+                        assert!(size > 0);
+                        rest = &rest[size..];
+                    }
+                    Err(e) => {
+                        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+                        encoded.append(&mut encoder.as_inner_mut().buf);
+                    }
+                }
+                // A block of the hardly compressible data is encoded into a little more than BLOCK_SIZE bytes
+                assert!(encoder.pending_output_len() < BLOCK_SIZE * 2);
+            }
+            while let Err(e) = encoder.try_finish() {
+                assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+                encoded.append(&mut encoder.as_inner_mut().buf);
+            }
+            encoded.append(&mut encoder.as_inner_mut().buf);
+
+            let mut decoded = Vec::new();
+            Decoder::new(&encoded[..])
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, input);
+        }
+    }
+
+    #[test]
+    fn sync_flush_writes_pending_output() {
+        struct LimitedWriter {
+            buf: Vec<u8>,
+            limit: usize,
+        }
+        impl io::Write for LimitedWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.buf.len() == self.limit {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "Full"));
+                }
+                let size = cmp::min(buf.len(), self.limit - self.buf.len());
+                self.buf.extend_from_slice(&buf[..size]);
+                Ok(size)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let text = b"Hello World!";
+        let inner = LimitedWriter {
+            buf: Vec::new(),
+            limit: 5,
+        };
+        let mut encoder = Encoder::new(inner);
+        encoder.write_all(text).unwrap();
+
+        // The sync marker cannot be written yet
+        let e = encoder.sync_flush().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+        assert!(encoder.pending_output_len() > 0);
+
+        encoder.as_inner_mut().limit = usize::MAX;
+        encoder.sync_flush().unwrap();
+        assert_eq!(encoder.pending_output_len(), 0);
+        assert!(encoder.as_inner_ref().buf.ends_with(&[0, 0, 255, 255]));
+
+        let mut decoded = [0; 12];
+        Decoder::new(&encoder.as_inner_ref().buf[..])
+            .read_exact(&mut decoded)
+            .unwrap();
+        assert_eq!(&decoded, text);
+    }
+
+    #[test]
+    fn concatenating_not_final_segments_works() {
+        let segments = [
+            &b"Hello "[..],
+            &b""[..],
+            include_bytes!("../../README.md"),
+            &b"World!"[..],
+        ];
+        for options in deflate_encode_options() {
+            let mut encoded = Vec::new();
+            for (i, segment) in segments.iter().enumerate() {
+                let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+                encoder.write_all(segment).unwrap();
+                if i + 1 < segments.len() {
+                    encoded.extend(encoder.finish_not_final().unwrap());
+                } else {
+                    encoded.extend(encoder.finish().into_result().unwrap());
+                }
+            }
+
+            let mut decoded = Vec::new();
+            Decoder::new(&encoded[..])
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, segments.concat());
+        }
+    }
+
+    #[test]
+    fn strategies_work() {
+        let encode = |input: &[u8], options: EncodeOptions| {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(input).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut decoded = Vec::new();
+            Decoder::new(&encoded[..])
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, input);
+            encoded.len()
+        };
+
+        let mut x = 12345u32;
+        let mut random = move || {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x
+        };
+        let small_values = (0..200_000)
+            .map(|_| (random() >> 8) as u8 % 4)
+            .collect::<Vec<_>>();
+        let runs = (0..2_000)
+            .flat_map(|_| {
+                let r = random();
+                core::iter::repeat((r >> 8) as u8).take((r % 40) as usize)
+            })
+            .collect::<Vec<_>>();
+        let text = include_bytes!("../../README.md");
+
+        for input in [&small_values[..], &runs, text] {
+            for strategy in [
+                Strategy::Default,
+                Strategy::Filtered,
+                Strategy::HuffmanOnly,
+                Strategy::Rle,
+            ] {
+                encode(input, EncodeOptions::new().strategy(strategy));
+                encode(
+                    input,
+                    EncodeOptions::new()
+                        .fixed_huffman_codes()
+                        .strategy(strategy)
+                        .block_size(10_000),
+                );
+            }
+        }
+
+        let default_size = encode(&small_values, EncodeOptions::new());
+        for strategy in [Strategy::Filtered, Strategy::HuffmanOnly, Strategy::Rle] {
+            let size = encode(&small_values, EncodeOptions::new().strategy(strategy));
+            assert!(size < default_size, "{strategy:?}");
+        }
+        assert!(
+            encode(&runs, EncodeOptions::new().strategy(Strategy::Rle))
+                < encode(&runs, EncodeOptions::new())
+        );
+    }
+
+    #[test]
+    fn encode_into_borrowed_buffer_works() {
+        let payloads = [&b"Hello World!"[..], &b"foo bar baz"[..], &b""[..]];
+
+        let mut buf = Vec::new();
+        let mut ends = Vec::new();
+        for payload in &payloads {
+            let mut encoder = Encoder::new(&mut buf);
+            encoder.write_all(payload).unwrap();
+            let inner: &mut Vec<u8> = encoder.finish().into_result().unwrap();
+            ends.push(inner.len());
+        }
+
+        let mut start = 0;
+        for (payload, end) in payloads.iter().zip(ends) {
+            let mut decoded = Vec::new();
+            Decoder::new(&buf[start..end])
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, *payload);
+            start = end;
+        }
+    }
+
+    #[test]
+    #[allow(clippy::unused_io_amount)]
     fn test_issue_27() {
         // See: https://github.com/sile/libflate/issues/27
 
@@ -482,4 +1575,351 @@ mod tests {
             "fooooooooooooooooobarbazfooooooooooooooooobarbaz".as_bytes()
         );
     }
+
+    #[test]
+    fn checkpoint_and_resume_works() {
+        let text = include_bytes!("../../README.md");
+
+        let encode = |use_dictionary: bool| {
+            let mut encoder = Encoder::new(Vec::new());
+            encoder.write_all(text).unwrap();
+            let (buf, dictionary) = encoder.checkpoint().unwrap();
+            let prefix_len = buf.len();
+
+            let dictionary = if use_dictionary {
+                dictionary
+            } else {
+                DeflateDictionary::default()
+            };
+            let mut encoder = Encoder::resume(buf, dictionary);
+            encoder.write_all(text).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut decoded = Vec::new();
+            Decoder::new(&encoded[..])
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, [&text[..], &text[..]].concat());
+            encoded.len() - prefix_len
+        };
+        assert!(encode(true) < encode(false));
+    }
+
+    #[test]
+    fn checkpoint_keeps_window_after_flush() {
+        // The size of the input is a multiple of the flush threshold of the LZ77 encoder
+        let text = include_bytes!("../../README.md")
+            .iter()
+            .cycle()
+            .take(256 * 1024)
+            .copied()
+            .collect::<Vec<_>>();
+        let window = &text[text.len() - lz77::MAX_WINDOW_SIZE as usize..];
+
+        let resume = |dictionary: DeflateDictionary| {
+            let mut encoder = Encoder::resume(Vec::new(), dictionary);
+            encoder.write_all(window).unwrap();
+            encoder.finish().into_result().unwrap()
+        };
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().strategy(Strategy::HuffmanOnly),
+        ] {
+            let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+            encoder.write_all(&text).unwrap();
+            let (_, dictionary) = encoder.checkpoint().unwrap();
+            assert_eq!(dictionary.as_bytes(), window);
+            assert_eq!(
+                resume(dictionary),
+                resume(DeflateDictionary::new(text.clone()))
+            );
+
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.compress_slice(&text).unwrap();
+            let (_, dictionary) = encoder.checkpoint().unwrap();
+            assert_eq!(dictionary.as_bytes(), window);
+        }
+    }
+
+    #[test]
+    fn empty_input_works() {
+        for options in deflate_encode_options() {
+            let encoder = Encoder::with_options(Vec::new(), options);
+            let encoded = encoder.finish().into_result().unwrap();
+            assert!(!encoded.is_empty());
+
+            let mut decoded = Vec::new();
+            Decoder::new(&encoded[..])
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert!(decoded.is_empty());
+        }
+    }
+
+    #[test]
+    fn flush_block_works() {
+        let records = [&b"Hello World!"[..], b"", b"foo bar foo bar foo bar", b"a"];
+        for options in deflate_encode_options() {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            for record in records {
+                encoder.write_all(record).unwrap();
+                encoder.flush_block().unwrap();
+            }
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut block_sizes = Vec::new();
+            let mut size = 0;
+            for s in Decoder::new(&encoded[..]).symbols() {
+                match s.unwrap() {
+                    symbol::Symbol::Code(lz77::Code::Literal(_)) => size += 1,
+                    symbol::Symbol::Code(lz77::Code::Pointer { length, .. }) => {
+                        size += usize::from(length)
+                    }
+                    symbol::Symbol::EndOfBlock => block_sizes.push(core::mem::take(&mut size)),
+                }
+            }
+            assert_eq!(block_sizes, [12, 23, 1, 0]);
+        }
+    }
+
+    #[test]
+    fn encoded_output_is_stable() {
+        // Any change of these vectors alters the output of the encoder (see "Deterministic output" in the crate docs)
+        let inputs: [&[u8]; 3] = [
+            b"",
+            b"Hello World! Hello World!",
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaabcabcabcabcabc",
+        ];
+        let small_lz77 = lz77::DefaultLz77EncoderBuilder::new().max_length(8).build();
+        let vectors: [(EncodeOptions, [&[u8]; 3]); 6] = [
+            (
+                EncodeOptions::new().no_compression(),
+                [
+                    &[1, 0, 0, 255, 255],
+                    &[
+                        1, 25, 0, 230, 255, 72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+                        32, 72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+                    ],
+                    &[
+                        1, 46, 0, 209, 255, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97,
+                        97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 97, 98,
+                        99, 97, 98, 99, 97, 98, 99, 97, 98, 99, 97, 98, 99,
+                    ],
+                ],
+            ),
+            (
+                EncodeOptions::new().fixed_huffman_codes(),
+                [
+                    &[3, 0],
+                    &[
+                        243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 84, 64, 230, 0, 0,
+                    ],
+                    &[75, 36, 0, 146, 146, 145, 17, 0],
+                ],
+            ),
+            (
+                EncodeOptions::new(),
+                [
+                    &[5, 192, 129, 0, 0, 0, 0, 0, 144, 255, 107, 0],
+                    &[
+                        77, 199, 49, 13, 0, 0, 8, 3, 65, 43, 197, 13, 14, 48, 0, 219, 39, 77, 240,
+                        63, 176, 50, 94, 14, 88, 229, 165, 67, 31, 7,
+                    ],
+                    &[
+                        133, 194, 49, 13, 0, 0, 0, 194, 48, 173, 128, 127, 15, 188, 251, 214, 52,
+                        162, 227, 3,
+                    ],
+                ],
+            ),
+            (
+                EncodeOptions::new()
+                    .fixed_huffman_codes()
+                    .strategy(Strategy::HuffmanOnly),
+                [
+                    &[3, 0],
+                    &[
+                        243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 84, 240, 72, 205, 201,
+                        201, 87, 8, 207, 47, 202, 73, 81, 4, 0,
+                    ],
+                    &[
+                        75, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76,
+                        76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 76, 74, 78, 76, 74, 78,
+                        76, 74, 78, 76, 74, 78, 76, 74, 6, 0,
+                    ],
+                ],
+            ),
+            (
+                EncodeOptions::new()
+                    .fixed_huffman_codes()
+                    .strategy(Strategy::Rle),
+                [
+                    &[3, 0],
+                    &[
+                        243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 84, 240, 72, 205, 201,
+                        201, 87, 8, 207, 47, 202, 73, 81, 4, 0,
+                    ],
+                    &[
+                        75, 36, 0, 146, 146, 19, 147, 146, 19, 147, 146, 19, 147, 146, 19, 147,
+                        146, 1,
+                    ],
+                ],
+            ),
+            (
+                EncodeOptions::with_lz77(small_lz77).fixed_huffman_codes(),
+                [
+                    &[3, 0],
+                    &[
+                        243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 84, 128, 113, 64, 28,
+                        0,
+                    ],
+                    &[75, 132, 1, 24, 128, 1, 40, 72, 74, 134, 33, 16, 2, 0],
+                ],
+            ),
+        ];
+        for (options, expected) in vectors {
+            for (input, expected) in inputs.iter().zip(expected) {
+                // Writing byte by byte must not change the output
+                for chunk_size in [input.len().max(1), 1] {
+                    let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+                    encoder.write_all_chunks(input.chunks(chunk_size)).unwrap();
+                    let encoded = encoder.finish().into_result().unwrap();
+                    assert_eq!(encoded, expected);
+                }
+
+                let mut decoded = Vec::new();
+                Decoder::new(expected).read_to_end(&mut decoded).unwrap();
+                assert_eq!(decoded, *input);
+            }
+        }
+    }
+
+    #[test]
+    fn on_progress_works() {
+        use alloc::sync::Arc;
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        let text = include_bytes!("../../README.md");
+        let calls = Arc::new(AtomicU64::new(0));
+        let last = Arc::new([AtomicU64::new(0), AtomicU64::new(0)]);
+
+        let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().block_size(1000));
+        let (c, l) = (Arc::clone(&calls), Arc::clone(&last));
+        encoder.on_progress(move |bytes_in, bytes_out| {
+            assert!(bytes_in >= l[0].swap(bytes_in, Ordering::SeqCst));
+            assert!(bytes_out >= l[1].swap(bytes_out, Ordering::SeqCst));
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+        for chunk in text.chunks(333) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let encoded = encoder.finish().into_result().unwrap();
+
+        assert!(calls.load(Ordering::SeqCst) > 1);
+        assert_eq!(last[0].load(Ordering::SeqCst), text.len() as u64);
+        assert_eq!(last[1].load(Ordering::SeqCst), encoded.len() as u64);
+    }
+
+    #[test]
+    fn on_progress_accepts_non_sync_callback() {
+        use core::cell::Cell;
+
+        // `Cell` is `Send` but not `Sync`
+        let calls = Cell::new(0);
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.on_progress(move |_, _| calls.set(calls.get() + 1));
+        encoder.write_all(b"Hello World!").unwrap();
+        encoder.finish().into_result().unwrap();
+    }
+
+    #[test]
+    fn pending_input_works() {
+        for options in deflate_encode_options() {
+            let mut encoder = Encoder::with_options(Vec::new(), options.block_size(10));
+            assert_eq!(encoder.pending_input(), 0);
+            encoder.write_all(b"Hello ").unwrap();
+            assert_eq!(encoder.pending_input(), 6);
+            encoder.write_all(b"World!").unwrap();
+            assert_eq!(encoder.pending_input(), 2);
+            encoder.flush_block().unwrap();
+            assert_eq!(encoder.pending_input(), 0);
+        }
+    }
+
+    #[test]
+    fn flush_message_works() {
+        use alloc::{format, vec};
+
+        let messages = (0..10)
+            .map(|i| format!("GET /items/{i} HTTP/1.1\r\nHost: example.com\r\nAccept: */*\r\n\r\n"))
+            .collect::<Vec<_>>();
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().fixed_huffman_codes(),
+            EncodeOptions::new().strategy(Strategy::Filtered),
+        ] {
+            let encode = |rolling: bool| {
+                let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+                let mut sizes = Vec::new();
+                for (i, message) in messages.iter().enumerate() {
+                    let start = encoder.as_inner_ref().len();
+                    encoder.write_all(message.as_bytes()).unwrap();
+                    if rolling {
+                        encoder.flush_message().unwrap();
+                    } else {
+                        encoder.sync_flush().unwrap();
+                    }
+                    sizes.push(encoder.as_inner_ref().len() - start);
+
+                    // The messages written so far can be decoded
+                    let expected = messages[..=i].concat();
+                    let mut decoder = Decoder::new(&encoder.as_inner_ref()[..]);
+                    let mut decoded = vec![0; expected.len()];
+                    decoder.read_exact(&mut decoded).unwrap();
+                    assert_eq!(decoded, expected.as_bytes());
+                }
+                let encoded = encoder.finish().into_result().unwrap();
+                let mut decoded = Vec::new();
+                Decoder::new(&encoded[..])
+                    .read_to_end(&mut decoded)
+                    .unwrap();
+                assert_eq!(decoded, messages.concat().as_bytes());
+                sizes
+            };
+
+            let rolling = encode(true);
+            let independent = encode(false);
+            assert_eq!(rolling[0], independent[0]);
+            for (rolling, independent) in rolling.into_iter().zip(independent).skip(1) {
+                assert!(
+                    rolling < independent,
+                    "rolling={rolling}, independent={independent}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn compress_slice_works() {
+        let text = include_bytes!("../../README.md").repeat(200);
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().no_compression(),
+            EncodeOptions::new().fixed_huffman_codes(),
+            EncodeOptions::new().strategy(Strategy::Filtered),
+            EncodeOptions::new().strategy(Strategy::Rle),
+            EncodeOptions::new().block_size(100_000),
+        ] {
+            let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+            encoder.write_all(b"prefix").unwrap();
+            encoder.compress_slice(&text).unwrap();
+            encoder.compress_slice(&text[..1000]).unwrap();
+            let borrowed = encoder.finish().into_result().unwrap();
+
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(b"prefix").unwrap();
+            encoder.write_all(&text).unwrap();
+            encoder.write_all(&text[..1000]).unwrap();
+            assert_eq!(encoder.finish().into_result().unwrap(), borrowed);
+        }
+    }
 }
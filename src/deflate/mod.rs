@@ -19,10 +19,21 @@
 //!
 //! assert_eq!(decoded_data, b"Hello World!");
 //! ```
+pub use self::decode::peek_first_block_type;
 pub use self::decode::Decoder;
+pub use self::decode::MatchStats;
+pub use self::decode::Symbols;
+pub use self::encode::compressed_len;
+pub use self::encode::DeflateDictionary;
 pub use self::encode::EncodeOptions;
 pub use self::encode::Encoder;
+pub use self::encode::Strategy;
 pub use self::encode::DEFAULT_BLOCK_SIZE;
+pub use self::symbol::write_symbol;
+pub use self::symbol::BitWriter;
+pub use self::symbol::Symbol;
+pub use self::symbol::SymbolCodes;
+pub use self::symbol::{DISTANCE_CODE_TABLE, LENGTH_CODE_TABLE};
 
 mod decode;
 mod encode;
@@ -31,10 +42,16 @@ pub(crate) mod symbol;
 #[cfg(test)]
 pub(crate) mod test_data;
 
-#[derive(Debug, Clone, Copy)]
-enum BlockType {
+/// The type of a DEFLATE block (i.e., the BTYPE field of the block header).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockType {
+    /// Non-compressed (stored) block.
     Raw = 0b00,
+
+    /// Block compressed with fixed Huffman codes.
     Fixed = 0b01,
+
+    /// Block compressed with dynamic Huffman codes.
     Dynamic = 0b10,
 }
 
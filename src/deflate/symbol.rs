@@ -1,8 +1,10 @@
+use super::BlockType;
 use crate::bit;
 use crate::huffman;
 use crate::huffman::Builder;
 use crate::lz77;
-use alloc::{boxed::Box, vec::Vec};
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
 use core::{cmp, iter, ops::Range};
 use core2::io;
 
@@ -19,7 +21,11 @@ const BITWIDTH_CODE_ORDER: [usize; 19] = [
 
 const END_OF_BLOCK: u16 = 256;
 
-const LENGTH_TABLE: [(u16, u8); 29] = [
+/// The base lengths and the numbers of extra bits of the length codes (257..=285).
+///
+/// The `i`-th entry corresponds to the code `257 + i`.
+/// See [RFC-1951 Section 3.2.5](https://tools.ietf.org/html/rfc1951#section-3.2.5).
+pub const LENGTH_CODE_TABLE: [(u16, u8); 29] = [
     (3, 0),
     (4, 0),
     (5, 0),
@@ -53,7 +59,11 @@ const LENGTH_TABLE: [(u16, u8); 29] = [
 
 const MAX_DISTANCE_CODE_COUNT: usize = 30;
 
-const DISTANCE_TABLE: [(u16, u8); 30] = [
+/// The base distances and the numbers of extra bits of the distance codes (0..=29).
+///
+/// The `i`-th entry corresponds to the code `i`.
+/// See [RFC-1951 Section 3.2.5](https://tools.ietf.org/html/rfc1951#section-3.2.5).
+pub const DISTANCE_CODE_TABLE: [(u16, u8); 30] = [
     (1, 0),
     (2, 0),
     (3, 0),
@@ -86,13 +96,17 @@ const DISTANCE_TABLE: [(u16, u8); 30] = [
     (24_577, 13),
 ];
 
-#[derive(Debug, PartialEq, Eq)]
+/// A symbol in a compressed DEFLATE block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Symbol {
+    /// End of the current block.
     EndOfBlock,
+
+    /// LZ77 code (a literal byte or a backward pointer).
     Code(lz77::Code),
 }
 impl Symbol {
-    pub fn code(&self) -> u16 {
+    pub(crate) fn code(&self) -> u16 {
         match *self {
             Symbol::Code(lz77::Code::Literal(b)) => u16::from(b),
             Symbol::EndOfBlock => 256,
@@ -108,7 +122,7 @@ impl Symbol {
             },
         }
     }
-    pub fn extra_lengh(&self) -> Option<(u8, u16)> {
+    pub(crate) fn extra_lengh(&self) -> Option<(u8, u16)> {
         if let Symbol::Code(lz77::Code::Pointer { length, .. }) = *self {
             match length {
                 3..=10 | 258 => None,
@@ -123,7 +137,7 @@ impl Symbol {
             None
         }
     }
-    pub fn distance(&self) -> Option<(u8, u8, u16)> {
+    pub(crate) fn distance(&self) -> Option<(u8, u8, u16)> {
         if let Symbol::Code(lz77::Code::Pointer {
             backward_distance: distance,
             ..
@@ -153,13 +167,14 @@ impl Symbol {
         }
     }
 }
+
 impl From<lz77::Code> for Symbol {
     fn from(code: lz77::Code) -> Self {
         Symbol::Code(code)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Encoder {
     literal: huffman::Encoder,
     distance: huffman::Encoder,
@@ -181,9 +196,278 @@ impl Encoder {
         }
         Ok(())
     }
+    fn has_code(&self, symbol: &Symbol) -> bool {
+        self.literal.has_code(symbol.code())
+            && symbol
+                .distance()
+                .map_or(true, |(code, _, _)| self.distance.has_code(u16::from(code)))
+    }
 }
 
+/// Huffman codes used to encode the symbols of a compressed DEFLATE block.
+///
+/// This is useful to estimate the size of a block under different strategies.
+///
+/// # Examples
+/// ```
+/// use libflate::deflate::{Symbol, SymbolCodes};
+/// use libflate::lz77::Code;
+///
+/// let symbols = [
+///     Symbol::Code(Code::Literal(b'a')),
+///     Symbol::Code(Code::Pointer { length: 10, backward_distance: 1 }),
+///     Symbol::EndOfBlock,
+/// ];
+///
+/// // Literal 'a' (8 bits), length 10 (7 bits), distance 1 (5 bits) and end of block (7 bits)
+/// let fixed = SymbolCodes::fixed();
+/// assert_eq!(fixed.bit_cost(&symbols), 8 + 7 + 5 + 7);
+///
+/// let dynamic = SymbolCodes::dynamic(&symbols).unwrap();
+/// assert_eq!(dynamic.bit_cost(&symbols), 2 + 2 + 1 + 1);
+/// assert_eq!(dynamic.literal_bitwidthes()[usize::from(b'a')], 2);
+/// ```
 #[derive(Debug)]
+pub struct SymbolCodes {
+    block_type: BlockType,
+    encoder: Encoder,
+}
+impl SymbolCodes {
+    /// Returns the fixed Huffman codes defined by RFC-1951.
+    pub fn fixed() -> Self {
+        let encoder = FixedHuffmanCodec
+            .build(&[])
+            .expect("The fixed Huffman codes are always valid")
+            .into_owned();
+        SymbolCodes {
+            block_type: BlockType::Fixed,
+            encoder,
+        }
+    }
+
+    /// Builds the dynamic Huffman codes optimized for `symbols`,
+    /// in the same way as [`Encoder`](crate::deflate::Encoder) does.
+    pub fn dynamic(symbols: &[Symbol]) -> io::Result<Self> {
+        let encoder = DynamicHuffmanCodec.build(symbols)?.into_owned();
+        Ok(SymbolCodes {
+            block_type: BlockType::Dynamic,
+            encoder,
+        })
+    }
+
+    /// Builds the dynamic Huffman codes from the code lengths of the literal/length alphabet
+    /// and the distance alphabet.
+    ///
+    /// `literal` must contain `257..=286` code lengths and `distance` must contain `1..=30` code lengths.
+    /// Each of the code lengths must form a complete (i.e., neither over-subscribed nor incomplete) prefix code
+    /// whose lengths are at most 15, and the end-of-block symbol (`256`) must have a code.
+    /// Otherwise an `InvalidData` error is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate::SymbolCodes;
+    ///
+    /// let mut literal = vec![0; 257];
+    /// literal[usize::from(b'a')] = 1;
+    /// literal[256] = 1;
+    /// let codes = SymbolCodes::from_bitwidthes(&literal, &[1]).unwrap();
+    /// assert_eq!(codes.literal_bitwidthes()[256], 1);
+    ///
+    /// // A block without distance codes still needs a distance code length
+    /// assert!(SymbolCodes::from_bitwidthes(&literal, &[0]).is_ok());
+    /// assert!(SymbolCodes::from_bitwidthes(&literal, &[]).is_err());
+    ///
+    /// // Over-subscribed
+    /// literal[usize::from(b'b')] = 1;
+    /// assert!(SymbolCodes::from_bitwidthes(&literal, &[1]).is_err());
+    /// ```
+    pub fn from_bitwidthes(literal: &[u8], distance: &[u8]) -> io::Result<Self> {
+        if !(257..=286).contains(&literal.len())
+            || !(1..=MAX_DISTANCE_CODE_COUNT).contains(&distance.len())
+        {
+            return Err(invalid_data_error!(
+                "Invalid number of code lengths: literal={}, distance={}",
+                literal.len(),
+                distance.len()
+            ));
+        }
+        if literal[256] == 0 {
+            return Err(invalid_data_error!("The end-of-block symbol has no code"));
+        }
+        let encoder = Encoder {
+            literal: huffman::EncoderBuilder::try_from_bitwidthes(literal)?,
+            distance: huffman::EncoderBuilder::try_from_bitwidthes(distance)?,
+        };
+        Ok(SymbolCodes {
+            block_type: BlockType::Dynamic,
+            encoder,
+        })
+    }
+
+    /// Returns the code lengths of the literal/length alphabet (`0..=285`).
+    ///
+    /// The length of a symbol which has no code is `0`.
+    pub fn literal_bitwidthes(&self) -> Vec<u8> {
+        let mut bitwidthes = self.encoder.literal.bitwidthes().collect::<Vec<_>>();
+        bitwidthes.resize(286, 0);
+        bitwidthes
+    }
+
+    /// Returns the code lengths of the distance alphabet (`0..=29`).
+    ///
+    /// The length of a symbol which has no code is `0`.
+    pub fn distance_bitwidthes(&self) -> Vec<u8> {
+        let mut bitwidthes = self.encoder.distance.bitwidthes().collect::<Vec<_>>();
+        bitwidthes.resize(MAX_DISTANCE_CODE_COUNT, 0);
+        bitwidthes
+    }
+
+    /// Returns the number of bits required to encode `symbols` with these codes.
+    ///
+    /// The extra bits of lengths and distances are included,
+    /// but the block header and the code tables of a dynamic block are not.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `symbols` contains a symbol which has no code.
+    pub fn bit_cost(&self, symbols: &[Symbol]) -> usize {
+        let literal = self.literal_bitwidthes();
+        let distance = self.distance_bitwidthes();
+        let width = |widths: &[u8], code: usize| {
+            let width = widths[code];
+            assert_ne!(width, 0, "No code is assigned to the symbol: {code}");
+            usize::from(width)
+        };
+        symbols
+            .iter()
+            .map(|s| {
+                let mut cost = width(&literal, usize::from(s.code()));
+                if let Some((bits, _)) = s.extra_lengh() {
+                    cost += usize::from(bits);
+                }
+                if let Some((code, bits, _)) = s.distance() {
+                    cost += width(&distance, usize::from(code)) + usize::from(bits);
+                }
+                cost
+            })
+            .sum()
+    }
+}
+
+/// A low-level writer of compressed DEFLATE blocks.
+///
+/// This is intended for building experimental encoders (e.g., with custom LZ77 parsing strategies)
+/// on top of the Huffman coding of this crate.
+/// A block is written by [`BitWriter::write_block_header`] followed by the symbols of the block
+/// (written by [`write_symbol`]), the last of which must be [`Symbol::EndOfBlock`].
+///
+/// # Examples
+/// ```
+/// use core2::io::Read;
+/// use libflate::deflate::{self, BitWriter, Decoder, Symbol, SymbolCodes};
+/// use libflate::lz77::Code;
+///
+/// let symbols = [
+///     Symbol::Code(Code::Literal(b'a')),
+///     Symbol::Code(Code::Literal(b'b')),
+///     Symbol::Code(Code::Pointer { length: 6, backward_distance: 2 }),
+///     Symbol::EndOfBlock,
+/// ];
+/// let codes = SymbolCodes::dynamic(&symbols).unwrap();
+///
+/// let mut writer = BitWriter::new(Vec::new());
+/// writer.write_block_header(true, &codes).unwrap();
+/// for symbol in &symbols {
+///     deflate::write_symbol(&mut writer, &codes, symbol).unwrap();
+/// }
+/// let encoded_data = writer.finish().unwrap();
+///
+/// let mut decoded_data = Vec::new();
+/// Decoder::new(&encoded_data[..]).read_to_end(&mut decoded_data).unwrap();
+/// assert_eq!(decoded_data, b"abababab");
+/// ```
+#[derive(Debug)]
+pub struct BitWriter<W> {
+    inner: bit::BitWriter<W>,
+}
+impl<W> BitWriter<W>
+where
+    W: io::Write,
+{
+    /// Makes a new writer instance.
+    pub fn new(inner: W) -> Self {
+        BitWriter {
+            inner: bit::BitWriter::new(inner),
+        }
+    }
+
+    /// Writes the lowest `bitwidth` bits of `bits` in the LSB-first order of DEFLATE.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bitwidth` is greater than `15`.
+    pub fn write_bits(&mut self, bitwidth: u8, bits: u16) -> io::Result<()> {
+        assert!(bitwidth <= 15, "Too large bitwidth: {bitwidth}");
+        self.inner
+            .write_bits(bitwidth, bits & ((1 << bitwidth) - 1))
+    }
+
+    /// Writes the header of a block compressed with `codes`.
+    ///
+    /// In the case of dynamic codes, the code tables are written as well.
+    pub fn write_block_header(&mut self, is_final: bool, codes: &SymbolCodes) -> io::Result<()> {
+        self.inner.write_bit(is_final)?;
+        self.inner.write_bits(2, codes.block_type as u16)?;
+        if codes.block_type == BlockType::Dynamic {
+            DynamicHuffmanCodec.save(&mut self.inner, &codes.encoder)?;
+        }
+        Ok(())
+    }
+
+    /// Pads the output to a byte boundary with zero bits and returns the inner stream.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.flush()?;
+        self.inner.write_pending()?;
+        Ok(self.inner.into_inner())
+    }
+}
+
+/// Writes `symbol` encoded with `codes` to `writer`, including the extra bits of the length and the distance.
+///
+/// An `InvalidInput` error is returned if `symbol` is out of the range of DEFLATE
+/// or `codes` does not have a code for it.
+pub fn write_symbol<W>(
+    writer: &mut BitWriter<W>,
+    codes: &SymbolCodes,
+    symbol: &Symbol,
+) -> io::Result<()>
+where
+    W: io::Write,
+{
+    if let Symbol::Code(lz77::Code::Pointer {
+        length,
+        backward_distance,
+    }) = *symbol
+    {
+        if !(3..=lz77::MAX_LENGTH).contains(&length)
+            || !(1..=lz77::MAX_DISTANCE).contains(&backward_distance)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The length or the distance of the pointer is out of range",
+            ));
+        }
+    }
+    if !codes.encoder.has_code(symbol) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No code is assigned to the symbol",
+        ));
+    }
+    codes.encoder.encode(&mut writer.inner, symbol)
+}
+
+#[derive(Debug, Clone)]
 pub struct Decoder {
     literal: huffman::Decoder,
     distance: huffman::Decoder,
@@ -213,7 +497,15 @@ impl Decoder {
         match decoded {
             0..=255 => Symbol::Code(lz77::Code::Literal(decoded as u8)),
             256 => Symbol::EndOfBlock,
-            286 | 287 => {
+            257..=285 => {
+                let (base, extra_bits) = LENGTH_CODE_TABLE[decoded as usize - 257];
+                let extra = reader.read_bits_unchecked(extra_bits);
+                Symbol::Code(lz77::Code::Pointer {
+                    length: base + extra,
+                    backward_distance: 0,
+                })
+            }
+            _ => {
                 #[cfg(feature = "std")]
                 let message = format!("The value {decoded} must not occur in compressed data");
                 #[cfg(not(feature = "std"))]
@@ -221,14 +513,6 @@ impl Decoder {
                 reader.set_last_error(io::Error::new(io::ErrorKind::InvalidData, message));
                 Symbol::EndOfBlock // dummy value
             }
-            length_code => {
-                let (base, extra_bits) = LENGTH_TABLE[length_code as usize - 257];
-                let extra = reader.read_bits_unchecked(extra_bits);
-                Symbol::Code(lz77::Code::Pointer {
-                    length: base + extra,
-                    backward_distance: 0,
-                })
-            }
         }
     }
     #[inline(always)]
@@ -237,27 +521,34 @@ impl Decoder {
         R: io::Read,
     {
         let decoded = self.distance.decode_unchecked(reader) as usize;
-        let (base, extra_bits) = DISTANCE_TABLE[decoded];
+        let Some(&(base, extra_bits)) = DISTANCE_CODE_TABLE.get(decoded) else {
+            #[cfg(feature = "std")]
+            let message = format!("The distance code {decoded} must not occur in compressed data");
+            #[cfg(not(feature = "std"))]
+            let message = "The distance codes [30, 31] must not occur in compressed data";
+            reader.set_last_error(io::Error::new(io::ErrorKind::InvalidData, message));
+            return 0; // dummy value
+        };
         let extra = reader.read_bits_unchecked(extra_bits);
         base + extra
     }
 }
 
+/// The codes are returned as `Cow`, so that the fixed ones can be shared without copying the tables.
 pub trait HuffmanCodec {
-    fn build(&self, symbols: &[Symbol]) -> io::Result<Encoder>;
+    fn build(&self, symbols: &[Symbol]) -> io::Result<Cow<'static, Encoder>>;
     fn save<W>(&self, writer: &mut bit::BitWriter<W>, codec: &Encoder) -> io::Result<()>
     where
         W: io::Write;
-    fn load<R>(&self, reader: &mut bit::BitReader<R>) -> io::Result<Decoder>
+    fn load<R>(&self, reader: &mut bit::BitReader<R>) -> io::Result<Cow<'static, Decoder>>
     where
         R: io::Read;
 }
 
 #[derive(Debug)]
 pub struct FixedHuffmanCodec;
-impl HuffmanCodec for FixedHuffmanCodec {
-    #[allow(unused_variables)]
-    fn build(&self, symbols: &[Symbol]) -> io::Result<Encoder> {
+impl FixedHuffmanCodec {
+    fn build_encoder() -> io::Result<Encoder> {
         let mut literal_builder = huffman::EncoderBuilder::new(288);
         for &(bitwidth, ref symbols, code_base) in &FIXED_LITERAL_OR_LENGTH_CODE_TABLE {
             for (code, symbol) in symbols
@@ -279,18 +570,8 @@ impl HuffmanCodec for FixedHuffmanCodec {
             distance: distance_builder.finish(),
         })
     }
-    #[allow(unused_variables)]
-    fn save<W>(&self, writer: &mut bit::BitWriter<W>, codec: &Encoder) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        Ok(())
-    }
-    #[allow(unused_variables)]
-    fn load<R>(&self, reader: &mut bit::BitReader<R>) -> io::Result<Decoder>
-    where
-        R: io::Read,
-    {
+
+    fn build_decoder() -> io::Result<Decoder> {
         let mut literal_builder = huffman::DecoderBuilder::new(9, None, Some(END_OF_BLOCK));
         for &(bitwidth, ref symbols, code_base) in &FIXED_LITERAL_OR_LENGTH_CODE_TABLE {
             for (code, symbol) in symbols
@@ -302,9 +583,10 @@ impl HuffmanCodec for FixedHuffmanCodec {
             }
         }
 
+        // The reserved codes 30 and 31 are also mapped so that they can be rejected explicitly
         let mut distance_builder =
             huffman::DecoderBuilder::new(5, literal_builder.safely_peek_bitwidth(), None);
-        for i in 0..30 {
+        for i in 0..32 {
             distance_builder.set_mapping(i, huffman::Code::new(5, i))?;
         }
 
@@ -314,11 +596,48 @@ impl HuffmanCodec for FixedHuffmanCodec {
         })
     }
 }
+impl HuffmanCodec for FixedHuffmanCodec {
+    #[allow(unused_variables)]
+    fn build(&self, symbols: &[Symbol]) -> io::Result<Cow<'static, Encoder>> {
+        // The fixed codes are built only once and shared if `std` is available
+        #[cfg(feature = "std")]
+        {
+            static ENCODER: std::sync::OnceLock<Encoder> = std::sync::OnceLock::new();
+            Ok(Cow::Borrowed(ENCODER.get_or_init(|| {
+                Self::build_encoder().expect("The fixed Huffman codes are always valid")
+            })))
+        }
+        #[cfg(not(feature = "std"))]
+        Self::build_encoder().map(Cow::Owned)
+    }
+    #[allow(unused_variables)]
+    fn save<W>(&self, writer: &mut bit::BitWriter<W>, codec: &Encoder) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        Ok(())
+    }
+    #[allow(unused_variables)]
+    fn load<R>(&self, reader: &mut bit::BitReader<R>) -> io::Result<Cow<'static, Decoder>>
+    where
+        R: io::Read,
+    {
+        #[cfg(feature = "std")]
+        {
+            static DECODER: std::sync::OnceLock<Decoder> = std::sync::OnceLock::new();
+            Ok(Cow::Borrowed(DECODER.get_or_init(|| {
+                Self::build_decoder().expect("The fixed Huffman codes are always valid")
+            })))
+        }
+        #[cfg(not(feature = "std"))]
+        Self::build_decoder().map(Cow::Owned)
+    }
+}
 
 #[derive(Debug)]
 pub struct DynamicHuffmanCodec;
 impl HuffmanCodec for DynamicHuffmanCodec {
-    fn build(&self, symbols: &[Symbol]) -> io::Result<Encoder> {
+    fn build(&self, symbols: &[Symbol]) -> io::Result<Cow<'static, Encoder>> {
         let mut literal_counts = [0; 286];
         let mut distance_counts = [0; 30];
         let mut empty_distance_table = true;
@@ -335,10 +654,10 @@ impl HuffmanCodec for DynamicHuffmanCodec {
             // See https://github.com/sile/libflate/issues/23 for more details.
             distance_counts[0] = 1;
         }
-        Ok(Encoder {
+        Ok(Cow::Owned(Encoder {
             literal: huffman::EncoderBuilder::from_frequencies(&literal_counts, 15)?,
             distance: huffman::EncoderBuilder::from_frequencies(&distance_counts, 15)?,
-        })
+        }))
     }
     fn save<W>(&self, writer: &mut bit::BitWriter<W>, codec: &Encoder) -> io::Result<()>
     where
@@ -384,7 +703,7 @@ impl HuffmanCodec for DynamicHuffmanCodec {
         }
         Ok(())
     }
-    fn load<R>(&self, reader: &mut bit::BitReader<R>) -> io::Result<Decoder>
+    fn load<R>(&self, reader: &mut bit::BitReader<R>) -> io::Result<Cow<'static, Decoder>>
     where
         R: io::Read,
     {
@@ -412,35 +731,30 @@ impl HuffmanCodec for DynamicHuffmanCodec {
         let bitwidth_decoder =
             huffman::DecoderBuilder::from_bitwidthes(&bitwidth_code_bitwidthes, Some(1), None)?;
 
-        let mut literal_code_bitwidthes = Vec::with_capacity(literal_code_count as usize);
-        while literal_code_bitwidthes.len() < literal_code_count as usize {
+        // The code lengths of the literal/length and distance alphabets form a single sequence,
+        // so a repeat code may cross the boundary between them, but not the end of the sequence.
+        let total_code_count = literal_code_count as usize + distance_code_count as usize;
+        let mut code_bitwidthes = Vec::with_capacity(total_code_count);
+        while code_bitwidthes.len() < total_code_count {
             let c = bitwidth_decoder.decode(reader)?;
-            let last = literal_code_bitwidthes.last().cloned();
-            literal_code_bitwidthes.extend(load_bitwidthes(reader, c, last)?);
+            let last = code_bitwidthes.last().cloned();
+            code_bitwidthes.extend(load_bitwidthes(reader, c, last)?);
         }
-
-        let mut distance_code_bitwidthes = literal_code_bitwidthes
-            .drain(literal_code_count as usize..)
-            .collect::<Vec<_>>();
-        while distance_code_bitwidthes.len() < distance_code_count as usize {
-            let c = bitwidth_decoder.decode(reader)?;
-            let last = distance_code_bitwidthes
-                .last()
-                .cloned()
-                .or_else(|| literal_code_bitwidthes.last().cloned());
-            distance_code_bitwidthes.extend(load_bitwidthes(reader, c, last)?);
-        }
-        if distance_code_bitwidthes.len() > distance_code_count as usize {
+        if code_bitwidthes.len() != total_code_count {
             #[cfg(feature = "std")]
             let message = format!(
-                "The length of `distance_code_bitwidthes` is too large: actual={}, expected={}",
-                distance_code_bitwidthes.len(),
+                "Repeated code lengths overrun the declared counts: actual={}, expected={} (HLIT+257={}, HDIST+1={})",
+                code_bitwidthes.len(),
+                total_code_count,
+                literal_code_count,
                 distance_code_count
             );
             #[cfg(not(feature = "std"))]
-            let message = "The length of `distance_code_bitwidthes` is too large";
+            let message = "Repeated code lengths overrun the declared counts";
             return Err(io::Error::new(io::ErrorKind::InvalidData, message));
         }
+        let distance_code_bitwidthes = code_bitwidthes.split_off(literal_code_count as usize);
+        let literal_code_bitwidthes = code_bitwidthes;
 
         let literal = huffman::DecoderBuilder::from_bitwidthes(
             &literal_code_bitwidthes,
@@ -452,7 +766,7 @@ impl HuffmanCodec for DynamicHuffmanCodec {
             Some(literal.safely_peek_bitwidth()),
             None,
         )?;
-        Ok(Decoder { literal, distance })
+        Ok(Cow::Owned(Decoder { literal, distance }))
     }
 }
 
@@ -460,24 +774,24 @@ fn load_bitwidthes<R>(
     reader: &mut bit::BitReader<R>,
     code: u16,
     last: Option<u8>,
-) -> io::Result<Box<dyn Iterator<Item = u8>>>
+) -> io::Result<iter::Take<iter::Repeat<u8>>>
 where
     R: io::Read,
 {
     Ok(match code {
-        0..=15 => Box::new(iter::once(code as u8)),
+        0..=15 => iter::repeat(code as u8).take(1),
         16 => {
             let count = reader.read_bits(2)? + 3;
             let last = last.ok_or_else(|| invalid_data_error!("No preceding value"))?;
-            Box::new(iter::repeat(last).take(count as usize))
+            iter::repeat(last).take(count as usize)
         }
         17 => {
             let zeros = reader.read_bits(3)? + 3;
-            Box::new(iter::repeat(0).take(zeros as usize))
+            iter::repeat(0).take(zeros as usize)
         }
         18 => {
             let zeros = reader.read_bits(7)? + 11;
-            Box::new(iter::repeat(0).take(zeros as usize))
+            iter::repeat(0).take(zeros as usize)
         }
         _ => unreachable!(),
     })
@@ -499,7 +813,7 @@ fn build_bitwidth_codes(
         (&codec.distance, distance_code_count),
     ] {
         for (i, c) in (0..size).map(|x| e.lookup(x).width).enumerate() {
-            if i > 0 && run_lens.last().map_or(false, |s| s.value == c) {
+            if i > 0 && run_lens.last().is_some_and(|s| s.value == c) {
                 run_lens.last_mut().unwrap().count += 1;
             } else {
                 run_lens.push(RunLength { value: c, count: 1 })
@@ -538,3 +852,169 @@ fn build_bitwidth_codes(
     }
     codes
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrunning_code_length_repeats_are_rejected() {
+        let mut writer = bit::BitWriter::new(Vec::new());
+        writer.write_bits(5, 0).unwrap(); // HLIT
+        writer.write_bits(5, 0).unwrap(); // HDIST
+        writer.write_bits(4, 14).unwrap(); // HCLEN
+        for &i in &BITWIDTH_CODE_ORDER[..18] {
+            // Only the code length codes `1` (code `0`) and `18` (code `1`) are used
+            writer
+                .write_bits(3, if i == 1 || i == 18 { 1 } else { 0 })
+                .unwrap();
+        }
+        for _ in 0..2 {
+            // 138 zeros (276 in total, but only 258 code lengths are declared)
+            writer.write_bits(1, 1).unwrap();
+            writer.write_bits(7, 127).unwrap();
+        }
+        writer.flush().unwrap();
+        let encoded = writer.into_inner();
+
+        let mut reader = bit::BitReader::new(&encoded[..]);
+        let e = DynamicHuffmanCodec.load(&mut reader).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        #[cfg(feature = "std")]
+        assert!(e.to_string().starts_with("Repeated code lengths overrun"));
+    }
+
+    #[test]
+    fn shared_fixed_codes_match_freshly_built_ones() {
+        for _ in 0..2 {
+            let shared = FixedHuffmanCodec.build(&[]).unwrap();
+            let fresh = FixedHuffmanCodec::build_encoder().unwrap();
+            assert!(shared.literal.bitwidthes().eq(fresh.literal.bitwidthes()));
+            assert!(shared.distance.bitwidthes().eq(fresh.distance.bitwidthes()));
+        }
+    }
+
+    #[test]
+    fn code_tables_cover_all_lengths_and_distances() {
+        for (table, max) in [
+            (&LENGTH_CODE_TABLE[..28], 257),
+            (&DISTANCE_CODE_TABLE[..], lz77::MAX_DISTANCE),
+        ] {
+            for w in table.windows(2) {
+                assert_eq!(w[0].0 + (1 << w[0].1), w[1].0);
+            }
+            let &(base, extra_bits) = table.last().unwrap();
+            assert!(base + ((1 << extra_bits) - 1) >= max);
+        }
+        assert_eq!(LENGTH_CODE_TABLE[28], (lz77::MAX_LENGTH, 0));
+
+        for length in 3..=lz77::MAX_LENGTH {
+            let symbol = Symbol::Code(lz77::Code::Pointer {
+                length,
+                backward_distance: 1,
+            });
+            let (base, _) = LENGTH_CODE_TABLE[symbol.code() as usize - 257];
+            let extra = symbol.extra_lengh().map_or(0, |x| x.1);
+            assert_eq!(base + extra, length);
+        }
+        for distance in 1..=lz77::MAX_DISTANCE {
+            let symbol = Symbol::Code(lz77::Code::Pointer {
+                length: 3,
+                backward_distance: distance,
+            });
+            let (code, _, extra) = symbol.distance().unwrap();
+            assert_eq!(DISTANCE_CODE_TABLE[code as usize].0 + extra, distance);
+        }
+    }
+
+    #[test]
+    fn bit_cost_matches_encoded_size() {
+        use lz77::Lz77Encode as _;
+
+        let mut symbols = Vec::new();
+        let mut lz77 = lz77::DefaultLz77Encoder::new();
+        lz77.encode(include_bytes!("../../README.md"), &mut symbols);
+        lz77.flush(&mut symbols);
+        symbols.push(Symbol::EndOfBlock);
+
+        for codes in [
+            SymbolCodes::fixed(),
+            SymbolCodes::dynamic(&symbols).unwrap(),
+        ] {
+            let mut writer = bit::BitWriter::new(Vec::new());
+            for s in &symbols {
+                codes.encoder.encode(&mut writer, s).unwrap();
+            }
+            // Terminates with a 1-bit so that the total bit length can be recovered
+            writer.write_bit(true).unwrap();
+            writer.flush().unwrap();
+            let encoded = writer.into_inner();
+            let last = *encoded.last().unwrap();
+            let bits = (encoded.len() - 1) * 8 + (8 - last.leading_zeros() as usize) - 1;
+            assert_eq!(codes.bit_cost(&symbols), bits);
+        }
+    }
+
+    #[test]
+    fn hand_assembled_blocks_can_be_decoded() {
+        use crate::deflate::Decoder;
+        use core2::io::Read as _;
+        use lz77::Lz77Encode as _;
+
+        let text = include_bytes!("../../README.md");
+        let mut symbols = Vec::new();
+        let mut lz77 = lz77::DefaultLz77Encoder::new();
+        lz77.encode(text, &mut symbols);
+        lz77.flush(&mut symbols);
+        symbols.push(Symbol::EndOfBlock);
+
+        // A fixed block followed by a dynamic final block
+        let second = symbols.split_off(symbols.len() / 2);
+        let mut first = symbols;
+        first.push(Symbol::EndOfBlock);
+
+        let mut writer = BitWriter::new(Vec::new());
+        let fixed = SymbolCodes::fixed();
+        writer.write_block_header(false, &fixed).unwrap();
+        for s in &first {
+            write_symbol(&mut writer, &fixed, s).unwrap();
+        }
+        let dynamic = SymbolCodes::dynamic(&second).unwrap();
+        writer.write_block_header(true, &dynamic).unwrap();
+        for s in &second {
+            write_symbol(&mut writer, &dynamic, s).unwrap();
+        }
+        let encoded = writer.finish().unwrap();
+
+        let mut decoded = Vec::new();
+        Decoder::new(&encoded[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+
+        // Symbols without codes and out-of-range pointers are rejected
+        let mut writer = BitWriter::new(Vec::new());
+        let literal_only =
+            SymbolCodes::dynamic(&[Symbol::Code(lz77::Code::Literal(b'a'))]).unwrap();
+        for s in [
+            Symbol::Code(lz77::Code::Literal(b'b')),
+            Symbol::Code(lz77::Code::Pointer {
+                length: 3,
+                backward_distance: 100,
+            }),
+        ] {
+            let e = write_symbol(&mut writer, &literal_only, &s).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        }
+        let e = write_symbol(
+            &mut writer,
+            &fixed,
+            &Symbol::Code(lz77::Code::Pointer {
+                length: 2,
+                backward_distance: 1,
+            }),
+        )
+        .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+}
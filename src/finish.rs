@@ -7,8 +7,43 @@ use core2::io::{self, Write};
 /// may have an error occurred during the computation.
 ///
 /// Logically, `Finish<T, E>` is equivalent to `Result<T, (T, E)>`.
+///
+/// The error type defaults to `io::Error`, which is the one returned by the encoders in this crate.
+/// Other error types can be used to report richer errors (see [`Finish::map_err`]).
+///
+/// # Examples
+/// ```
+/// use core2::io::{self, Write};
+/// use libflate::Finish;
+/// use libflate::gzip::Encoder;
+///
+/// #[derive(Debug)]
+/// enum MyError {
+///     Io(io::Error),
+///     TooLarge,
+/// }
+///
+/// fn encode(data: &[u8], limit: usize) -> Finish<Vec<u8>, MyError> {
+///     let mut encoder = Encoder::new(Vec::new()).unwrap();
+///     if let Err(e) = encoder.write_all(data) {
+///         return Finish::new(encoder.into_inner(), Some(MyError::Io(e)));
+///     }
+///     let too_large = data.len() > limit;
+///     encoder
+///         .finish()
+///         .map_err(MyError::Io)
+///         .and_then(|v| Finish::new(v, too_large.then_some(MyError::TooLarge)))
+/// }
+///
+/// assert!(encode(b"foo", 8).into_result().is_ok());
+/// assert!(matches!(encode(b"Hello World!", 8).into_result(), Err(MyError::TooLarge)));
+///
+/// // The error type can be omitted for `io::Error`
+/// let finish: Finish<Vec<u8>> = Encoder::new(Vec::new()).unwrap().finish();
+/// assert!(finish.into_result().is_ok());
+/// ```
 #[derive(Debug, Default, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
-pub struct Finish<T, E> {
+pub struct Finish<T, E = io::Error> {
     value: T,
     error: Option<E>,
 }
@@ -86,6 +121,66 @@ impl<T, E> Finish<T, E> {
             Ok(&self.value)
         }
     }
+
+    /// Maps the value by applying `f`, preserving the error (if any).
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::Finish;
+    ///
+    /// let succeeded = Finish::new("value", None as Option<()>);
+    /// assert_eq!(succeeded.map(str::len).into_result(), Ok(5));
+    ///
+    /// let failed = Finish::new("value", Some("error"));
+    /// assert_eq!(failed.map(str::len).unwrap(), (5, Some("error")));
+    /// ```
+    pub fn map<U, F>(self, f: F) -> Finish<U, E>
+    where
+        F: FnOnce(T) -> U,
+    {
+        Finish::new(f(self.value), self.error)
+    }
+
+    /// Maps the error (if any) by applying `f`, leaving the value untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::Finish;
+    ///
+    /// let failed = Finish::new("value", Some("error"));
+    /// assert_eq!(failed.map_err(str::len).into_result(), Err(5));
+    /// ```
+    pub fn map_err<F, O>(self, f: O) -> Finish<T, F>
+    where
+        O: FnOnce(E) -> F,
+    {
+        Finish::new(self.value, self.error.map(f))
+    }
+
+    /// Applies `f`, which may fail as well, to the value.
+    ///
+    /// If both `self` and the result of `f` have errors, the error of `self` is kept
+    /// because it occurred first.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::Finish;
+    ///
+    /// let succeeded = Finish::new("value", None);
+    /// let failed = succeeded.and_then(|v| Finish::new(v.len(), Some("second")));
+    /// assert_eq!(failed.unwrap(), (5, Some("second")));
+    ///
+    /// let failed = Finish::new("value", Some("first"));
+    /// let failed = failed.and_then(|v| Finish::new(v.len(), Some("second")));
+    /// assert_eq!(failed.unwrap(), (5, Some("first")));
+    /// ```
+    pub fn and_then<U, F>(self, f: F) -> Finish<U, E>
+    where
+        F: FnOnce(T) -> Finish<U, E>,
+    {
+        let (value, error) = f(self.value).unwrap();
+        Finish::new(value, self.error.or(error))
+    }
 }
 
 /// A wrapper struct that completes the processing of the underlying instance when drops.
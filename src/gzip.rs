@@ -19,18 +19,27 @@
 //!
 //! assert_eq!(decoded_data, b"Hello World!");
 //! ```
-use crate::checksum;
+use crate::checksum::{self, ChecksumMismatchPolicy};
 use crate::deflate;
 use crate::finish::{Complete, Finish};
 use crate::lz77;
-use alloc::{ffi::CString, vec::Vec};
-use core2::io;
+use crate::util::truncated;
+pub use crate::zlib::FlushMode;
+use alloc::{borrow::Cow, boxed::Box, ffi::CString, string::String, vec, vec::Vec};
+use core::fmt;
+use core2::io::{self, Read as _};
 #[cfg(feature = "std")]
 use std::time;
 
 const GZIP_ID: [u8; 2] = [31, 139];
 const COMPRESSION_METHOD_DEFLATE: u8 = 8;
 
+/// Size of the fixed-length header and the trailer of a member.
+const MIN_MEMBER_SIZE: u64 = 18;
+
+/// Size of the internal buffer of [`EventDecoder`].
+const EVENT_BUFFER_SIZE: usize = 8 * 1024;
+
 const OS_FAT: u8 = 0;
 const OS_AMIGA: u8 = 1;
 const OS_VMS: u8 = 2;
@@ -47,6 +56,9 @@ const OS_QDOS: u8 = 12;
 const OS_ACORN_RISCOS: u8 = 13;
 const OS_UNKNOWN: u8 = 255;
 
+/// Maximum number of unknown bytes tolerated before FHCRC in the lenient mode.
+const MAX_LENIENT_HEADER_JUNK_SIZE: usize = 1024;
+
 const F_TEXT: u8 = 0b00_0001;
 const F_HCRC: u8 = 0b00_0010;
 const F_EXTRA: u8 = 0b00_0100;
@@ -100,15 +112,19 @@ impl Trailer {
     pub fn crc32(&self) -> u32 {
         self.crc32
     }
+    pub fn input_size(&self) -> u32 {
+        self.input_size
+    }
     pub fn read_from<R>(mut reader: R) -> io::Result<Self>
     where
         R: io::Read,
     {
-        let mut buf = [0; 4];
-        reader.read_exact(&mut buf)?;
-        let crc32 = u32::from_le_bytes(buf);
-        reader.read_exact(&mut buf)?;
-        let input_size = u32::from_le_bytes(buf);
+        let mut buf = [0; 8];
+        reader
+            .read_exact(&mut buf)
+            .map_err(|e| truncated(e, TRUNCATED_IN_TRAILER))?;
+        let crc32 = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        let input_size = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]);
         Ok(Trailer { crc32, input_size })
     }
     fn write_to<W>(&self, mut writer: W) -> io::Result<()>
@@ -179,6 +195,26 @@ impl HeaderBuilder {
         self
     }
 
+    /// Sets the modification time from a `SystemTime` (e.g., the one of filesystem metadata).
+    ///
+    /// The time is converted to the seconds since the UNIX epoch, saturating to the range of `u32`:
+    /// a time before the epoch becomes `0`, and a time after `2106-02-07T06:28:15Z` becomes `u32::MAX`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use libflate::gzip::HeaderBuilder;
+    ///
+    /// let mtime = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    /// let header = HeaderBuilder::new().modification_system_time(mtime).finish();
+    /// assert_eq!(header.modification_time(), 1_700_000_000);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn modification_system_time(&mut self, modification_time: time::SystemTime) -> &mut Self {
+        self.header.modification_time = to_unix_seconds(modification_time);
+        self
+    }
+
     /// Sets the OS type.
     ///
     /// ```
@@ -236,6 +272,52 @@ impl HeaderBuilder {
         self
     }
 
+    /// Appends a subfield to the extra field.
+    ///
+    /// If the extra field has not been set, a new one is created.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::gzip::{Decoder, EncodeOptions, Encoder, ExtraSubField, HeaderBuilder};
+    ///
+    /// // BGZF-style `BC` subfield followed by another one
+    /// let header = HeaderBuilder::new()
+    ///     .add_extra_subfield(*b"BC", vec![0x1B, 0x00])
+    ///     .add_extra_subfield(*b"xy", vec![1, 2, 3])
+    ///     .finish();
+    ///
+    /// let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().header(header)).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// // XLEN (2 bytes) followed by the subfields (ID, LEN and data)
+    /// assert_eq!(
+    ///     &encoded_data[10..][..15],
+    ///     &[13, 0, b'B', b'C', 2, 0, 0x1B, 0x00, b'x', b'y', 3, 0, 1, 2, 3]
+    /// );
+    ///
+    /// let decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// let subfields = &decoder.header().extra_field().unwrap().subfields;
+    /// assert_eq!(
+    ///     subfields,
+    ///     &[
+    ///         ExtraSubField { id: *b"BC", data: vec![0x1B, 0x00] },
+    ///         ExtraSubField { id: *b"xy", data: vec![1, 2, 3] },
+    ///     ]
+    /// );
+    /// ```
+    pub fn add_extra_subfield(&mut self, id: [u8; 2], data: Vec<u8>) -> &mut Self {
+        self.header
+            .extra_field
+            .get_or_insert_with(|| ExtraField {
+                subfields: Vec::new(),
+            })
+            .subfields
+            .push(ExtraSubField { id, data });
+        self
+    }
+
     /// Sets the file name.
     ///
     /// # Examples
@@ -276,6 +358,46 @@ impl HeaderBuilder {
         self
     }
 
+    /// Sets the file name encoded as UTF-8.
+    ///
+    /// Although RFC-1952 specifies ISO 8859-1 (LATIN-1) for the file name,
+    /// many modern tools store UTF-8 strings.
+    ///
+    /// If `filename` contains a NUL byte, an `InvalidInput` error is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::HeaderBuilder;
+    ///
+    /// let header = HeaderBuilder::new().filename_str("café.txt").unwrap().finish();
+    /// assert_eq!(header.filename_str().unwrap(), "café.txt");
+    ///
+    /// assert!(HeaderBuilder::new().filename_str("foo\0bar").is_err());
+    /// ```
+    pub fn filename_str(&mut self, filename: &str) -> io::Result<&mut Self> {
+        self.header.filename = Some(to_cstring(filename)?);
+        Ok(self)
+    }
+
+    /// Sets the comment encoded as UTF-8.
+    ///
+    /// Although RFC-1952 specifies ISO 8859-1 (LATIN-1) for the comment,
+    /// many modern tools store UTF-8 strings.
+    ///
+    /// If `comment` contains a NUL byte, an `InvalidInput` error is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::HeaderBuilder;
+    ///
+    /// let header = HeaderBuilder::new().comment_str("日本語").unwrap().finish();
+    /// assert_eq!(header.comment_str().unwrap(), "日本語");
+    /// ```
+    pub fn comment_str(&mut self, comment: &str) -> io::Result<&mut Self> {
+        self.header.comment = Some(to_cstring(comment)?);
+        Ok(self)
+    }
+
     /// Returns the result header.
     pub fn finish(&self) -> Header {
         self.header.clone()
@@ -287,6 +409,22 @@ impl Default for HeaderBuilder {
     }
 }
 
+#[cfg(feature = "std")]
+fn to_unix_seconds(t: time::SystemTime) -> u32 {
+    t.duration_since(time::UNIX_EPOCH)
+        .map(|d| u32::try_from(d.as_secs()).unwrap_or(u32::MAX))
+        .unwrap_or(0)
+}
+
+fn to_cstring(s: &str) -> io::Result<CString> {
+    CString::new(s).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The string must not contain NUL bytes",
+        )
+    })
+}
+
 /// GZIP Header.
 #[derive(Debug, Clone)]
 pub struct Header {
@@ -340,6 +478,26 @@ impl Header {
         self.comment.as_ref()
     }
 
+    /// Returns the file name decoded as UTF-8.
+    ///
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+    /// Use [`Header::filename`] to get the raw bytes.
+    pub fn filename_str(&self) -> Option<Cow<'_, str>> {
+        self.filename
+            .as_ref()
+            .map(|s| String::from_utf8_lossy(s.as_bytes()))
+    }
+
+    /// Returns the comment decoded as UTF-8.
+    ///
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+    /// Use [`Header::comment`] to get the raw bytes.
+    pub fn comment_str(&self) -> Option<Cow<'_, str>> {
+        self.comment
+            .as_ref()
+            .map(|s| String::from_utf8_lossy(s.as_bytes()))
+    }
+
     fn flags(&self) -> u8 {
         [
             (F_TEXT, self.is_text),
@@ -387,13 +545,23 @@ impl Header {
         }
         Ok(())
     }
-    pub(crate) fn read_from<R>(mut reader: R) -> io::Result<Self>
+    /// Reads a header from `reader`.
+    ///
+    /// If `strict` is `false` and the header has FHCRC,
+    /// unknown bytes preceding the CRC16 are tolerated as long as the CRC16 matches.
+    pub(crate) fn read_from<R>(reader: R, strict: bool) -> io::Result<Self>
     where
         R: io::Read,
     {
+        let mut reader = RecordingReader {
+            inner: reader,
+            bytes: Vec::new(),
+        };
         let mut this = HeaderBuilder::new().finish();
         let mut buf = [0; 2 + 1 + 1 + 4 + 1 + 1];
-        reader.read_exact(&mut buf)?;
+        reader
+            .read_exact(&mut buf)
+            .map_err(|e| truncated(e, TRUNCATED_IN_HEADER))?;
         let id = &buf[0..2];
         if id != GZIP_ID {
             return Err(invalid_data_error!(
@@ -416,23 +584,31 @@ impl Header {
         this.compression_level = CompressionLevel::from_u8(buf[8]);
         this.os = Os::from_u8(buf[9]);
         if flags & F_EXTRA != 0 {
-            this.extra_field = Some(ExtraField::read_from(&mut reader)?);
+            let extra_field = ExtraField::read_from(&mut reader)
+                .map_err(|e| truncated(e, TRUNCATED_IN_FEXTRA))?;
+            this.extra_field = Some(extra_field);
         }
         if flags & F_NAME != 0 {
-            this.filename = Some(read_cstring(&mut reader)?);
+            let filename =
+                read_cstring(&mut reader).map_err(|e| truncated(e, TRUNCATED_IN_FNAME))?;
+            this.filename = Some(filename);
         }
         if flags & F_COMMENT != 0 {
-            this.comment = Some(read_cstring(&mut reader)?);
+            let comment =
+                read_cstring(&mut reader).map_err(|e| truncated(e, TRUNCATED_IN_FCOMMENT))?;
+            this.comment = Some(comment);
         }
         // Checksum verification is skipped during fuzzing
         // so that random data from fuzzer can reach actually interesting code.
         // Compilation flag 'fuzzing' is automatically set by all 3 Rust fuzzers.
         if flags & F_HCRC != 0 && cfg!(not(fuzzing)) {
             let mut buf = [0; 2];
-            reader.read_exact(&mut buf)?;
+            reader
+                .read_exact(&mut buf)
+                .map_err(|e| truncated(e, TRUNCATED_IN_FHCRC))?;
             let crc = u16::from_le_bytes(buf);
             let expected = this.crc16();
-            if crc != expected {
+            if crc != expected && (strict || !reader.recover_crc16()?) {
                 return Err(invalid_data_error!(
                     "CRC16 of GZIP header mismatched: value={}, \
                      expected={}",
@@ -446,6 +622,55 @@ impl Header {
     }
 }
 
+const TRUNCATED_IN_HEADER: &str = "GZIP stream is truncated in the fixed-length header";
+const TRUNCATED_IN_FEXTRA: &str = "GZIP stream is truncated in the FEXTRA field of the header";
+const TRUNCATED_IN_FNAME: &str = "GZIP stream is truncated in the FNAME field of the header";
+const TRUNCATED_IN_FCOMMENT: &str = "GZIP stream is truncated in the FCOMMENT field of the header";
+const TRUNCATED_IN_FHCRC: &str = "GZIP stream is truncated in the FHCRC field of the header";
+const TRUNCATED_IN_BODY: &str = "GZIP stream is truncated in the DEFLATE compressed data";
+const TRUNCATED_IN_TRAILER: &str = "GZIP stream is truncated in the trailer";
+
+/// A reader which records the bytes read through it.
+#[derive(Debug)]
+struct RecordingReader<R> {
+    inner: R,
+    bytes: Vec<u8>,
+}
+impl<R> RecordingReader<R>
+where
+    R: io::Read,
+{
+    /// Searches the position where the last two bytes are the CRC16 of the preceding bytes,
+    /// by reading up to [`MAX_LENIENT_HEADER_JUNK_SIZE`] bytes more.
+    ///
+    /// Returns `false` if no such position is found.
+    fn recover_crc16(&mut self) -> io::Result<bool> {
+        for _ in 0..=MAX_LENIENT_HEADER_JUNK_SIZE {
+            let (header, crc) = self.bytes.split_at(self.bytes.len() - 2);
+            let mut expected = checksum::Crc32::new();
+            expected.update(header);
+            if u16::from_le_bytes([crc[0], crc[1]]) == expected.value() as u16 {
+                return Ok(true);
+            }
+
+            let mut buf = [0; 1];
+            self.read_exact(&mut buf)
+                .map_err(|e| truncated(e, TRUNCATED_IN_FHCRC))?;
+        }
+        Ok(false)
+    }
+}
+impl<R> io::Read for RecordingReader<R>
+where
+    R: io::Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+}
+
 fn read_cstring<R>(mut reader: R) -> io::Result<CString>
 where
     R: io::Read,
@@ -635,19 +860,23 @@ impl Os {
 }
 
 /// Options for a GZIP encoder.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EncodeOptions<E>
 where
     E: lz77::Lz77Encode,
 {
     header: Header,
     options: deflate::EncodeOptions<E>,
+    flush_mode: FlushMode,
+    known_trailer: Option<Trailer>,
 }
 impl Default for EncodeOptions<lz77::DefaultLz77Encoder> {
     fn default() -> Self {
         EncodeOptions {
             header: HeaderBuilder::new().finish(),
             options: Default::default(),
+            flush_mode: FlushMode::None,
+            known_trailer: None,
         }
     }
 }
@@ -685,6 +914,8 @@ where
         EncodeOptions {
             header,
             options: deflate::EncodeOptions::with_lz77(lz77),
+            flush_mode: FlushMode::None,
+            known_trailer: None,
         }
     }
 
@@ -748,13 +979,166 @@ where
         self.options = self.options.fixed_huffman_codes();
         self
     }
+
+    /// Specifies the compression strategy.
+    ///
+    /// See [`deflate::Strategy`] for details.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::deflate::Strategy;
+    /// use libflate::gzip::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().strategy(Strategy::HuffmanOnly);
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    pub fn strategy(mut self, strategy: deflate::Strategy) -> Self {
+        self.options = self.options.strategy(strategy);
+        self
+    }
+
+    /// Specifies flush mode.
+    ///
+    /// If `FlushMode::Sync` is specified, `Encoder::flush` terminates the current DEFLATE block
+    /// and aligns the output to a byte boundary, so that a decoder can decode
+    /// all the data written so far without waiting for the end of the member.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::gzip::{Encoder, EncodeOptions, FlushMode};
+    ///
+    /// let options = EncodeOptions::new().flush_mode(FlushMode::Sync);
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    pub fn flush_mode(mut self, mode: FlushMode) -> Self {
+        self.flush_mode = mode;
+        self
+    }
+
+    /// Specifies the CRC32 and the size of the whole uncompressed data in advance.
+    ///
+    /// The encoder writes `crc32` and `input_size` to the trailer as is,
+    /// and skips computing the checksum of the written data.
+    /// The values are not verified, so the caller must make sure that they match the written data
+    /// (otherwise the resulting GZIP stream will be rejected by decoders).
+    ///
+    /// # Example
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::gzip::{Decoder, Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().skip_crc_with(0x1C291CA3, 12);
+    /// let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut buf = Vec::new();
+    /// Decoder::new(&encoded_data[..]).unwrap().read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn skip_crc_with(mut self, crc32: u32, input_size: u32) -> Self {
+        self.known_trailer = Some(Trailer { crc32, input_size });
+        self
+    }
+}
+
+/// Builder of [`Encoder`].
+///
+/// See [`Encoder::builder`].
+#[derive(Debug)]
+pub struct EncoderBuilder<W> {
+    inner: W,
+    header: HeaderBuilder,
+    level: Option<lz77::CompressionLevel>,
+}
+impl<W> EncoderBuilder<W>
+where
+    W: io::Write,
+{
+    fn new(inner: W) -> Self {
+        EncoderBuilder {
+            inner,
+            header: HeaderBuilder::new(),
+            level: None,
+        }
+    }
+
+    /// Sets the file name stored in the header.
+    pub fn filename(mut self, filename: CString) -> Self {
+        self.header.filename(filename);
+        self
+    }
+
+    /// Sets the comment stored in the header.
+    pub fn comment(mut self, comment: CString) -> Self {
+        self.header.comment(comment);
+        self
+    }
+
+    /// Sets the modification time (UNIX timestamp) stored in the header.
+    pub fn modification_time(mut self, modification_time: u32) -> Self {
+        self.header.modification_time(modification_time);
+        self
+    }
+
+    /// Sets the modification time stored in the header from a `SystemTime`.
+    ///
+    /// See [`HeaderBuilder::modification_system_time`] for the conversion.
+    #[cfg(feature = "std")]
+    pub fn modification_system_time(mut self, modification_time: time::SystemTime) -> Self {
+        self.header.modification_system_time(modification_time);
+        self
+    }
+
+    /// Sets the OS type stored in the header.
+    pub fn os(mut self, os: Os) -> Self {
+        self.header.os(os);
+        self
+    }
+
+    /// Sets the compression level.
+    ///
+    /// `CompressionLevel::None` disables compression,
+    /// `CompressionLevel::Fast` compresses with fixed Huffman codes,
+    /// and the others compress with dynamic Huffman codes (the default).
+    /// The compression level field of the header is set accordingly.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Write;
+    /// use libflate::gzip::{CompressionLevel, Encoder};
+    /// use libflate::lz77;
+    ///
+    /// let encoder = Encoder::builder(Vec::new())
+    ///     .level(lz77::CompressionLevel::Fast)
+    ///     .finish()
+    ///     .unwrap();
+    /// assert_eq!(encoder.header().compression_level(), CompressionLevel::Fastest);
+    /// ```
+    pub fn level(mut self, level: lz77::CompressionLevel) -> Self {
+        self.header.header.compression_level = From::from(level.clone());
+        self.level = Some(level);
+        self
+    }
+
+    /// Makes a new encoder and writes the header to the inner stream.
+    pub fn finish(self) -> io::Result<Encoder<W>> {
+        let options = match self.level {
+            Some(lz77::CompressionLevel::None) => EncodeOptions::new().no_compression(),
+            Some(lz77::CompressionLevel::Fast) => EncodeOptions::new().fixed_huffman_codes(),
+            _ => EncodeOptions::new(),
+        };
+        Encoder::with_options(self.inner, options.header(self.header.finish()))
+    }
 }
 
 /// GZIP encoder.
 pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
     header: Header,
+    flush_mode: FlushMode,
     crc32: checksum::Crc32,
     input_size: u32,
+    known_trailer: Option<Trailer>,
     writer: deflate::Encoder<W, E>,
 }
 impl<W> Encoder<W, lz77::DefaultLz77Encoder>
@@ -777,6 +1161,70 @@ where
     pub fn new(inner: W) -> io::Result<Self> {
         Self::with_options(inner, EncodeOptions::new())
     }
+
+    /// Makes a new builder of an encoder which writes the encoded GZIP stream to `inner`.
+    ///
+    /// This is a shorthand for building a `Header` and `EncodeOptions` by hand.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(not(feature = "std"))]
+    /// extern crate alloc;
+    /// #[cfg(not(feature = "std"))]
+    /// use alloc::ffi::CString;
+    /// #[cfg(feature = "std")]
+    /// use std::ffi::CString;
+    /// use core2::io::Write;
+    /// use libflate::gzip::{Decoder, Encoder};
+    ///
+    /// let mut encoder = Encoder::builder(Vec::new())
+    ///     .filename(CString::new("hello.txt").unwrap())
+    ///     .modification_time(123)
+    ///     .finish()
+    ///     .unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// assert_eq!(decoder.header().filename(), Some(&CString::new("hello.txt").unwrap()));
+    /// assert_eq!(decoder.header().modification_time(), 123);
+    /// ```
+    pub fn builder(inner: W) -> EncoderBuilder<W> {
+        EncoderBuilder::new(inner)
+    }
+}
+#[cfg(feature = "std")]
+impl<W> Encoder<W, lz77::DefaultLz77Encoder>
+where
+    W: io::Write + io::Seek,
+{
+    /// Makes a new encoder instance which appends a new member to the end of `inner` (e.g., an existing GZIP file).
+    ///
+    /// The resulting data is a multi-member GZIP stream,
+    /// which can be read by [`MultiDecoder`] (and standard `gunzip`) as the concatenation of the members.
+    /// Note that a `File` must be opened with write access for appending.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::{Cursor, Read, Write};
+    /// use libflate::gzip::{Encoder, MultiDecoder};
+    ///
+    /// let mut file = Cursor::new(Vec::new());
+    /// for payload in [&b"foo"[..], b"bar", b"baz"] {
+    ///     let mut encoder = Encoder::append(&mut file).unwrap();
+    ///     encoder.write_all(payload).unwrap();
+    ///     encoder.finish().into_result().unwrap();
+    ///     file.set_position(0);
+    /// }
+    ///
+    /// let mut decoded_data = Vec::new();
+    /// MultiDecoder::new(&file.get_ref()[..]).unwrap().read_to_end(&mut decoded_data).unwrap();
+    /// assert_eq!(decoded_data, b"foobarbaz");
+    /// ```
+    pub fn append(mut inner: W) -> io::Result<Self> {
+        inner.seek(io::SeekFrom::End(0))?;
+        Self::new(inner)
+    }
 }
 impl<W, E> Encoder<W, E>
 where
@@ -805,8 +1253,10 @@ where
         options.header.write_to(&mut inner)?;
         Ok(Encoder {
             header: options.header.clone(),
+            flush_mode: options.flush_mode,
             crc32: checksum::Crc32::new(),
             input_size: 0,
+            known_trailer: options.known_trailer,
             writer: deflate::Encoder::with_options(inner, options.options),
         })
     }
@@ -824,6 +1274,20 @@ where
         &self.header
     }
 
+    /// Writes all the chunks in `chunks` in order.
+    ///
+    /// The output of the encoder is identical regardless of how the input is chunked.
+    /// See [`deflate::Encoder::write_all_chunks`] for more details.
+    pub fn write_all_chunks<'a, I>(&mut self, chunks: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        for chunk in chunks {
+            io::Write::write_all(self, chunk)?;
+        }
+        Ok(())
+    }
+
     /// Writes the GZIP trailer and returns the inner stream.
     ///
     /// # Examples
@@ -856,10 +1320,10 @@ where
     /// std::io::copy(&mut &plain[..], &mut encoder).unwrap();
     /// ```
     pub fn finish(self) -> Finish<W, io::Error> {
-        let trailer = Trailer {
+        let trailer = self.known_trailer.unwrap_or(Trailer {
             crc32: self.crc32.value(),
             input_size: self.input_size,
-        };
+        });
         let mut inner = finish_try!(self.writer.finish());
         match trailer.write_to(&mut inner).and_then(|_| inner.flush()) {
             Ok(_) => Finish::new(inner, None),
@@ -867,6 +1331,63 @@ where
         }
     }
 
+    /// Writes already-compressed DEFLATE blocks verbatim.
+    ///
+    /// `raw_deflate` must be a sequence of complete non-final DEFLATE blocks which ends at a byte boundary
+    /// (e.g., the data produced by `deflate::Encoder::checkpoint`).
+    /// `uncompressed_crc` and `uncompressed_len` must be the CRC32 and the size of the data represented by the blocks,
+    /// and they are reflected to the trailer of this GZIP member.
+    ///
+    /// The blocks must not contain back-references to the data outside of themselves,
+    /// because the data preceding them in this member is not the one they have been compressed with
+    /// (e.g., the data produced by `deflate::Encoder::resume` with a non-empty dictionary must not be passed).
+    ///
+    /// Before writing the blocks, the data buffered in this encoder is flushed
+    /// and the stream is aligned to a byte boundary (as with [`FlushMode::Sync`]).
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::{deflate, gzip};
+    ///
+    /// let mut deflate_encoder = deflate::Encoder::new(Vec::new());
+    /// deflate_encoder.write_all(b"World!").unwrap();
+    /// let (blocks, _) = deflate_encoder.checkpoint().unwrap();
+    ///
+    /// let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello ").unwrap();
+    /// encoder.write_compressed_block(&blocks, 0x76289DDE, 6).unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoder = gzip::Decoder::new(&encoded_data[..]).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn write_compressed_block(
+        &mut self,
+        raw_deflate: &[u8],
+        uncompressed_crc: u32,
+        uncompressed_len: u32,
+    ) -> io::Result<()> {
+        self.writer.write_raw_blocks(raw_deflate)?;
+        self.crc32
+            .combine(uncompressed_crc, u64::from(uncompressed_len));
+        self.input_size = self.input_size.wrapping_add(uncompressed_len);
+        Ok(())
+    }
+
+    /// Registers `f` to be called whenever a DEFLATE block is flushed.
+    ///
+    /// The number of output bytes passed to `f` only counts the DEFLATE data (i.e., not the GZIP header).
+    /// See [`deflate::Encoder::on_progress`] for more details.
+    pub fn on_progress<F>(&mut self, f: F)
+    where
+        F: FnMut(u64, u64) + Send + 'static,
+    {
+        self.writer.on_progress(f);
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &W {
         self.writer.as_inner_ref()
@@ -889,12 +1410,18 @@ where
 {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let written_size = self.writer.write(buf)?;
-        self.crc32.update(&buf[..written_size]);
-        self.input_size = self.input_size.wrapping_add(written_size as u32);
+        if self.known_trailer.is_none() {
+            self.crc32.update(&buf[..written_size]);
+            self.input_size = self.input_size.wrapping_add(written_size as u32);
+        }
         Ok(written_size)
     }
     fn flush(&mut self) -> io::Result<()> {
-        self.writer.flush()
+        match self.flush_mode {
+            FlushMode::None => self.writer.flush(),
+            FlushMode::Partial => self.writer.partial_flush(),
+            FlushMode::Sync => self.writer.sync_flush(),
+        }
     }
 }
 impl<W, E> Complete for Encoder<W, E>
@@ -907,12 +1434,120 @@ where
     }
 }
 
+/// Reads the header of a GZIP stream from `reader`.
+///
+/// This parses the whole header (including the optional fields) without decoding the compressed data,
+/// and returns it along with `reader` positioned at the beginning of the DEFLATE compressed data.
+///
+/// # Examples
+/// ```
+/// use core2::io::{Read, Write};
+/// use libflate::gzip::{self, EncodeOptions, Encoder, HeaderBuilder};
+/// use libflate::deflate;
+/// #[cfg(not(feature = "std"))]
+/// extern crate alloc;
+/// #[cfg(not(feature = "std"))]
+/// use alloc::ffi::CString;
+/// #[cfg(feature = "std")]
+/// use std::ffi::CString;
+///
+/// let header = HeaderBuilder::new().filename(CString::new("foo.txt").unwrap()).finish();
+/// let options = EncodeOptions::new().header(header);
+/// let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+/// encoder.write_all(b"Hello World!").unwrap();
+/// let encoded_data = encoder.finish().into_result().unwrap();
+///
+/// let (header, body) = gzip::read_header(&encoded_data[..]).unwrap();
+/// assert_eq!(header.filename().unwrap().to_str(), Ok("foo.txt"));
+///
+/// // The remaining data can be decoded as a DEFLATE stream
+/// let mut decoded_data = Vec::new();
+/// deflate::Decoder::new(body).read_to_end(&mut decoded_data).unwrap();
+/// assert_eq!(decoded_data, b"Hello World!");
+/// ```
+pub fn read_header<R>(mut reader: R) -> io::Result<(Header, R)>
+where
+    R: io::Read,
+{
+    let header = Header::read_from(&mut reader, true)?;
+    Ok((header, reader))
+}
+
+/// Reads the ISIZE field (the size of the uncompressed data modulo 2^32) from the trailer of a GZIP stream.
+///
+/// This seeks to the last 4 bytes of `reader`, reads ISIZE and restores the original position of `reader`.
+/// The result is useful to pre-allocate a buffer before decoding the stream.
+///
+/// Note that the value is only a hint:
+/// if the stream consists of multiple members, this returns the ISIZE of the last member,
+/// and the value of a corrupted (or crafted) stream is not reliable until the stream is decoded.
+///
+/// # Examples
+/// ```
+/// use core2::io::{Cursor, Write};
+/// use libflate::gzip::{self, Encoder};
+///
+/// let mut encoder = Encoder::new(Vec::new()).unwrap();
+/// encoder.write_all(b"Hello World!").unwrap();
+/// let encoded_data = encoder.finish().into_result().unwrap();
+///
+/// let mut reader = Cursor::new(encoded_data);
+/// assert_eq!(gzip::read_isize(&mut reader).unwrap(), 12);
+/// assert_eq!(reader.position(), 0);
+/// ```
+pub fn read_isize<R>(reader: &mut R) -> io::Result<u32>
+where
+    R: io::Read + io::Seek,
+{
+    #[cfg(feature = "std")]
+    let position = reader.stream_position()?;
+    // `core2::io::Seek` does not provide `stream_position`
+    #[cfg(not(feature = "std"))]
+    let position = reader.seek(io::SeekFrom::Current(0))?;
+    let result = read_trailing_isize(reader);
+    reader.seek(io::SeekFrom::Start(position))?;
+    result
+}
+
+fn read_trailing_isize<R>(reader: &mut R) -> io::Result<u32>
+where
+    R: io::Read + io::Seek,
+{
+    let end = reader.seek(io::SeekFrom::End(0))?;
+    if end < MIN_MEMBER_SIZE {
+        return Err(invalid_data_error!(
+            "GZIP stream is too short: {} bytes",
+            end
+        ));
+    }
+    reader.seek(io::SeekFrom::End(-4))?;
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
 /// GZIP decoder.
+///
+/// # Errors
+///
+/// If the stream is truncated, an `UnexpectedEof` error is returned
+/// with a message which describes where the truncation happened:
+///
+/// - `"GZIP stream is truncated in the fixed-length header"`
+/// - `"GZIP stream is truncated in the FEXTRA field of the header"`
+/// - `"GZIP stream is truncated in the FNAME field of the header"`
+/// - `"GZIP stream is truncated in the FCOMMENT field of the header"`
+/// - `"GZIP stream is truncated in the FHCRC field of the header"`
+/// - `"GZIP stream is truncated in the DEFLATE compressed data"`
+/// - `"GZIP stream is truncated in the trailer"`
 #[derive(Debug)]
 pub struct Decoder<R> {
     header: Header,
     reader: deflate::Decoder<R>,
     crc32: checksum::Crc32,
+    trailer: Option<Trailer>,
+    checksum_mismatch_policy: ChecksumMismatchPolicy,
+    checksum_ok: Option<bool>,
     eos: bool,
 }
 impl<R> Decoder<R>
@@ -938,9 +1573,50 @@ where
     ///
     /// assert_eq!(buf, b"Hello World!");
     /// ```
-    pub fn new(mut inner: R) -> io::Result<Self> {
-        let header = Header::read_from(&mut inner)?;
-        Ok(Self::with_header(inner, header))
+    pub fn new(inner: R) -> io::Result<Self> {
+        Self::with_strictness(inner, true)
+    }
+
+    /// Makes a new decoder instance with the specified strictness of header parsing.
+    ///
+    /// If `strict` is `true`, this is equivalent to [`Decoder::new`].
+    /// Otherwise, a header which has FHCRC is accepted even if there are unknown bytes
+    /// (up to 1024 bytes) between the last optional field and the CRC16,
+    /// provided that the CRC16 matches all the preceding bytes of the header.
+    /// Such headers are produced by some nonconforming writers.
+    pub fn with_strictness(mut inner: R, strict: bool) -> io::Result<Self> {
+        let header = Header::read_from(&mut inner, strict)?;
+        Ok(Self::with_header(header, deflate::Decoder::new(inner)))
+    }
+
+    /// Makes a new decoder instance which uses `buffer` as the buffer of decoded data.
+    ///
+    /// See [`deflate::Decoder::with_buffer`] for details.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::gzip::{Decoder, Encoder};
+    ///
+    /// let mut buffer = Vec::new();
+    /// for text in ["foo", "bar", "baz"] {
+    ///     let mut encoder = Encoder::new(Vec::new()).unwrap();
+    ///     encoder.write_all(text.as_bytes()).unwrap();
+    ///     let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    ///     let mut decoder = Decoder::with_buffer(&encoded_data[..], buffer).unwrap();
+    ///     let mut buf = Vec::new();
+    ///     decoder.read_to_end(&mut buf).unwrap();
+    ///     assert_eq!(buf, text.as_bytes());
+    ///     buffer = decoder.into_buffer();
+    /// }
+    /// ```
+    pub fn with_buffer(mut inner: R, buffer: Vec<u8>) -> io::Result<Self> {
+        let header = Header::read_from(&mut inner, true)?;
+        Ok(Self::with_header(
+            header,
+            deflate::Decoder::with_buffer(inner, buffer),
+        ))
     }
 
     /// Returns the header of the GZIP stream.
@@ -970,6 +1646,20 @@ where
         self.reader.as_inner_mut()
     }
 
+    /// Returns the immutable reference to the inner stream.
+    ///
+    /// This is an alias of [`Decoder::as_inner_ref`].
+    pub fn get_ref(&self) -> &R {
+        self.as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    ///
+    /// This is an alias of [`Decoder::as_inner_mut`].
+    pub fn get_mut(&mut self) -> &mut R {
+        self.as_inner_mut()
+    }
+
     /// Unwraps this `Decoder`, returning the underlying reader.
     ///
     /// # Examples
@@ -988,18 +1678,108 @@ where
         self.reader.into_inner()
     }
 
-    /// Returns the data that has been decoded but has not yet been read.
+    /// Unwraps this `Decoder`, returning the underlying reader and
+    /// the bytes which have been read from it but not consumed by the decoder.
     ///
-    /// This method is useful to retrieve partial decoded data when the decoding process is failed.
-    pub fn unread_decoded_data(&self) -> &[u8] {
-        self.reader.unread_decoded_data()
-    }
+    /// The bytes should be treated as preceding the remaining data of the reader.
+    /// This is useful for non-seekable readers (e.g., pipes) to keep track of the data following a member.
+    ///
+    /// Note that the decoder reads the inner stream byte by byte and never reads beyond the trailer of a member,
+    /// so once the member has been decoded entirely, the returned bytes are empty
+    /// and the data following the member can be read from the reader as is.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::gzip::{Decoder, Encoder};
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let mut encoded_data = encoder.finish().into_result().unwrap();
+    /// encoded_data.extend_from_slice(b"sentinel");
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    ///
+    /// let (mut reader, buffered) = decoder.into_inner_with_buffered();
+    /// let mut rest = buffered;
+    /// reader.read_to_end(&mut rest).unwrap();
+    /// assert_eq!(rest, b"sentinel");
+    /// ```
+    pub fn into_inner_with_buffered(self) -> (R, Vec<u8>) {
+        if self.eos {
+            // The trailer is read directly from the inner reader
+            (self.reader.into_inner(), Vec::new())
+        } else {
+            self.reader.into_inner_with_buffered()
+        }
+    }
+
+    /// Unwraps this `Decoder`, returning the buffer of decoded data.
+    ///
+    /// See [`deflate::Decoder::into_buffer`] for details.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.reader.into_buffer()
+    }
 
-    fn with_header(inner: R, header: Header) -> Self {
+    /// Returns the data that has been decoded but has not yet been read.
+    ///
+    /// This method is useful to retrieve partial decoded data when the decoding process is failed.
+    pub fn unread_decoded_data(&self) -> &[u8] {
+        self.reader.unread_decoded_data()
+    }
+
+    /// Specifies the behavior when the CRC32 checksum of the decoded data is mismatched.
+    ///
+    /// The default value is `ChecksumMismatchPolicy::Error`.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::ChecksumMismatchPolicy;
+    /// use libflate::gzip::Decoder;
+    ///
+    /// // The first byte of the CRC32 checksum is broken
+    /// let encoded_data = [31, 139, 8, 0, 123, 0, 0, 0, 0, 3, 1, 12, 0, 243, 255,
+    ///                     72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+    ///                     0, 28, 41, 28, 12, 0, 0, 0];
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// decoder.on_checksum_mismatch(ChecksumMismatchPolicy::Ignore);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// assert_eq!(decoder.checksum_ok(), Some(false));
+    /// ```
+    pub fn on_checksum_mismatch(&mut self, policy: ChecksumMismatchPolicy) {
+        self.checksum_mismatch_policy = policy;
+    }
+
+    /// Enables or disables the windowed mode of the underlying DEFLATE decoder.
+    ///
+    /// See [`deflate::Decoder::set_windowed`] for details.
+    pub fn set_windowed(&mut self, windowed: bool) {
+        self.reader.set_windowed(windowed);
+    }
+
+    /// Returns whether the CRC32 checksum of the decoded data matched the expected value.
+    ///
+    /// `None` is returned if the end of the stream has not been reached yet.
+    pub fn checksum_ok(&self) -> Option<bool> {
+        self.checksum_ok
+    }
+
+    fn with_header(header: Header, reader: deflate::Decoder<R>) -> Self {
         Decoder {
             header,
-            reader: deflate::Decoder::new(inner),
+            reader,
             crc32: checksum::Crc32::new(),
+            trailer: None,
+            checksum_mismatch_policy: ChecksumMismatchPolicy::default(),
+            checksum_ok: None,
             eos: false,
         }
     }
@@ -1008,9 +1788,70 @@ where
         self.header = header;
         self.reader.reset();
         self.crc32 = checksum::Crc32::new();
+        self.trailer = None;
+        self.checksum_ok = None;
         self.eos = false;
     }
 }
+impl<R> Decoder<R>
+where
+    R: io::Read + io::Seek,
+{
+    /// Moves the decoder to a virtual offset of a block-aligned GZIP file (e.g., BGZF).
+    ///
+    /// The inner reader is seeked to `compressed_offset`, which must be the start of a member,
+    /// and the header of the member is read.
+    /// Then the first `within_block` bytes of the decompressed data of the member are skipped.
+    /// Subsequent reads yield the rest of the decompressed data of the member.
+    ///
+    /// The virtual offsets are usually taken from an index file (e.g., `.gzi`, `.bai`).
+    ///
+    /// # Errors
+    ///
+    /// If the member is shorter than `within_block` bytes, an `UnexpectedEof` error is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Cursor, Read, Write};
+    /// use libflate::gzip::{Decoder, Encoder};
+    ///
+    /// let mut encoded_data = Vec::new();
+    /// let mut offsets = Vec::new();
+    /// for text in ["Hello World!", "Hello GZIP!"] {
+    ///     offsets.push(encoded_data.len() as u64);
+    ///     let mut encoder = Encoder::new(Vec::new()).unwrap();
+    ///     encoder.write_all(text.as_bytes()).unwrap();
+    ///     encoded_data.extend(encoder.finish().into_result().unwrap());
+    /// }
+    ///
+    /// let mut decoder = Decoder::new(Cursor::new(&encoded_data)).unwrap();
+    /// decoder.seek_to(offsets[1], 6).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"GZIP!");
+    /// ```
+    pub fn seek_to(&mut self, compressed_offset: u64, within_block: u32) -> io::Result<()> {
+        let inner = self.reader.as_inner_mut();
+        inner.seek(io::SeekFrom::Start(compressed_offset))?;
+        let header = Header::read_from(inner, true)?;
+        self.reset(header);
+
+        let mut remaining = within_block as usize;
+        let mut buf = [0; 1024];
+        while remaining > 0 {
+            let size = buf.len().min(remaining);
+            let read_size = self.read(&mut buf[..size])?;
+            if read_size == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "The member is shorter than the offset within the block",
+                ));
+            }
+            remaining -= read_size;
+        }
+        Ok(())
+    }
+}
 impl<R> io::Read for Decoder<R>
 where
     R: io::Read,
@@ -1019,7 +1860,10 @@ where
         if self.eos {
             Ok(0)
         } else {
-            let read_size = self.reader.read(buf)?;
+            let read_size = self
+                .reader
+                .read(buf)
+                .map_err(|e| truncated(e, TRUNCATED_IN_BODY))?;
             self.crc32.update(&buf[..read_size]);
             if read_size == 0 {
                 if buf.is_empty() {
@@ -1028,14 +1872,22 @@ where
 
                 self.eos = true;
                 let trailer = Trailer::read_from(self.reader.as_inner_mut())?;
+                let crc32 = trailer.crc32;
+                self.trailer = Some(trailer);
+                let checksum_ok = crc32 == self.crc32.value();
+                self.checksum_ok = Some(checksum_ok);
+
                 // checksum verification is skipped during fuzzing
                 // so that random data from fuzzer can reach actually interesting code
                 // Compilation flag 'fuzzing' is automatically set by all 3 Rust fuzzers.
-                if cfg!(not(fuzzing)) && trailer.crc32 != self.crc32.value() {
+                if cfg!(not(fuzzing))
+                    && !checksum_ok
+                    && self.checksum_mismatch_policy == ChecksumMismatchPolicy::Error
+                {
                     Err(invalid_data_error!(
                         "CRC32 mismatched: value={}, expected={}",
                         self.crc32.value(),
-                        trailer.crc32
+                        crc32
                     ))
                 } else {
                     Ok(0)
@@ -1047,11 +1899,63 @@ where
     }
 }
 
+/// Information about a member decoded by [`MultiDecoder`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MemberInfo {
+    compressed_len: u64,
+    decompressed_len: u32,
+    crc32: u32,
+}
+impl MemberInfo {
+    /// Returns the byte length of the member in the GZIP stream (including its header and trailer).
+    pub fn compressed_len(&self) -> u64 {
+        self.compressed_len
+    }
+
+    /// Returns the size of the decompressed data modulo 2^32 (i.e., the `ISIZE` field of the trailer).
+    pub fn decompressed_len(&self) -> u32 {
+        self.decompressed_len
+    }
+
+    /// Returns the CRC32 of the decompressed data stored in the trailer.
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+}
+
+#[derive(Debug)]
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+impl<R> io::Read for CountingReader<R>
+where
+    R: io::Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.count += size as u64;
+        Ok(size)
+    }
+}
+
+/// A predicate which selects the members to be yielded by [`MultiDecoder`].
+struct MemberFilter(Box<dyn FnMut(&Header) -> bool + Send>);
+impl fmt::Debug for MemberFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("MemberFilter")
+    }
+}
+
 /// A decoder that decodes all members in a GZIP stream.
 #[derive(Debug)]
 pub struct MultiDecoder<R> {
-    decoder: Decoder<R>,
+    decoder: Decoder<CountingReader<R>>,
+    members: Vec<MemberInfo>,
+    member_start: u64,
     eos: bool,
+    filter: Option<MemberFilter>,
+    selected: Option<bool>,
 }
 impl<R> MultiDecoder<R>
 where
@@ -1083,13 +1987,63 @@ where
     /// assert_eq!(buf, b"Hello World!");
     /// ```
     pub fn new(inner: R) -> io::Result<Self> {
-        let decoder = Decoder::new(inner)?;
+        let decoder = Decoder::new(CountingReader { inner, count: 0 })?;
         Ok(MultiDecoder {
             decoder,
+            members: Vec::new(),
+            member_start: 0,
             eos: false,
+            filter: None,
+            selected: None,
         })
     }
 
+    /// Sets the predicate which selects the members to be decoded into the output.
+    ///
+    /// `f` is called with the header of each member before its data is read.
+    /// The members for which `f` returns `false` are still decoded (to keep the position in the stream and
+    /// to verify their checksums), but their data is discarded instead of being yielded by `Read` methods.
+    ///
+    /// The predicate only applies to the members whose data has not been read yet.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(not(feature = "std"))]
+    /// extern crate alloc;
+    /// #[cfg(not(feature = "std"))]
+    /// use alloc::ffi::CString;
+    /// #[cfg(feature = "std")]
+    /// use std::ffi::CString;
+    /// use core2::io::{Read, Write};
+    /// use libflate::gzip::{Encoder, MultiDecoder};
+    ///
+    /// let mut encoded_data = Vec::new();
+    /// for (filename, text) in [("a.txt", "Hello "), ("b.bin", "foo"), ("c.txt", "World!")] {
+    ///     let mut encoder = Encoder::builder(Vec::new())
+    ///         .filename(CString::new(filename).unwrap())
+    ///         .finish()
+    ///         .unwrap();
+    ///     encoder.write_all(text.as_bytes()).unwrap();
+    ///     encoded_data.extend(encoder.finish().into_result().unwrap());
+    /// }
+    ///
+    /// let mut decoder = MultiDecoder::new(&encoded_data[..]).unwrap();
+    /// decoder.set_member_filter(|header| {
+    ///     header.filename().is_some_and(|name| name.as_bytes().ends_with(b".txt"))
+    /// });
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// assert_eq!(decoder.members().len(), 3);
+    /// ```
+    pub fn set_member_filter<F>(&mut self, f: F)
+    where
+        F: FnMut(&Header) -> bool + Send + 'static,
+    {
+        self.filter = Some(MemberFilter(Box::new(f)));
+    }
+
     /// Returns the header of the current member in the GZIP stream.
     ///
     /// # Examples
@@ -1107,14 +2061,91 @@ where
         self.decoder.header()
     }
 
+    /// Returns the information about the members that have been completely decoded so far.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::gzip::MultiDecoder;
+    ///
+    /// let encoded_data = [31, 139, 8, 0, 123, 0, 0, 0, 0, 3, 1, 12, 0, 243, 255,
+    ///                     72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+    ///                     163, 28, 41, 28, 12, 0, 0, 0];
+    ///
+    /// let mut decoder = MultiDecoder::new(&encoded_data[..]).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// let members = decoder.members();
+    /// assert_eq!(members.len(), 1);
+    /// assert_eq!(members[0].compressed_len(), encoded_data.len() as u64);
+    /// assert_eq!(members[0].decompressed_len(), 12);
+    /// ```
+    pub fn members(&self) -> &[MemberInfo] {
+        &self.members
+    }
+
+    /// Decodes the current member (i.e., the first member if nothing has been read yet) to the end,
+    /// appending the decoded data to `out`.
+    ///
+    /// The subsequent members are left undecoded and can be read later via `Read` methods.
+    /// If the end of the stream has been reached, this does nothing.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::gzip::{Encoder, MultiDecoder};
+    ///
+    /// let mut encoded_data = Vec::new();
+    /// for text in ["Hello", " World", "!"] {
+    ///     let mut encoder = Encoder::new(Vec::new()).unwrap();
+    ///     encoder.write_all(text.as_bytes()).unwrap();
+    ///     encoded_data.extend(encoder.finish().into_result().unwrap());
+    /// }
+    ///
+    /// let mut decoder = MultiDecoder::new(&encoded_data[..]).unwrap();
+    /// let mut first = Vec::new();
+    /// decoder.read_first_member(&mut first).unwrap();
+    /// assert_eq!(first, b"Hello");
+    ///
+    /// let mut rest = Vec::new();
+    /// decoder.read_to_end(&mut rest).unwrap();
+    /// assert_eq!(rest, b" World!");
+    /// ```
+    pub fn read_first_member(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        if !self.eos {
+            if self.is_selected() {
+                self.decoder.read_to_end(out)?;
+            } else {
+                self.discard_member()?;
+            }
+            self.next_member()?;
+        }
+        Ok(())
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &R {
-        self.decoder.as_inner_ref()
+        &self.decoder.as_inner_ref().inner
     }
 
     /// Returns the mutable reference to the inner stream.
     pub fn as_inner_mut(&mut self) -> &mut R {
-        self.decoder.as_inner_mut()
+        &mut self.decoder.as_inner_mut().inner
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    ///
+    /// This is an alias of [`MultiDecoder::as_inner_ref`].
+    pub fn get_ref(&self) -> &R {
+        self.as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    ///
+    /// This is an alias of [`MultiDecoder::as_inner_mut`].
+    pub fn get_mut(&mut self) -> &mut R {
+        self.as_inner_mut()
     }
 
     /// Unwraps this `MultiDecoder`, returning the underlying reader.
@@ -1132,7 +2163,54 @@ where
     /// assert_eq!(decoder.into_inner().into_inner(), &encoded_data[..]);
     /// ```
     pub fn into_inner(self) -> R {
-        self.decoder.into_inner()
+        self.decoder.into_inner().inner
+    }
+
+    /// Returns whether the data of the current member should be yielded, consulting the member filter if needed.
+    fn is_selected(&mut self) -> bool {
+        let header = self.decoder.header();
+        *self
+            .selected
+            .get_or_insert_with(|| self.filter.as_mut().map_or(true, |f| (f.0)(header)))
+    }
+
+    /// Decodes the rest of the current member and discards the data.
+    fn discard_member(&mut self) -> io::Result<()> {
+        let mut buf = [0; 1024];
+        while self.decoder.read(&mut buf)? != 0 {}
+        Ok(())
+    }
+
+    /// Records the current member, which has been decoded entirely, and moves on to the next member.
+    ///
+    /// Returns `false` if the current member has not been finished (or has already been recorded).
+    fn next_member(&mut self) -> io::Result<bool> {
+        // The current member may be empty, so the end of the stream is only detected by a missing header
+        let Some(trailer) = self.decoder.trailer.take() else {
+            return Ok(false);
+        };
+        let member_end = self.decoder.as_inner_ref().count;
+        self.members.push(MemberInfo {
+            compressed_len: member_end - self.member_start,
+            decompressed_len: trailer.input_size(),
+            crc32: trailer.crc32(),
+        });
+        self.member_start = member_end;
+        self.selected = None;
+
+        match Header::read_from(self.decoder.as_inner_mut(), true) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::UnexpectedEof {
+                    self.eos = true;
+                } else {
+                    return Err(e);
+                }
+            }
+            Ok(header) => {
+                self.decoder.reset(header);
+            }
+        }
+        Ok(true)
     }
 }
 impl<R> io::Read for MultiDecoder<R>
@@ -1140,29 +2218,162 @@ where
     R: io::Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if self.eos {
-            return Ok(0);
+        // Loops rather than recurses so that a long sequence of empty members is handled in constant stack space
+        while !self.eos {
+            if self.is_selected() {
+                let read_size = self.decoder.read(buf)?;
+                if read_size != 0 {
+                    return Ok(read_size);
+                }
+            } else {
+                self.discard_member()?;
+            }
+
+            if !self.next_member()? {
+                return Ok(0);
+            }
         }
+        Ok(0)
+    }
+}
+
+/// An event produced by [`EventDecoder`].
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The header of a member has been read.
+    Header(Header),
+
+    /// The given number of decoded bytes are available via [`EventDecoder::data`].
+    Data(usize),
 
-        let read_size = self.decoder.read(buf)?;
-        if read_size == 0 {
-            match Header::read_from(self.as_inner_mut()) {
+    /// The trailer of a member has been read.
+    EndOfMember {
+        /// The CRC32 of the decompressed data stored in the trailer.
+        crc32: u32,
+
+        /// The size of the decompressed data modulo 2^32 stored in the trailer.
+        input_size: u32,
+    },
+}
+
+/// A pull-based decoder that yields the structure of a GZIP stream as [`Event`]s.
+///
+/// For each member in the stream, an `Event::Header` is followed by zero or more `Event::Data`
+/// and an `Event::EndOfMember`.
+#[derive(Debug)]
+pub struct EventDecoder<R> {
+    decoder: Decoder<R>,
+    buffer: Vec<u8>,
+    data_len: usize,
+    header_pending: bool,
+    member_done: bool,
+    eos: bool,
+}
+impl<R> EventDecoder<R>
+where
+    R: io::Read,
+{
+    /// Makes a new decoder instance.
+    ///
+    /// `inner` is to be decoded GZIP stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::{Event, EventDecoder};
+    ///
+    /// let encoded_data = [31, 139, 8, 0, 123, 0, 0, 0, 0, 3, 1, 12, 0, 243, 255,
+    ///                     72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+    ///                     163, 28, 41, 28, 12, 0, 0, 0];
+    ///
+    /// let mut decoder = EventDecoder::new(&encoded_data[..]).unwrap();
+    /// assert!(matches!(decoder.next_event().unwrap(), Some(Event::Header(_))));
+    /// assert!(matches!(decoder.next_event().unwrap(), Some(Event::Data(12))));
+    /// assert_eq!(decoder.data(), b"Hello World!");
+    /// assert!(matches!(
+    ///     decoder.next_event().unwrap(),
+    ///     Some(Event::EndOfMember { input_size: 12, .. })
+    /// ));
+    /// assert!(decoder.next_event().unwrap().is_none());
+    /// ```
+    pub fn new(inner: R) -> io::Result<Self> {
+        let decoder = Decoder::new(inner)?;
+        Ok(EventDecoder {
+            decoder,
+            buffer: vec![0; EVENT_BUFFER_SIZE],
+            data_len: 0,
+            header_pending: true,
+            member_done: false,
+            eos: false,
+        })
+    }
+
+    /// Returns the next event, or `None` if the end of the stream has been reached.
+    pub fn next_event(&mut self) -> io::Result<Option<Event>> {
+        self.data_len = 0;
+        if self.eos {
+            return Ok(None);
+        }
+        if self.member_done {
+            match Header::read_from(self.decoder.as_inner_mut(), true) {
                 Err(e) => {
                     if e.kind() == io::ErrorKind::UnexpectedEof {
                         self.eos = true;
-                        Ok(0)
+                        return Ok(None);
                     } else {
-                        Err(e)
+                        return Err(e);
                     }
                 }
                 Ok(header) => {
                     self.decoder.reset(header);
-                    self.read(buf)
+                    self.member_done = false;
+                    self.header_pending = true;
                 }
             }
-        } else {
-            Ok(read_size)
         }
+        if self.header_pending {
+            self.header_pending = false;
+            return Ok(Some(Event::Header(self.decoder.header().clone())));
+        }
+
+        let read_size = self.decoder.read(&mut self.buffer)?;
+        if read_size != 0 {
+            self.data_len = read_size;
+            return Ok(Some(Event::Data(read_size)));
+        }
+        // The trailer is missing if reading it has failed before (e.g., the stream is truncated)
+        let Some(trailer) = self.decoder.trailer.take() else {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The GZIP trailer is missing",
+            ));
+        };
+        self.member_done = true;
+        Ok(Some(Event::EndOfMember {
+            crc32: trailer.crc32(),
+            input_size: trailer.input_size(),
+        }))
+    }
+
+    /// Returns the decoded bytes announced by the last `Event::Data`.
+    ///
+    /// If the last event is not `Event::Data`, an empty slice is returned.
+    pub fn data(&self) -> &[u8] {
+        &self.buffer[..self.data_len]
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        self.decoder.as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        self.decoder.as_inner_mut()
+    }
+
+    /// Unwraps this `EventDecoder`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.decoder.into_inner()
     }
 }
 
@@ -1214,6 +2425,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::flat_map_identity)]
     fn multi_decode_works() {
         use core::iter;
         let text = b"Hello World!";
@@ -1225,6 +2437,292 @@ mod tests {
         assert_eq!(decode_multi(&encoded).unwrap(), b"Hello World!Hello World!");
     }
 
+    #[test]
+    fn read_isize_works() {
+        let text = (0..100_000u32).map(|i| i as u8).collect::<Vec<_>>();
+        let encoded = [encode(b"foo").unwrap(), encode(&text).unwrap()].concat();
+
+        let mut reader = io::Cursor::new(&encoded);
+        reader.set_position(5);
+        assert_eq!(read_isize(&mut reader).unwrap(), text.len() as u32);
+        assert_eq!(reader.position(), 5);
+
+        let mut reader = io::Cursor::new(&encoded[..10]);
+        assert_eq!(
+            read_isize(&mut reader).err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidData)
+        );
+        assert_eq!(reader.position(), 0);
+    }
+
+    #[test]
+    fn event_decoder_works() {
+        let mut encoded = encode(b"Hello ").unwrap();
+        encoded.extend(encode(b"World!").unwrap());
+
+        let mut decoder = EventDecoder::new(&encoded[..]).unwrap();
+        for text in [&b"Hello "[..], b"World!"] {
+            assert!(matches!(
+                decoder.next_event().unwrap(),
+                Some(Event::Header(_))
+            ));
+            assert!(decoder.data().is_empty());
+
+            let mut decoded = Vec::new();
+            let (crc32, input_size) = loop {
+                match decoder.next_event().unwrap() {
+                    Some(Event::Data(size)) => {
+                        assert_eq!(decoder.data().len(), size);
+                        decoded.extend_from_slice(decoder.data());
+                    }
+                    Some(Event::EndOfMember { crc32, input_size }) => break (crc32, input_size),
+                    event => panic!("Unexpected event: {:?}", event),
+                }
+            };
+            assert_eq!(decoded, text);
+            let mut expected_crc32 = checksum::Crc32::new();
+            expected_crc32.update(text);
+            assert_eq!(crc32, expected_crc32.value());
+            assert_eq!(input_size, text.len() as u32);
+        }
+        assert!(decoder.next_event().unwrap().is_none());
+        assert!(decoder.next_event().unwrap().is_none());
+    }
+
+    #[test]
+    fn event_decoder_truncated_trailer_works() {
+        let encoded = encode(b"Hello World!").unwrap();
+        let encoded = &encoded[..encoded.len() - 4];
+
+        let mut decoder = EventDecoder::new(encoded).unwrap();
+        let e = loop {
+            match decoder.next_event() {
+                Ok(Some(Event::Header(_) | Event::Data(_))) => {}
+                Ok(event) => panic!("Unexpected event: {:?}", event),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Calling again after the error reports the error instead of panicking
+        let e = decoder.next_event().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn multi_decode_empty_members_works() {
+        let empty = encode(b"").unwrap();
+        let text = encode(b"Hello World!").unwrap();
+
+        let encoded = [&empty[..], &text[..]].concat();
+        assert_eq!(decode_multi(&encoded).unwrap(), b"Hello World!");
+
+        let encoded = [&empty[..], &empty[..], &text[..], &empty[..], &text[..]].concat();
+        let mut decoder = MultiDecoder::new(&encoded[..]).unwrap();
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"Hello World!Hello World!");
+        assert_eq!(decoder.members().len(), 5);
+
+        let encoded = empty.repeat(100_000);
+        assert_eq!(decode_multi(&encoded).unwrap(), b"");
+    }
+
+    #[test]
+    fn checksum_mismatch_policy_works() {
+        let mut encoded = encode(b"Hello World!").unwrap();
+        let crc32_offset = encoded.len() - 8;
+        encoded[crc32_offset] ^= 0xFF;
+
+        // ChecksumMismatchPolicy::Error
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        let mut buf = Vec::new();
+        let e = decoder.read_to_end(&mut buf).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(decoder.checksum_ok(), Some(false));
+
+        // ChecksumMismatchPolicy::Ignore
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        decoder.on_checksum_mismatch(ChecksumMismatchPolicy::Ignore);
+        assert_eq!(decoder.checksum_ok(), None);
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"Hello World!");
+        assert_eq!(decoder.checksum_ok(), Some(false));
+    }
+
+    #[test]
+    fn sync_flush_works() {
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::new().flush_mode(FlushMode::Sync))
+                .unwrap();
+        encoder.write_all(b"first line\n").unwrap();
+        encoder.flush().unwrap();
+
+        // The data written so far can be decoded before the member is finished
+        let mut decoder = Decoder::new(&encoder.as_inner_ref()[..]).unwrap();
+        let mut buf = [0; 11];
+        decoder.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"first line\n");
+
+        encoder.write_all(b"second line\n").unwrap();
+        encoder.flush().unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        assert_eq!(decode(&encoded).unwrap(), b"first line\nsecond line\n");
+    }
+
+    #[test]
+    fn multi_decode_members_works() {
+        let texts: [&[u8]; 3] = [b"Hello", b"", b"Hello World! Hello GZIP!!"];
+        let encoded_members = texts
+            .iter()
+            .map(|text| encode(text).unwrap())
+            .collect::<Vec<_>>();
+        let encoded = encoded_members.concat();
+
+        let mut decoder = MultiDecoder::new(&encoded[..]).unwrap();
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, texts.concat());
+
+        let members = decoder.members();
+        assert_eq!(members.len(), 3);
+        for ((member, text), encoded) in members.iter().zip(&texts).zip(&encoded_members) {
+            assert_eq!(member.compressed_len(), encoded.len() as u64);
+            assert_eq!(member.decompressed_len(), text.len() as u32);
+
+            let mut crc32 = checksum::Crc32::new();
+            crc32.update(text);
+            assert_eq!(member.crc32(), crc32.value());
+        }
+        assert_eq!(
+            members.iter().map(|m| m.compressed_len()).sum::<u64>(),
+            encoded.len() as u64
+        );
+        assert_eq!(
+            members.iter().map(|m| m.decompressed_len()).sum::<u32>(),
+            decoded.len() as u32
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn encoder_append_works() {
+        use std::io::Cursor;
+
+        let payloads = [&b"first run\n"[..], b"second run\n", b"third run\n"];
+        let mut file = Cursor::new(Vec::new());
+        for payload in payloads {
+            // Each run starts at the beginning of the file, as if it were opened again
+            file.set_position(0);
+            let mut encoder = Encoder::append(file).unwrap();
+            encoder.write_all(payload).unwrap();
+            file = encoder.finish().into_result().unwrap();
+        }
+
+        let mut decoded = Vec::new();
+        MultiDecoder::new(&file.get_ref()[..])
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, payloads.concat());
+    }
+
+    #[test]
+    fn multi_decode_with_member_filter_works() {
+        let large = include_bytes!("../README.md").repeat(100);
+        let texts: [&[u8]; 4] = [b"Hello ", &large, b"World!", b""];
+        let encoded = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let header = HeaderBuilder::new().modification_time(i as u32).finish();
+                let options = EncodeOptions::new().header(header);
+                let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+                encoder.write_all(text).unwrap();
+                encoder.finish().into_result().unwrap()
+            })
+            .collect::<Vec<_>>()
+            .concat();
+
+        let mut decoder = MultiDecoder::new(&encoded[..]).unwrap();
+        // A filter which is `Send` but not `Sync` (selects every other member)
+        let index = core::cell::Cell::new(0);
+        decoder.set_member_filter(move |_| {
+            index.set(index.get() + 1);
+            index.get() % 2 == 1
+        });
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, b"Hello World!");
+        assert_eq!(decoder.members().len(), 4);
+
+        // `read_first_member` discards an unselected member, too
+        let mut decoder = MultiDecoder::new(&encoded[..]).unwrap();
+        decoder.set_member_filter(|header| header.modification_time() != 0);
+        let mut first = Vec::new();
+        decoder.read_first_member(&mut first).unwrap();
+        assert!(first.is_empty());
+        decoder.read_first_member(&mut first).unwrap();
+        assert_eq!(first, large);
+    }
+
+    #[test]
+    fn seek_to_works() {
+        // BGZF-like blocks, each of which has a "BC" subfield
+        let blocks: Vec<Vec<u8>> = (0..3)
+            .map(|i| (0..1000u32).map(|j| (i * 7 + j) as u8).collect())
+            .collect();
+        let mut encoded = Vec::new();
+        let mut offsets = Vec::new();
+        for block in &blocks {
+            offsets.push(encoded.len() as u64);
+            let header = HeaderBuilder::new()
+                .add_extra_subfield(*b"BC", vec![0, 0])
+                .finish();
+            let mut encoder =
+                Encoder::with_options(Vec::new(), EncodeOptions::new().header(header)).unwrap();
+            encoder.write_all(block).unwrap();
+            encoded.extend(encoder.finish().into_result().unwrap());
+        }
+
+        let mut decoder = Decoder::new(io::Cursor::new(&encoded)).unwrap();
+        for (i, within_block) in [(2, 0), (0, 999), (1, 123), (2, 1000)] {
+            decoder.seek_to(offsets[i], within_block).unwrap();
+            let mut buf = Vec::new();
+            decoder.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf, blocks[i][within_block as usize..]);
+        }
+
+        let e = decoder.seek_to(offsets[1], 1001).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_first_member_works() {
+        let texts: [&[u8]; 3] = [b"Hello", b" World!", b" Hello GZIP!!"];
+        let encoded = texts
+            .iter()
+            .map(|text| encode(text).unwrap())
+            .collect::<Vec<_>>()
+            .concat();
+
+        let mut decoder = MultiDecoder::new(&encoded[..]).unwrap();
+        let mut first = Vec::new();
+        decoder.read_first_member(&mut first).unwrap();
+        assert_eq!(first, texts[0]);
+        assert_eq!(decoder.members().len(), 1);
+
+        let mut rest = Vec::new();
+        decoder.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, texts[1..].concat());
+        assert_eq!(decoder.members().len(), 3);
+
+        let mut buf = Vec::new();
+        decoder.read_first_member(&mut buf).unwrap();
+        assert!(buf.is_empty());
+    }
+
     #[test]
     /// See: https://github.com/sile/libflate/issues/15 and https://github.com/RazrFalcon/usvg/issues/20
     fn issue_15_1() {
@@ -1248,6 +2746,7 @@ mod tests {
 
     #[test]
     /// See: https://github.com/sile/libflate/issues/61
+    #[allow(clippy::unused_io_amount)]
     fn issue_61() {
         let data = encode(b"Hello World").unwrap();
         let mut decoder = Decoder::new(&data[..]).unwrap();
@@ -1302,4 +2801,285 @@ mod tests {
             assert_eq!(decoder.header().extra_field(), Some(&extra_field));
         }
     }
+
+    #[test]
+    fn finish_map_preserves_write_error() {
+        let mut buf = [0; 16];
+        let mut encoder = Encoder::new(&mut buf[..]).unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let (remaining, error) = encoder.finish().map(|w| w.len()).unwrap();
+        assert_eq!(remaining, 0);
+        assert_eq!(error.unwrap().kind(), io::ErrorKind::WriteZero);
+
+        let mut buf = [0; 64];
+        let mut encoder = Encoder::new(&mut buf[..]).unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let remaining = encoder.finish().map(|w| w.len()).into_result().unwrap();
+        assert!(remaining > 0);
+    }
+
+    #[test]
+    fn lenient_header_parsing_works() {
+        let encoded = encode(b"Hello World!").unwrap();
+
+        // A header with FNAME and FHCRC, and unknown bytes preceding the CRC16
+        let mut header = vec![31, 139, 8, F_NAME | F_HCRC, 0, 0, 0, 0, 0, OS_UNIX];
+        header.extend_from_slice(b"foo.txt\0");
+        header.extend_from_slice(&[0xFF, 0xEE, 0xDD]);
+        let mut crc = checksum::Crc32::new();
+        crc.update(&header);
+        header.extend_from_slice(&(crc.value() as u16).to_le_bytes());
+
+        let mut input = header;
+        input.extend_from_slice(&encoded[10..]);
+
+        assert!(Decoder::with_strictness(&input[..], true).is_err());
+
+        let mut decoder = Decoder::with_strictness(&input[..], false).unwrap();
+        assert_eq!(
+            decoder.header().filename(),
+            Some(&CString::new("foo.txt").unwrap())
+        );
+        assert!(decoder.header().is_verified());
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"Hello World!");
+
+        // A corrupted CRC16 is still rejected
+        let mut corrupted = input.clone();
+        corrupted[21] ^= 1;
+        assert!(Decoder::with_strictness(&corrupted[..], false).is_err());
+    }
+
+    #[test]
+    fn encode_options_can_be_reused() {
+        let options = EncodeOptions::new().fixed_huffman_codes();
+        for text in [&b"foo"[..], b"bar", b"baz"] {
+            let mut encoder = Encoder::with_options(Vec::new(), options.clone()).unwrap();
+            encoder.write_all(text).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+            assert_eq!(decode(&encoded).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn skip_crc_with_works() {
+        let text = include_bytes!("../README.md");
+        let mut crc32 = checksum::Crc32::new();
+        crc32.update(text);
+
+        let options = EncodeOptions::new().skip_crc_with(crc32.value(), text.len() as u32);
+        let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+        encoder.write_all(text).unwrap();
+        assert_eq!(encoder.crc32.value(), checksum::Crc32::new().value());
+        assert_eq!(encoder.input_size, 0);
+
+        let encoded = encoder.finish().into_result().unwrap();
+        assert_eq!(encoded, encode(text).unwrap());
+        assert_eq!(decode(&encoded).unwrap(), text);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn boxed_writer_with_custom_lz77_works() {
+        use alloc::boxed::Box;
+
+        struct MyLz77(lz77::DefaultLz77Encoder);
+        impl lz77::Lz77Encode for MyLz77 {
+            fn encode<S>(&mut self, buf: &[u8], sink: S)
+            where
+                S: lz77::Sink,
+            {
+                self.0.encode(buf, sink)
+            }
+            fn flush<S>(&mut self, sink: S)
+            where
+                S: lz77::Sink,
+            {
+                self.0.flush(sink)
+            }
+        }
+
+        let plain = b"Hello World! Hello GZIP!!";
+        let mut buf = Vec::new();
+        {
+            let writer: Box<dyn Write + '_> = Box::new(&mut buf);
+            let options = EncodeOptions::with_lz77(MyLz77(lz77::DefaultLz77Encoder::new()));
+            let mut encoder: Encoder<Box<dyn Write + '_>, MyLz77> =
+                Encoder::with_options(writer, options).unwrap();
+            encoder.write_all(plain).unwrap();
+            encoder.finish().into_result().unwrap();
+        }
+        assert_eq!(decode(&buf).unwrap(), plain);
+    }
+
+    #[test]
+    fn utf8_filename_and_comment_work() {
+        let header = HeaderBuilder::new()
+            .filename_str("ファイル.txt")
+            .unwrap()
+            .comment_str("コメント")
+            .unwrap()
+            .finish();
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::new().header(header)).unwrap();
+        encoder.write_all(b"foo").unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        let decoder = Decoder::new(&encoded[..]).unwrap();
+        assert_eq!(decoder.header().filename_str().unwrap(), "ファイル.txt");
+        assert_eq!(decoder.header().comment_str().unwrap(), "コメント");
+
+        // Invalid UTF-8 (e.g., LATIN-1) bytes are preserved by the raw-bytes API
+        let filename = CString::new(vec![b'c', b'a', b'f', 0xE9]).unwrap();
+        let header = HeaderBuilder::new().filename(filename.clone()).finish();
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::new().header(header)).unwrap();
+        encoder.write_all(b"foo").unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        let decoder = Decoder::new(&encoded[..]).unwrap();
+        assert_eq!(decoder.header().filename(), Some(&filename));
+        assert_eq!(decoder.header().filename_str().unwrap(), "caf\u{FFFD}");
+        assert!(decoder.header().comment_str().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn modification_system_time_works() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        for (time, expected) in [
+            (
+                UNIX_EPOCH + Duration::from_millis(1_234_567_890_999),
+                1_234_567_890,
+            ),
+            (UNIX_EPOCH - Duration::from_secs(1), 0),
+            (UNIX_EPOCH + Duration::from_secs(1 << 32), u32::MAX),
+        ] {
+            let header = HeaderBuilder::new().modification_system_time(time).finish();
+            assert_eq!(header.modification_time(), expected);
+        }
+
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let encoder = Encoder::builder(Vec::new())
+            .modification_system_time(mtime)
+            .finish()
+            .unwrap();
+        assert_eq!(encoder.header().modification_time(), 1_700_000_000);
+    }
+
+    #[test]
+    fn encoder_builder_level_keeps_other_settings() {
+        let mut encoder = Encoder::builder(Vec::new())
+            .modification_time(123)
+            .level(lz77::CompressionLevel::None)
+            .os(Os::Unix)
+            .level(lz77::CompressionLevel::Fast)
+            .finish()
+            .unwrap();
+        assert_eq!(encoder.header().modification_time(), 123);
+        assert_eq!(encoder.header().os(), Os::Unix);
+        assert_eq!(
+            encoder.header().compression_level(),
+            CompressionLevel::Fastest
+        );
+
+        encoder.write_all(b"Hello World!").unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        let mut decoder = deflate::Decoder::new(&encoded[10..]);
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, b"Hello World!");
+        assert_eq!(decoder.last_block_type(), Some(deflate::BlockType::Fixed));
+    }
+
+    #[test]
+    fn empty_input_works() {
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().fixed_huffman_codes(),
+            EncodeOptions::new().no_compression(),
+        ] {
+            let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+            assert_eq!(encoded[encoded.len() - 8..], [0; 8]); // CRC32 and ISIZE of empty data
+
+            let mut decoder = Decoder::new(&encoded[..]).unwrap();
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert!(decoded.is_empty());
+            assert_eq!(decoder.checksum_ok(), Some(true));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn truncation_errors_are_distinguishable() {
+        let header = HeaderBuilder::new()
+            .extra_field(ExtraField {
+                subfields: vec![ExtraSubField {
+                    id: [1, 2],
+                    data: vec![3, 4],
+                }],
+            })
+            .filename(CString::new("foo").unwrap())
+            .comment(CString::new("bar").unwrap())
+            .verify()
+            .finish();
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::new().header(header)).unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let mut messages = Vec::new();
+        for len in 0..encoded.len() {
+            let error = Decoder::new(&encoded[..len])
+                .and_then(|mut decoder| decoder.read_to_end(&mut Vec::new()))
+                .unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+            let message = error.to_string();
+            if messages.last() != Some(&message) {
+                messages.push(message);
+            }
+        }
+        assert_eq!(
+            messages,
+            [
+                TRUNCATED_IN_HEADER,
+                TRUNCATED_IN_FEXTRA,
+                TRUNCATED_IN_FNAME,
+                TRUNCATED_IN_FCOMMENT,
+                TRUNCATED_IN_FHCRC,
+                TRUNCATED_IN_BODY,
+                TRUNCATED_IN_TRAILER
+            ]
+        );
+    }
+
+    #[test]
+    fn write_compressed_block_works() {
+        use crate::checksum::Crc32;
+
+        let text = include_bytes!("../README.md");
+        let mut deflate_encoder = deflate::Encoder::new(Vec::new());
+        deflate_encoder.write_all(text).unwrap();
+        let (blocks, _) = deflate_encoder.checkpoint().unwrap();
+        let mut crc32 = Crc32::new();
+        crc32.update(text);
+
+        let mut encoder = Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(b"Hello ").unwrap();
+        encoder
+            .write_compressed_block(&blocks, crc32.value(), text.len() as u32)
+            .unwrap();
+        encoder.write_all(b"World!").unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        // The decoder verifies the CRC32 and the size in the trailer
+        let mut decoded = Vec::new();
+        Decoder::new(&encoded[..])
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, [&b"Hello "[..], text, b"World!"].concat());
+    }
 }
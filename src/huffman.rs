@@ -80,6 +80,7 @@ impl DecoderBuilder {
         safely_peek_bitwidth: Option<u8>,
         eob_symbol: Option<u16>,
     ) -> io::Result<Decoder> {
+        check_kraft_inequality(bitwidthes)?;
         let builder = Self::new(
             bitwidthes.iter().cloned().max().unwrap_or(0),
             safely_peek_bitwidth,
@@ -132,7 +133,34 @@ impl Builder for DecoderBuilder {
     }
 }
 
-#[derive(Debug)]
+/// Checks that the code lengths form a complete prefix code.
+///
+/// As an exception, a single code of length 1 (or no codes at all) is also allowed
+/// because RFC-1951 permits it for the distance codes.
+fn check_kraft_inequality(bitwidthes: &[u8]) -> io::Result<()> {
+    let full = 1u32 << MAX_BITWIDTH;
+    let mut sum = 0;
+    let mut count = 0;
+    for &width in bitwidthes.iter().filter(|&&w| w > 0) {
+        if width > MAX_BITWIDTH {
+            return Err(invalid_data_error!(
+                "Too long Huffman code length: {}",
+                width
+            ));
+        }
+        sum += full >> width;
+        count += 1;
+    }
+    if sum > full {
+        Err(invalid_data_error!("Over-subscribed Huffman code lengths"))
+    } else if sum < full && !(count == 0 || (count == 1 && sum == full / 2)) {
+        Err(invalid_data_error!("Incomplete Huffman code lengths"))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Decoder {
     table: Vec<u16>,
     safely_peek_bitwidth: u8,
@@ -193,13 +221,20 @@ impl EncoderBuilder {
         let symbol_count = bitwidthes
             .iter()
             .enumerate()
-            .filter(|e| *e.1 > 0)
-            .last()
+            .rfind(|e| *e.1 > 0)
             .map_or(0, |e| e.0)
             + 1;
         let builder = Self::new(symbol_count);
         builder.restore_canonical_huffman_codes(bitwidthes)
     }
+    /// Same as `from_bitwidthes`, but checks that `bitwidthes` form a valid prefix code
+    /// (i.e., neither over-subscribed nor incomplete) in advance.
+    ///
+    /// `from_bitwidthes` trusts its input, so this should be used for untrusted code lengths.
+    pub fn try_from_bitwidthes(bitwidthes: &[u8]) -> io::Result<Encoder> {
+        check_kraft_inequality(bitwidthes)?;
+        Self::from_bitwidthes(bitwidthes)
+    }
     pub fn from_frequencies(symbol_frequencies: &[usize], max_bitwidth: u8) -> io::Result<Encoder> {
         let max_bitwidth = cmp::min(
             max_bitwidth,
@@ -245,6 +280,12 @@ impl Encoder {
         );
         self.table[symbol as usize].clone()
     }
+    pub fn bitwidthes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.table.iter().map(|c| c.width)
+    }
+    pub fn has_code(&self, symbol: u16) -> bool {
+        self.table.get(symbol as usize).is_some_and(|c| c.width > 0)
+    }
     pub fn used_max_symbol(&self) -> Option<u16> {
         self.table
             .iter()
@@ -365,6 +406,59 @@ mod length_limited_huffman_codes {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {}
+
+    #[test]
+    fn decode_short_code_at_end_of_stream_works() {
+        // The decoder peeks 2 bits, but the last code only has 1 bit in the stream
+        let decoder = DecoderBuilder::from_bitwidthes(&[1, 2, 2], Some(2), None).unwrap();
+        let mut reader = bit::BitReader::new(&[0b0000_0000][..]);
+        for _ in 0..8 {
+            assert_eq!(decoder.decode(&mut reader).unwrap(), 0);
+        }
+        assert!(decoder.decode(&mut reader).is_err());
+    }
+
+    #[test]
+    fn decoder_builder_checks_kraft_inequality() {
+        assert!(DecoderBuilder::from_bitwidthes(&[1, 2, 2], None, None).is_ok());
+        assert!(DecoderBuilder::from_bitwidthes(&[0, 1, 0], None, None).is_ok());
+        assert!(DecoderBuilder::from_bitwidthes(&[0, 0, 0], None, None).is_ok());
+
+        // Over-subscribed
+        assert!(DecoderBuilder::from_bitwidthes(&[1, 1, 1], None, None).is_err());
+
+        // Incomplete
+        assert!(DecoderBuilder::from_bitwidthes(&[1, 2, 0], None, None).is_err());
+        assert!(DecoderBuilder::from_bitwidthes(&[2, 0, 0], None, None).is_err());
+    }
+
+    #[test]
+    fn decoder_builder_rejects_too_long_code_lengths() {
+        // Complete, but longer than DEFLATE allows (so the table would have 2^16 entries)
+        let mut bitwidthes = vec![16; 2];
+        bitwidthes.extend((1..16).rev());
+        let e = DecoderBuilder::from_bitwidthes(&bitwidthes, None, None).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn encoder_builder_try_from_bitwidthes_works() {
+        let encoder = EncoderBuilder::try_from_bitwidthes(&[1, 2, 2]).unwrap();
+        assert_eq!(encoder.bitwidthes().collect::<Vec<_>>(), [1, 2, 2]);
+        assert!(EncoderBuilder::try_from_bitwidthes(&[0, 1, 0]).is_ok());
+
+        // Over-subscribed
+        let e = EncoderBuilder::try_from_bitwidthes(&[1, 1, 1]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        // Incomplete
+        assert!(EncoderBuilder::try_from_bitwidthes(&[1, 2, 0]).is_err());
+
+        // Too long
+        assert!(EncoderBuilder::try_from_bitwidthes(&[16, 16]).is_err());
+    }
 }
@@ -1,10 +1,48 @@
 //! A Rust implementation of DEFLATE algorithm and related formats (ZLIB, GZIP).
+//!
+//! # `no_std` support
+//!
+//! All the encoders and decoders (including GZIP and ZLIB) are available in `no_std` environments
+//! that provide `alloc`, by disabling the default `std` feature:
+//!
+//! ```toml
+//! [dependencies]
+//! libflate = { version = "2", default-features = false }
+//! ```
+//!
+//! In that case, the I/O traits of [`core2::io`] are used instead of those of `std::io`,
+//! and the default modification time of a GZIP header (see `gzip::HeaderBuilder::new`) is `0`.
+//!
+//! # SIMD accelerated checksums
+//!
+//! By default, the Adler-32 (ZLIB) and CRC-32 (GZIP) checksums use only the SIMD instructions
+//! enabled at compile time (e.g., by `-C target-cpu=native`), falling back to the scalar implementations otherwise.
+//! The `simd` feature enables the runtime detection of the instructions available on the running CPU
+//! (e.g., AVX2 for Adler-32 and PCLMULQDQ for CRC-32), which makes the checksums of large inputs several times faster
+//! on a generic build for x86_64.
+//! The computed checksums are the same regardless of the feature. This feature requires `std`.
+//!
+//! # Deterministic output
+//!
+//! For a fixed version of this crate and fixed encoding options, the encoders are deterministic:
+//! the same input always yields the same bytes, regardless of how the input is split into writes,
+//! the platform, and the enabled features.
+//! Note that a GZIP header records the current time by default (when `std` is enabled),
+//! so the modification time should be fixed (e.g., `gzip::HeaderBuilder::modification_time`) to get reproducible GZIP output.
+//!
+//! The output may change between versions (e.g., by improvements of the compression),
+//! so byte-exact outputs should not be pinned across upgrades.
 
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub use auto::{compress_best, decompress_auto, DeflateOrZlibDecoder, Format};
+pub use checksum::ChecksumMismatchPolicy;
 pub use finish::Finish;
+pub use tee::TeeWriter;
+pub use transcode::transcode_gzip_to_zlib;
+pub use zlib::verify_roundtrip;
 extern crate alloc;
 
 macro_rules! invalid_data_error {
@@ -25,6 +63,7 @@ macro_rules! finish_try {
     };
 }
 
+pub mod checksum;
 pub mod deflate;
 pub mod finish;
 pub mod gzip;
@@ -32,7 +71,38 @@ pub mod lz77;
 pub mod non_blocking;
 pub mod zlib;
 
+mod auto;
 mod bit;
-mod checksum;
 mod huffman;
+mod tee;
+mod transcode;
 mod util;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn decoders_and_encoders_are_send() {
+        type R = &'static [u8];
+        assert_send_sync::<deflate::Decoder<R>>();
+        assert_send_sync::<zlib::Decoder<R>>();
+        assert_send_sync::<gzip::Decoder<R>>();
+        assert_send_sync::<gzip::EventDecoder<R>>();
+        assert_send_sync::<non_blocking::deflate::Decoder<R>>();
+        assert_send_sync::<non_blocking::zlib::Decoder<R>>();
+        assert_send_sync::<non_blocking::gzip::Decoder<R>>();
+
+        // `MultiDecoder` is not `Sync` as the member filter is only required to be `Send`
+        assert_send::<gzip::MultiDecoder<R>>();
+
+        // The encoders are not `Sync` as the progress callbacks are only required to be `Send`
+        assert_send::<deflate::Encoder<Vec<u8>>>();
+        assert_send::<zlib::Encoder<Vec<u8>>>();
+        assert_send::<gzip::Encoder<Vec<u8>>>();
+    }
+}
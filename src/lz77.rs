@@ -7,7 +7,9 @@ pub use libflate_lz77::*;
 mod tests {
     use super::*;
     use crate::deflate::symbol::Symbol;
+    use crate::deflate::{Decoder, EncodeOptions, Encoder};
     use alloc::{vec, vec::Vec};
+    use core2::io::{Read as _, Write as _};
 
     #[test]
     // See: https://github.com/sile/libflate/issues/21
@@ -27,4 +29,103 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn optimal_encoder_works() {
+        let text = include_bytes!("../README.md");
+
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(text).unwrap();
+        let default_encoded = encoder.finish().into_result().unwrap();
+
+        let options = EncodeOptions::with_lz77(OptimalLz77Encoder::new());
+        let mut encoder = Encoder::with_options(Vec::new(), options);
+        encoder.write_all(text).unwrap();
+        let optimal_encoded = encoder.finish().into_result().unwrap();
+        assert!(optimal_encoded.len() < default_encoded.len());
+
+        let mut decoded = Vec::new();
+        Decoder::new(&optimal_encoded[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+    }
+
+    #[test]
+    fn huffman_only_encoder_works() {
+        let mut sink = Vec::<Symbol>::new();
+        HuffmanOnlyLz77Encoder::new().encode(b"aaaaa", &mut sink);
+        assert!(sink
+            .iter()
+            .all(|s| matches!(s, Symbol::Code(Code::Literal(b'a')))));
+
+        // Skewed byte distribution without repeated strings
+        let mut x = 1u32;
+        let text: Vec<u8> = (0..10000)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                b"aaaaaaabbbc"[(x % 11) as usize]
+            })
+            .collect();
+
+        let options =
+            EncodeOptions::with_lz77(HuffmanOnlyLz77Encoder::new()).dynamic_huffman_codes();
+        let mut encoder = Encoder::with_options(Vec::new(), options);
+        encoder.write_all(&text).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        assert!(encoded.len() < text.len() / 4);
+
+        let mut decoded = Vec::new();
+        Decoder::new(&encoded[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+    }
+
+    #[test]
+    fn build_dictionary_works() {
+        use crate::deflate::DeflateDictionary;
+        use alloc::format;
+
+        let record = |i: u32| {
+            format!(
+                r#"{{"id":{},"name":"user{}","email":"user{}@example.com","status":"active","roles":["reader","writer"]}}"#,
+                i,
+                i * 7919 % 1000,
+                i * 104729 % 1000
+            )
+            .into_bytes()
+        };
+        let samples = (0..100).map(record).collect::<Vec<_>>();
+        let samples = samples.iter().map(|s| &s[..]).collect::<Vec<_>>();
+        let dictionary = build_dictionary(&samples, 4096);
+        assert!(!dictionary.is_empty());
+        assert!(dictionary.len() <= 4096);
+        assert!(build_dictionary(&samples, 10).len() <= 10);
+        assert!(build_dictionary(&[], 4096).is_empty());
+
+        let (mut with_dictionary, mut without_dictionary) = (0, 0);
+        for i in 1000..1100 {
+            let text = record(i);
+
+            let mut encoder = Encoder::new(Vec::new());
+            encoder.write_all(&text).unwrap();
+            without_dictionary += encoder.finish().into_result().unwrap().len();
+
+            let mut encoder =
+                Encoder::resume(Vec::new(), DeflateDictionary::new(dictionary.clone()));
+            encoder.write_all(&text).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+            with_dictionary += encoded.len();
+
+            let mut decoder =
+                crate::non_blocking::deflate::Decoder::with_dictionary(&encoded[..], &dictionary);
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, text);
+        }
+        assert!(with_dictionary < without_dictionary / 2);
+    }
 }
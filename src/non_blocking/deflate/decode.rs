@@ -1,6 +1,7 @@
 use crate::deflate::symbol::{self, HuffmanCodec};
 use crate::lz77;
 use crate::non_blocking::transaction::TransactionalBitReader;
+use alloc::borrow::Cow;
 use core::cmp;
 use core2::io::{self, Read};
 /// DEFLATE decoder which supports non-blocking I/O.
@@ -10,6 +11,8 @@ pub struct Decoder<R> {
     eos: bool,
     bit_reader: TransactionalBitReader<R>,
     block_decoder: BlockDecoder,
+    needs_input: bool,
+    total_out: u64,
 }
 impl<R: Read> Decoder<R> {
     /// Makes a new decoder instance.
@@ -34,9 +37,40 @@ impl<R: Read> Decoder<R> {
             eos: false,
             bit_reader: TransactionalBitReader::new(inner),
             block_decoder: BlockDecoder::new(),
+            needs_input: false,
+            total_out: 0,
         }
     }
 
+    /// Makes a new decoder instance whose history is preloaded with `dictionary`.
+    ///
+    /// The dictionary is not emitted as output, but the pointers in `inner` may refer to it
+    /// (only the last [`MAX_DISTANCE`](crate::lz77::MAX_DISTANCE) bytes of the dictionary can be referred).
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::deflate::{DeflateDictionary, Encoder};
+    /// use libflate::non_blocking::deflate::Decoder;
+    ///
+    /// let dictionary = b"Hello World!".to_vec();
+    /// let mut encoder = Encoder::resume(Vec::new(), DeflateDictionary::new(dictionary.clone()));
+    /// encoder.write_all(b"Hello World! Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoder = Decoder::with_dictionary(&encoded_data[..], &dictionary);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World! Hello World!");
+    /// ```
+    pub fn with_dictionary(inner: R, dictionary: &[u8]) -> Self {
+        let mut this = Self::new(inner);
+        let start = dictionary.len().saturating_sub(lz77::MAX_DISTANCE as usize);
+        this.block_decoder.extend(&dictionary[start..]);
+        this
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &R {
         self.bit_reader.as_inner_ref()
@@ -62,12 +96,63 @@ impl<R: Read> Decoder<R> {
         self.bit_reader.into_inner()
     }
 
+    /// Returns `true` if the last `read` call failed with `WouldBlock`
+    /// because the inner stream had no more input available.
+    ///
+    /// In that case, the caller should wait until the inner stream becomes readable before retrying.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{self, Read};
+    /// use libflate::non_blocking::deflate::Decoder;
+    ///
+    /// struct Starved;
+    /// impl Read for Starved {
+    ///     fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+    ///         Err(io::Error::new(io::ErrorKind::WouldBlock, "Would block"))
+    ///     }
+    /// }
+    ///
+    /// let mut decoder = Decoder::new(Starved);
+    /// assert!(!decoder.needs_input());
+    ///
+    /// let e = decoder.read(&mut [0; 16]).unwrap_err();
+    /// assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+    /// assert!(decoder.needs_input());
+    /// ```
+    pub fn needs_input(&self) -> bool {
+        self.needs_input
+    }
+
+    /// Returns the total number of decoded bytes handed to the caller so far.
+    ///
+    /// The bytes of the dictionary given to [`Decoder::with_dictionary`] are not counted.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::non_blocking::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    ///
+    /// let mut buf = [0; 5];
+    /// decoder.read_exact(&mut buf).unwrap();
+    /// assert_eq!(decoder.total_out(), 5);
+    ///
+    /// decoder.read_to_end(&mut Vec::new()).unwrap();
+    /// assert_eq!(decoder.total_out(), 12);
+    /// ```
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
     pub(crate) fn bit_reader_mut(&mut self) -> &mut TransactionalBitReader<R> {
         &mut self.bit_reader
     }
 }
-impl<R: Read> Read for Decoder<R> {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+impl<R: Read> Decoder<R> {
+    fn read_data(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let mut read_size;
         loop {
             let next = match self.state {
@@ -140,7 +225,7 @@ impl<R: Read> Read for Decoder<R> {
                         .transaction(|r| symbol::DynamicHuffmanCodec.load(r))?;
                     DecoderState::DecodeBlock(symbol_decoder)
                 }
-                DecoderState::DecodeBlock(ref mut symbol_decoder) => {
+                DecoderState::DecodeBlock(ref symbol_decoder) => {
                     self.block_decoder
                         .decode(&mut self.bit_reader, symbol_decoder)?;
                     read_size = self.block_decoder.read(buf)?;
@@ -156,6 +241,19 @@ impl<R: Read> Read for Decoder<R> {
         Ok(read_size)
     }
 }
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.bit_reader.take_would_block();
+        let result = self.read_data(buf);
+        let would_block = self.bit_reader.take_would_block();
+        self.needs_input =
+            would_block && matches!(result, Err(ref e) if e.kind() == io::ErrorKind::WouldBlock);
+        if let Ok(size) = result {
+            self.total_out += size as u64;
+        }
+        result
+    }
+}
 
 #[derive(Debug)]
 enum DecoderState {
@@ -164,7 +262,7 @@ enum DecoderState {
     ReadNonCompressedBlock { len: u16 },
     LoadFixedHuffmanCode,
     LoadDynamicHuffmanCode,
-    DecodeBlock(symbol::Decoder),
+    DecodeBlock(Cow<'static, symbol::Decoder>),
 }
 
 #[derive(Debug)]
@@ -185,7 +283,7 @@ impl BlockDecoder {
     pub fn decode<R: Read>(
         &mut self,
         bit_reader: &mut TransactionalBitReader<R>,
-        symbol_decoder: &mut symbol::Decoder,
+        symbol_decoder: &symbol::Decoder,
     ) -> io::Result<()> {
         if self.eob {
             return Ok(());
@@ -211,7 +309,7 @@ impl BlockDecoder {
     fn decode_symbol<R: Read>(
         &mut self,
         bit_reader: &mut TransactionalBitReader<R>,
-        symbol_decoder: &mut symbol::Decoder,
+        symbol_decoder: &symbol::Decoder,
     ) -> io::Result<Option<symbol::Symbol>> {
         let result = bit_reader.transaction(|bit_reader| {
             let s = symbol_decoder.decode_unchecked(bit_reader);
@@ -270,6 +368,84 @@ mod tests {
     }
 
     #[test]
+    fn with_dictionary_works() {
+        use crate::deflate::DeflateDictionary;
+
+        let dictionary: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8).collect();
+        let text = &dictionary[20_000..30_000];
+        let mut encoder = Encoder::resume(Vec::new(), DeflateDictionary::new(dictionary.clone()));
+        encoder.write_all(text).unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+
+        // The stream refers to the dictionary
+        let mut decoded_data = Vec::new();
+        assert!(Decoder::new(&encoded_data[..])
+            .read_to_end(&mut decoded_data)
+            .is_err());
+
+        let decoder =
+            Decoder::with_dictionary(WouldBlockReader::new(&encoded_data[..]), &dictionary);
+        let decoded_data = nb_read_to_end(decoder).unwrap();
+        assert_eq!(decoded_data, text);
+    }
+
+    #[test]
+    fn needs_input_works() {
+        struct PartialReader<'a> {
+            data: &'a [u8],
+            available: usize,
+        }
+        impl Read for PartialReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.available == 0 && !self.data.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::WouldBlock, "Would block"));
+                }
+                let size = cmp::min(buf.len(), self.available);
+                buf[..size].copy_from_slice(&self.data[..size]);
+                self.data = &self.data[size..];
+                self.available -= size;
+                Ok(size)
+            }
+        }
+
+        let text: String = (0..1000).map(|i| format!("test {}", i)).collect();
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+
+        let mut decoder = Decoder::new(PartialReader {
+            data: &encoded_data,
+            available: 0,
+        });
+        assert!(!decoder.needs_input());
+
+        let mut decoded_data = Vec::new();
+        let mut buf = [0; 64];
+        loop {
+            match decoder.read(&mut buf) {
+                Ok(0) => break,
+                Ok(size) => {
+                    assert!(!decoder.needs_input());
+                    decoded_data.extend_from_slice(&buf[..size]);
+                    assert_eq!(decoder.total_out(), decoded_data.len() as u64);
+                }
+                Err(e) => {
+                    assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+                    assert!(decoder.needs_input());
+                    assert_eq!(decoder.total_out(), decoded_data.len() as u64);
+                    assert_eq!(decoder.as_inner_ref().available, 0);
+                    decoder.as_inner_mut().available =
+                        cmp::min(10, decoder.as_inner_ref().data.len());
+                }
+            }
+        }
+        assert!(!decoder.needs_input());
+        assert_eq!(decoded_data, text.as_bytes());
+        assert_eq!(decoder.total_out(), text.len() as u64);
+    }
+
+    #[test]
+    #[allow(clippy::useless_conversion)]
     fn non_blocking_io_for_large_text_works() {
         let text: String = (0..10000)
             .into_iter()
@@ -296,4 +472,13 @@ mod tests {
 
         assert_eq!(decoded_data, b"Hello World!");
     }
+
+    #[test]
+    fn too_long_backward_reference_is_rejected() {
+        // A fixed Huffman block whose first code is a pointer (length=3, distance=1) to empty history
+        let input = [0b0000_0011, 0b0000_0010, 0];
+        let mut decoder = Decoder::new(&input[..]);
+        let e = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
 }
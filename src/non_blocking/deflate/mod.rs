@@ -21,5 +21,7 @@
 //! assert_eq!(decoded_data, b"Hello World!");
 //! ```
 pub use self::decode::Decoder;
+pub use self::push::PushDecoder;
 
 mod decode;
+mod push;
@@ -0,0 +1,162 @@
+use super::Decoder;
+use alloc::vec::Vec;
+use core::cmp;
+use core2::io::{self, Read};
+
+const DRAIN_CHUNK_SIZE: usize = 4096;
+
+/// DEFLATE decoder which is fed by pushing compressed bytes instead of reading them from a stream.
+///
+/// This is useful for integrating with event loops (or channels) which hand over the input
+/// as it arrives.
+///
+/// # Examples
+/// ```
+/// use core2::io::Write;
+/// use libflate::deflate::Encoder;
+/// use libflate::non_blocking::deflate::PushDecoder;
+///
+/// let mut encoder = Encoder::new(Vec::new());
+/// encoder.write_all(b"Hello World!").unwrap();
+/// let encoded_data = encoder.finish().into_result().unwrap();
+///
+/// let mut decoder = PushDecoder::new();
+/// let mut decoded_data = Vec::new();
+/// for chunk in encoded_data.chunks(3) {
+///     decoder.push(chunk).unwrap();
+///     if decoder.drain(&mut decoded_data).unwrap() {
+///         break;
+///     }
+/// }
+/// assert_eq!(decoded_data, b"Hello World!");
+/// ```
+#[derive(Debug)]
+pub struct PushDecoder {
+    decoder: Decoder<PushBuffer>,
+    eos: bool,
+}
+impl PushDecoder {
+    /// Makes a new decoder instance.
+    pub fn new() -> Self {
+        PushDecoder {
+            decoder: Decoder::new(PushBuffer {
+                buf: Vec::new(),
+                offset: 0,
+            }),
+            eos: false,
+        }
+    }
+
+    /// Appends `input` to the compressed bytes to be decoded.
+    ///
+    /// The bytes are not decoded until [`PushDecoder::drain`] is called.
+    /// The bytes pushed after the end of the DEFLATE stream are ignored.
+    pub fn push(&mut self, input: &[u8]) -> io::Result<()> {
+        self.decoder.as_inner_mut().push(input);
+        Ok(())
+    }
+
+    /// Decodes the bytes pushed so far as much as possible, and appends the decoded data to `out`.
+    ///
+    /// Returns `true` if the end of the DEFLATE stream has been reached,
+    /// otherwise more input needs to be pushed.
+    pub fn drain(&mut self, out: &mut Vec<u8>) -> io::Result<bool> {
+        while !self.eos {
+            let start = out.len();
+            out.resize(start + DRAIN_CHUNK_SIZE, 0);
+            let result = self.decoder.read(&mut out[start..]);
+            out.truncate(start + *result.as_ref().unwrap_or(&0));
+            match result {
+                Ok(0) => self.eos = true,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the total number of decoded bytes drained so far.
+    pub fn total_out(&self) -> u64 {
+        self.decoder.total_out()
+    }
+}
+impl Default for PushDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+struct PushBuffer {
+    buf: Vec<u8>,
+    offset: usize,
+}
+impl PushBuffer {
+    fn push(&mut self, input: &[u8]) {
+        self.buf.drain(..self.offset);
+        self.offset = 0;
+        self.buf.extend_from_slice(input);
+    }
+}
+impl Read for PushBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = &self.buf[self.offset..];
+        if remaining.is_empty() && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "No more pushed input",
+            ));
+        }
+        let size = cmp::min(buf.len(), remaining.len());
+        buf[..size].copy_from_slice(&remaining[..size]);
+        self.offset += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deflate::Encoder;
+    use crate::util::deflate_encode_options;
+    use core2::io::Write;
+
+    #[test]
+    fn push_arbitrary_sized_chunks_works() {
+        let text = include_bytes!("../../../README.md").repeat(10);
+        for options in deflate_encode_options() {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&text).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            for chunk_size in [1, 7, 100, encoded.len()] {
+                let mut decoder = PushDecoder::new();
+                let mut decoded = Vec::new();
+                let mut eos = false;
+                for chunk in encoded.chunks(chunk_size) {
+                    assert!(!eos);
+                    decoder.push(chunk).unwrap();
+                    eos = decoder.drain(&mut decoded).unwrap();
+                }
+                assert!(eos);
+                assert_eq!(decoded, text);
+                assert_eq!(decoder.total_out(), text.len() as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn drain_without_input_needs_more_input() {
+        let mut decoder = PushDecoder::new();
+        let mut decoded = Vec::new();
+        assert!(!decoder.drain(&mut decoded).unwrap());
+        assert!(decoded.is_empty());
+
+        decoder.push(&[0xFF]).unwrap();
+        assert_eq!(
+            decoder.drain(&mut decoded).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -83,7 +83,7 @@ impl<R: Read> Decoder<R> {
             let header = self
                 .reader
                 .bit_reader_mut()
-                .transaction(|r| Header::read_from(r.as_inner_mut()))?;
+                .transaction(|r| Header::read_from(r.as_inner_mut(), true))?;
             self.header = Some(header);
             self.header()
         }
@@ -127,10 +127,11 @@ impl<R: Read> Read for Decoder<R> {
         } else {
             let read_size = self.reader.read(buf)?;
             if read_size == 0 {
-                let trailer = self
-                    .reader
+                let mut buf = [0; 8];
+                self.reader
                     .bit_reader_mut()
-                    .transaction(|r| Trailer::read_from(r.as_inner_mut()))?;
+                    .read_exact_transactional(&mut buf)?;
+                let trailer = Trailer::read_from(&buf[..])?;
                 self.eos = true;
                 // checksum verification is skipped during fuzzing
                 // so that random data from fuzzer can reach actually interesting code
@@ -174,6 +175,23 @@ mod tests {
         assert_eq!(decode_all(&encoded).unwrap(), plain);
     }
 
+    #[test]
+    fn trailer_is_validated() {
+        let plain = b"Hello World! Hello GZIP!!";
+        let mut encoder = Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(plain.as_ref()).unwrap();
+        let mut encoded = encoder.finish().into_result().unwrap();
+
+        // The trailer is read across `WouldBlock`s one byte at a time
+        let crc32_offset = encoded.len() - 8;
+        encoded[crc32_offset] ^= 1;
+        let e = decode_all(&encoded).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        encoded[crc32_offset] ^= 1;
+        assert_eq!(decode_all(&encoded).unwrap(), plain);
+    }
+
     #[test]
     fn decode_works_noncompressed_block_offset_sync() {
         let encoded = include_bytes!("../../data/noncompressed_block_offset_sync/offset.gz");
@@ -42,6 +42,29 @@ impl<R: Read> TransactionalBitReader<R> {
     pub fn commit_transaction(&mut self) {
         self.inner.as_inner_mut().commit_transaction();
     }
+    /// Reads exactly `buf.len()` bytes from the byte boundary following the current bit position.
+    ///
+    /// If the bytes are not fully available yet (e.g., `WouldBlock` is returned in the middle),
+    /// the reader is rewound as if this method had not been called, so the read can be retried later.
+    pub fn read_exact_transactional(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.transaction(|r| {
+            // The bytes which have already been read into the bit reader come first
+            r.skip_to_byte_boundary();
+            let buffered = cmp::min(r.buffered_bytes().len(), buf.len());
+            for b in &mut buf[..buffered] {
+                *b = r.read_bits(8)? as u8;
+            }
+            if buffered < buf.len() {
+                r.reset();
+                r.as_inner_mut().read_exact(&mut buf[buffered..])?;
+            }
+            Ok(())
+        })
+    }
+    #[inline]
+    pub fn take_would_block(&mut self) -> bool {
+        core::mem::take(&mut self.inner.as_inner_mut().would_block)
+    }
 }
 impl<R> TransactionalBitReader<R> {
     pub fn as_inner_ref(&self) -> &R {
@@ -61,6 +84,7 @@ pub struct TransactionalReader<R> {
     in_transaction: bool,
     buffer: Vec<u8>,
     offset: usize,
+    would_block: bool,
 }
 impl<R> TransactionalReader<R> {
     pub fn new(inner: R) -> Self {
@@ -69,6 +93,7 @@ impl<R> TransactionalReader<R> {
             buffer: Vec::new(),
             in_transaction: false,
             offset: 0,
+            would_block: false,
         }
     }
     #[inline]
@@ -98,7 +123,13 @@ impl<R: Read> Read for TransactionalReader<R> {
             return Ok(size);
         }
 
-        let size = self.inner.read(buf)?;
+        let size = match self.inner.read(buf) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.would_block = true;
+                return Err(e);
+            }
+            result => result?,
+        };
         if self.in_transaction {
             self.buffer.extend_from_slice(&buf[0..size]);
             self.offset += size;
@@ -106,3 +137,60 @@ impl<R: Read> Read for TransactionalReader<R> {
         Ok(size)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reader which returns `WouldBlock` once the first `available` bytes have been read.
+    struct PartialReader {
+        data: Vec<u8>,
+        offset: usize,
+        available: usize,
+    }
+    impl Read for PartialReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let end = cmp::min(self.available, self.data.len());
+            if self.offset == end && self.offset < self.data.len() {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "Would block"));
+            }
+            let size = cmp::min(buf.len(), end - self.offset);
+            buf[..size].copy_from_slice(&self.data[self.offset..self.offset + size]);
+            self.offset += size;
+            Ok(size)
+        }
+    }
+
+    #[test]
+    fn read_exact_transactional_works() {
+        let data = Vec::from(&[0b1010_0101, 1, 2, 3, 4, 5, 6, 7, 8][..]);
+        let mut reader = TransactionalBitReader::new(PartialReader {
+            data,
+            offset: 0,
+            available: 0,
+        });
+
+        // Consumes a part of the first byte, and reads the next byte ahead
+        reader.as_inner_mut().available = 2;
+        let bits = reader.transaction(|r| {
+            let bits = r.read_bits(3)?;
+            r.peek_bits_unchecked(13);
+            r.check_last_error().map(|()| bits)
+        });
+        assert_eq!(bits.unwrap(), 0b101);
+
+        // The (byte-aligned) trailer arrives in pieces
+        let mut buf = [0; 8];
+        for available in 2..9 {
+            reader.as_inner_mut().available = available;
+            let e = reader.read_exact_transactional(&mut buf).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        }
+        reader.as_inner_mut().available = 9;
+        reader.read_exact_transactional(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let e = reader.read_exact_transactional(&mut buf[..1]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -82,7 +82,8 @@ impl<R: Read> Decoder<R> {
             let header = self
                 .reader
                 .bit_reader_mut()
-                .transaction(|r| Header::read_from(r.as_inner_mut()))?;
+                .transaction(|r| Header::read_from(r.as_inner_mut(), true))?;
+            header.check_no_preset_dictionary()?;
             self.header = Some(header);
             self.header()
         }
@@ -125,12 +126,11 @@ impl<R: Read> Read for Decoder<R> {
         } else {
             let read_size = self.reader.read(buf)?;
             if read_size == 0 {
-                let adler32 = self.reader.bit_reader_mut().transaction(|r| {
-                    let mut buf = [0; 4];
-                    r.as_inner_mut()
-                        .read_exact(&mut buf)
-                        .and(Ok(u32::from_be_bytes(buf)))
-                })?;
+                let mut buf = [0; 4];
+                self.reader
+                    .bit_reader_mut()
+                    .read_exact_transactional(&mut buf)?;
+                let adler32 = u32::from_be_bytes(buf);
                 self.eos = true;
                 // checksum verification is skipped during fuzzing
                 // so that random data from fuzzer can reach actually interesting code
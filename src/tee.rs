@@ -0,0 +1,133 @@
+use core2::io::{self, Write};
+
+/// A writer which duplicates every byte written to it into two writers.
+///
+/// This is useful to send the output of an encoder to a secondary sink (e.g., a mirror or a hasher)
+/// without compressing the data twice.
+///
+/// The bytes are written to the primary writer first, and only the bytes accepted by it
+/// are then written to the secondary writer (with `write_all`).
+/// So `write` returns the number of bytes accepted by the primary writer,
+/// and a short write of the primary writer never makes the secondary writer get ahead of it.
+///
+/// # Errors
+///
+/// The bytes accepted by the primary writer cannot be taken back.
+/// If the secondary writer fails after that, the error is returned
+/// although the primary writer has already received the bytes,
+/// so the two writers are out of sync and the `TeeWriter` should not be written to any more.
+///
+/// # Examples
+/// ```
+/// use core2::io::{Read, Write};
+/// use libflate::{zlib, TeeWriter};
+///
+/// let mut encoder = zlib::Encoder::new(TeeWriter::new(Vec::new(), Vec::new())).unwrap();
+/// encoder.write_all(b"Hello World!").unwrap();
+/// let (primary, secondary) = encoder.finish().into_result().unwrap().into_inner();
+/// assert_eq!(primary, secondary);
+///
+/// let mut decoded_data = Vec::new();
+/// zlib::Decoder::new(&secondary[..]).unwrap().read_to_end(&mut decoded_data).unwrap();
+/// assert_eq!(decoded_data, b"Hello World!");
+/// ```
+#[derive(Debug)]
+pub struct TeeWriter<A, B> {
+    primary: A,
+    secondary: B,
+}
+impl<A, B> TeeWriter<A, B>
+where
+    A: Write,
+    B: Write,
+{
+    /// Makes a new `TeeWriter` which writes to both `primary` and `secondary`.
+    pub fn new(primary: A, secondary: B) -> Self {
+        TeeWriter { primary, secondary }
+    }
+
+    /// Returns the immutable references to the primary and the secondary writers.
+    pub fn as_inner_ref(&self) -> (&A, &B) {
+        (&self.primary, &self.secondary)
+    }
+
+    /// Returns the mutable references to the primary and the secondary writers.
+    pub fn as_inner_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.primary, &mut self.secondary)
+    }
+
+    /// Unwraps this `TeeWriter`, returning the primary and the secondary writers.
+    pub fn into_inner(self) -> (A, B) {
+        (self.primary, self.secondary)
+    }
+}
+impl<A, B> Write for TeeWriter<A, B>
+where
+    A: Write,
+    B: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // See the "Errors" section of the type-level documentation for a failure of the secondary writer
+        let size = self.primary.write(buf)?;
+        self.secondary.write_all(&buf[..size])?;
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.primary.flush()?;
+        self.secondary.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gzip;
+    use alloc::vec::Vec;
+    use core2::io::Read;
+
+    /// A writer which accepts at most 3 bytes at a time.
+    struct ShortWriter(Vec<u8>);
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let size = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..size]);
+            Ok(size)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tee_gzip_encoder_works() {
+        let text = include_bytes!("../README.md");
+        let writer = TeeWriter::new(ShortWriter(Vec::new()), Vec::new());
+        let mut encoder = gzip::Encoder::new(writer).unwrap();
+        encoder.write_all(text).unwrap();
+        let (primary, secondary) = encoder.finish().into_result().unwrap().into_inner();
+        assert_eq!(primary.0, secondary);
+
+        for encoded in [&primary.0, &secondary] {
+            let mut decoded = Vec::new();
+            gzip::Decoder::new(&encoded[..])
+                .unwrap()
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, text);
+        }
+    }
+
+    #[test]
+    fn secondary_error_is_reported_after_primary_write() {
+        let mut secondary = [0; 4];
+        let mut writer = TeeWriter::new(Vec::new(), &mut secondary[..]);
+        assert_eq!(writer.write(b"foo").unwrap(), 3);
+
+        let e = writer.write(b"bar").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+        let (primary, _) = writer.into_inner();
+        assert_eq!(primary, b"foobar");
+        assert_eq!(secondary, *b"foob");
+    }
+}
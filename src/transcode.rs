@@ -0,0 +1,166 @@
+use alloc::vec::Vec;
+use core2::io::{self, Read, Write};
+
+use crate::checksum::{Adler32, Crc32};
+use crate::deflate;
+use crate::gzip;
+use crate::zlib;
+
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Converts the first member of a GZIP stream read from `reader` into a ZLIB stream written to `writer`.
+///
+/// The DEFLATE compressed data is copied verbatim (i.e., it is not re-compressed).
+/// It is still decoded once, to verify the CRC-32 checksum of the GZIP trailer
+/// and to compute the Adler-32 checksum of the ZLIB trailer.
+/// If the CRC-32 checksum or the size of the decoded data is mismatched, an `InvalidData` error is returned.
+///
+/// The compression level of the ZLIB header is derived from the one of the GZIP header,
+/// and the window size is always 32 KB (GZIP does not record it).
+///
+/// # Examples
+/// ```
+/// use core2::io::{Read, Write};
+/// use libflate::{gzip, zlib};
+///
+/// let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+/// encoder.write_all(b"Hello World!").unwrap();
+/// let gzip_data = encoder.finish().into_result().unwrap();
+///
+/// let mut zlib_data = Vec::new();
+/// libflate::transcode_gzip_to_zlib(&gzip_data[..], &mut zlib_data).unwrap();
+///
+/// let mut decoded_data = Vec::new();
+/// zlib::Decoder::new(&zlib_data[..]).unwrap().read_to_end(&mut decoded_data).unwrap();
+/// assert_eq!(decoded_data, b"Hello World!");
+/// ```
+pub fn transcode_gzip_to_zlib<R, W>(mut reader: R, mut writer: W) -> io::Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let gzip_header = gzip::Header::read_from(&mut reader, true)?;
+    let compression_level = match gzip_header.compression_level() {
+        gzip::CompressionLevel::Fastest => zlib::CompressionLevel::Fastest,
+        gzip::CompressionLevel::Slowest => zlib::CompressionLevel::Slowest,
+        gzip::CompressionLevel::Unknown => zlib::CompressionLevel::Default,
+    };
+    zlib::Header::new(zlib::Lz77WindowSize::KB32, compression_level).write_to(&mut writer)?;
+
+    let mut decoder = deflate::Decoder::new(CopyReader {
+        inner: &mut reader,
+        writer: &mut writer,
+        buf: Vec::with_capacity(BUFFER_SIZE),
+    });
+    let mut adler32 = Adler32::new();
+    let mut crc32 = Crc32::new();
+    let mut size = 0u32;
+    let mut buf = [0; BUFFER_SIZE];
+    loop {
+        let read_size = decoder.read(&mut buf)?;
+        if read_size == 0 {
+            break;
+        }
+        adler32.update(&buf[..read_size]);
+        crc32.update(&buf[..read_size]);
+        size = size.wrapping_add(read_size as u32);
+    }
+    decoder.into_inner().flush_buf()?;
+
+    let trailer = gzip::Trailer::read_from(&mut reader)?;
+    if trailer.crc32() != crc32.value() {
+        return Err(invalid_data_error!(
+            "CRC32 mismatched: value={}, expected={}",
+            crc32.value(),
+            trailer.crc32()
+        ));
+    }
+    if trailer.input_size() != size {
+        return Err(invalid_data_error!(
+            "ISIZE mismatched: value={}, expected={}",
+            size,
+            trailer.input_size()
+        ));
+    }
+    writer.write_all(&adler32.value().to_be_bytes())?;
+    writer.flush()
+}
+
+/// A reader which copies the bytes read through it to `writer`.
+///
+/// The bytes are buffered, as the DEFLATE decoder reads the inner stream a byte at a time.
+struct CopyReader<R, W> {
+    inner: R,
+    writer: W,
+    buf: Vec<u8>,
+}
+impl<R, W: Write> CopyReader<R, W> {
+    fn flush_buf(&mut self) -> io::Result<()> {
+        self.writer.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+impl<R: Read, W: Write> Read for CopyReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.buf.extend_from_slice(&buf[..size]);
+        if self.buf.len() >= BUFFER_SIZE {
+            self.flush_buf()?;
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcode_gzip_to_zlib_works() {
+        let text = include_bytes!("../README.md");
+        for options in [
+            gzip::EncodeOptions::new(),
+            gzip::EncodeOptions::new().fixed_huffman_codes(),
+            gzip::EncodeOptions::new().no_compression(),
+        ] {
+            let mut encoder = gzip::Encoder::with_options(Vec::new(), options).unwrap();
+            encoder.write_all(text).unwrap();
+            let gzip_data = encoder.finish().into_result().unwrap();
+
+            // Trailing data (e.g., the next member) is left in the reader
+            let mut reader = &[&gzip_data[..], b"rest"].concat()[..];
+            let mut zlib_data = Vec::new();
+            transcode_gzip_to_zlib(&mut reader, &mut zlib_data).unwrap();
+            assert_eq!(reader, b"rest");
+
+            // The DEFLATE data is copied verbatim
+            assert_eq!(
+                zlib_data[2..zlib_data.len() - 4],
+                gzip_data[10..gzip_data.len() - 8]
+            );
+
+            let mut decoded = Vec::new();
+            zlib::Decoder::new(&zlib_data[..])
+                .unwrap()
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, text);
+        }
+    }
+
+    #[test]
+    fn transcode_gzip_to_zlib_rejects_corrupted_trailer() {
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let gzip_data = encoder.finish().into_result().unwrap();
+
+        for i in [8, 4] {
+            let mut broken = gzip_data.clone();
+            let len = broken.len();
+            broken[len - i] ^= 1;
+            let e = transcode_gzip_to_zlib(&broken[..], Vec::new()).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
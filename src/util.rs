@@ -1,3 +1,17 @@
+use core2::io;
+
+/// Replaces an `UnexpectedEof` error with the one describing where the stream is truncated.
+pub(crate) fn truncated(e: io::Error, message: &'static str) -> io::Error {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        io::Error::new(io::ErrorKind::UnexpectedEof, message)
+    } else {
+        e
+    }
+}
+
+#[cfg(test)]
+pub(crate) use testonly::*;
+
 #[cfg(test)]
 pub(crate) mod testonly {
     use alloc::vec;
@@ -37,6 +51,24 @@ pub(crate) mod testonly {
         }
     }
 
+    /// Returns the options of a DEFLATE encoder for each kind of block
+    /// (dynamic Huffman codes, fixed Huffman codes and no compression).
+    pub fn deflate_encode_options() -> [crate::deflate::EncodeOptions; 3] {
+        use crate::deflate::EncodeOptions;
+        [
+            EncodeOptions::new(),
+            EncodeOptions::new().fixed_huffman_codes(),
+            EncodeOptions::new().no_compression(),
+        ]
+    }
+
+    /// Returns `len` bytes of deterministic pseudo-random data, which is hardly compressible.
+    pub fn pseudo_random_bytes(len: usize) -> Vec<u8> {
+        (0..len as u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect()
+    }
+
     pub fn nb_read_to_end<R: Read>(mut reader: R) -> io::Result<Vec<u8>> {
         let mut buf = vec![0; 1024];
         let mut offset = 0;
@@ -62,5 +94,3 @@ pub(crate) mod testonly {
         Ok(buf)
     }
 }
-#[cfg(test)]
-pub(crate) use testonly::*;
@@ -19,14 +19,20 @@
 //!
 //! assert_eq!(decoded_data, b"Hello World!");
 //! ```
-use crate::checksum;
+use crate::checksum::{self, ChecksumMismatchPolicy};
 use crate::deflate;
 use crate::finish::{Complete, Finish};
 use crate::lz77;
-use core2::io;
+use crate::util::truncated;
+use alloc::vec::Vec;
+use core::cmp;
+use core2::io::{self, Read as _, Write as _};
 
 const COMPRESSION_METHOD_DEFLATE: u8 = 8;
 
+const TRUNCATED_IN_HEADER: &str = "ZLIB stream too short: need at least 2 header bytes";
+const TRUNCATED_IN_DICTID: &str = "ZLIB stream is truncated in the DICTID field of the header";
+
 /// Compression levels defined by the ZLIB format.
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub enum CompressionLevel {
@@ -190,6 +196,13 @@ pub enum FlushMode {
     #[default]
     None = 0,
 
+    /// `Z_PARTIAL_FLUSH`.
+    ///
+    /// The current DEFLATE block is terminated and followed by an empty fixed Huffman block,
+    /// so that the decoder can decode all the data written so far.
+    /// Unlike `Sync`, the stream is not aligned to a byte boundary, which saves a few bytes.
+    Partial = 1,
+
     /// `Z_SYNC_FLUSH`.
     Sync = 2,
 }
@@ -199,6 +212,9 @@ pub enum FlushMode {
 pub struct Header {
     window_size: Lz77WindowSize,
     compression_level: CompressionLevel,
+    cmf: u8,
+    flg: u8,
+    dictionary_id: Option<u32>,
 }
 impl Header {
     /// Returns the LZ77 window size stored in the header.
@@ -209,21 +225,81 @@ impl Header {
     pub fn compression_level(&self) -> CompressionLevel {
         self.compression_level.clone()
     }
+    /// Returns the raw CMF (Compression Method and flags) byte of the header.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::zlib::Decoder;
+    ///
+    /// let encoded = [120, 156, 3, 0, 0, 0, 0, 1];
+    /// let decoder = Decoder::new(&encoded[..]).unwrap();
+    /// assert_eq!(decoder.header().cmf(), 120);
+    /// assert_eq!(decoder.header().flg(), 156);
+    /// ```
+    pub fn cmf(&self) -> u8 {
+        self.cmf
+    }
+    /// Returns the raw FLG (FLaGs) byte of the header.
+    pub fn flg(&self) -> u8 {
+        self.flg
+    }
+    /// Returns `true` if the stream requires a preset dictionary (i.e., the FDICT flag is set).
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::zlib;
+    ///
+    /// // FDICT is set and DICTID is `0x01020304`
+    /// let encoded = [0x78, 0xBB, 1, 2, 3, 4, 3, 0];
+    /// let (header, _) = zlib::read_header(&encoded[..]).unwrap();
+    /// assert!(header.has_preset_dictionary());
+    /// assert_eq!(header.dictionary_id(), Some(0x01020304));
+    /// ```
+    pub fn has_preset_dictionary(&self) -> bool {
+        self.dictionary_id.is_some()
+    }
+    /// Returns the identifier (the Adler-32 checksum) of the preset dictionary required by the stream.
+    ///
+    /// If the stream does not use a preset dictionary, `None` is returned.
+    pub fn dictionary_id(&self) -> Option<u32> {
+        self.dictionary_id
+    }
     fn from_lz77<E>(lz77: &E) -> Self
     where
         E: lz77::Lz77Encode,
     {
+        Self::new(
+            Lz77WindowSize::from_u16(lz77.window_size()),
+            CompressionLevel::from(lz77.compression_level()),
+        )
+    }
+    pub(crate) fn new(window_size: Lz77WindowSize, compression_level: CompressionLevel) -> Self {
+        let cmf = (window_size.as_u4() << 4) | COMPRESSION_METHOD_DEFLATE;
+        let mut flg = compression_level.as_u2() << 6;
+        let check = (u16::from(cmf) << 8) + u16::from(flg);
+        if check % 31 != 0 {
+            flg += (31 - check % 31) as u8;
+        }
         Header {
-            compression_level: From::from(lz77.compression_level()),
-            window_size: Lz77WindowSize::from_u16(lz77.window_size()),
+            compression_level,
+            window_size,
+            cmf,
+            flg,
+            dictionary_id: None,
         }
     }
-    pub(crate) fn read_from<R>(mut reader: R) -> io::Result<Self>
+    /// Reads a header from `reader`.
+    ///
+    /// If `strict` is `false`, CINFO values above 7 are treated as 7 (i.e., 32 KB window)
+    /// instead of being rejected.
+    pub(crate) fn read_from<R>(mut reader: R, strict: bool) -> io::Result<Self>
     where
         R: io::Read,
     {
         let mut buf = [0; 2];
-        reader.read_exact(&mut buf)?;
+        reader
+            .read_exact(&mut buf)
+            .map_err(|e| truncated(e, TRUNCATED_IN_HEADER))?;
         let [cmf, flg] = buf;
         let check = (u16::from(cmf) << 8) + u16::from(flg);
         if check % 31 != 0 {
@@ -244,47 +320,160 @@ impl Header {
                 compression_method
             ));
         }
-        let window_size = Lz77WindowSize::from_u4(compression_info).ok_or_else(|| {
-            invalid_data_error!("CINFO above 7 are not allowed: value={}", compression_info)
-        })?;
+        let window_size = match Lz77WindowSize::from_u4(compression_info) {
+            Some(window_size) => window_size,
+            None if !strict => Lz77WindowSize::KB32,
+            None => {
+                return Err(invalid_data_error!(
+                    "CINFO above 7 are not allowed: value={}",
+                    compression_info
+                ))
+            }
+        };
 
         let dict_flag = (flg & 0b10_0000) != 0;
-        if dict_flag {
+        let dictionary_id = if dict_flag {
             let mut buf = [0; 4];
-            reader.read_exact(&mut buf)?;
-            return Err(invalid_data_error!(
-                "Preset dictionaries are not supported: \
-                 dictionary_id=0x{:X}",
-                u32::from_be_bytes(buf)
-            ));
-        }
+            reader
+                .read_exact(&mut buf)
+                .map_err(|e| truncated(e, TRUNCATED_IN_DICTID))?;
+            Some(u32::from_be_bytes(buf))
+        } else {
+            None
+        };
         let compression_level = CompressionLevel::from_u2(flg >> 6);
         Ok(Header {
             window_size,
             compression_level,
+            cmf,
+            flg,
+            dictionary_id,
         })
     }
-    fn write_to<W>(&self, mut writer: W) -> io::Result<()>
+    /// Returns an error if the stream requires a preset dictionary, which the decoders do not support.
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    pub(crate) fn check_no_preset_dictionary(&self) -> io::Result<()> {
+        match self.dictionary_id {
+            Some(dictionary_id) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                #[cfg(feature = "std")]
+                format!(
+                    "Preset dictionaries are not supported: dictionary_id=0x{dictionary_id:08X}"
+                ),
+                #[cfg(not(feature = "std"))]
+                "Preset dictionaries are not supported",
+            )),
+            None => Ok(()),
+        }
+    }
+    pub(crate) fn write_to<W>(&self, mut writer: W) -> io::Result<()>
     where
         W: io::Write,
     {
-        let cmf = (self.window_size.as_u4() << 4) | COMPRESSION_METHOD_DEFLATE;
-        let mut flg = self.compression_level.as_u2() << 6;
-        let check = (u16::from(cmf) << 8) + u16::from(flg);
-        if check % 31 != 0 {
-            flg += (31 - check % 31) as u8;
+        writer.write_all(&[self.cmf, self.flg])?;
+        if let Some(dictionary_id) = self.dictionary_id {
+            writer.write_all(&dictionary_id.to_be_bytes())?;
         }
-        writer.write_all(&[cmf, flg])?;
         Ok(())
     }
 }
 
+/// Reads a ZLIB header from `reader`.
+///
+/// This returns the header along with `reader` positioned at the beginning of the DEFLATE compressed data.
+/// Unlike [`Decoder::new`], a header which requires a preset dictionary is not rejected,
+/// so the caller can find out which dictionary is required via [`Header::dictionary_id`].
+///
+/// # Examples
+/// ```
+/// use libflate::zlib;
+///
+/// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+/// let (header, body) = zlib::read_header(&encoded_data[..]).unwrap();
+/// assert!(!header.has_preset_dictionary());
+/// assert_eq!(body, &encoded_data[2..]);
+/// ```
+pub fn read_header<R>(mut reader: R) -> io::Result<(Header, R)>
+where
+    R: io::Read,
+{
+    let header = Header::read_from(&mut reader, true)?;
+    Ok((header, reader))
+}
+
+/// Decodes a ZLIB stream prefixed with its decoded length (a 4-byte big-endian integer).
+///
+/// An `InvalidData` error is returned if the length of the decoded data differs from the prefix.
+/// The decoding stops as soon as the decoded data exceeds the declared length.
+///
+/// # Examples
+/// ```
+/// use libflate::zlib;
+///
+/// let encoded_data = [0, 0, 0, 12, 120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+/// let decoded_data = zlib::decode_with_length_prefix(&encoded_data[..]).unwrap();
+/// assert_eq!(decoded_data, b"Hello World!");
+///
+/// let mut encoded_data = encoded_data;
+/// encoded_data[3] = 11;
+/// assert!(zlib::decode_with_length_prefix(&encoded_data[..]).is_err());
+/// ```
+pub fn decode_with_length_prefix<R>(mut reader: R) -> io::Result<Vec<u8>>
+where
+    R: io::Read,
+{
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    let len = u32::from_be_bytes(buf);
+
+    // The capacity is bounded because the prefix is not trustworthy until the data is decoded
+    let mut decoded = Vec::with_capacity(cmp::min(len as usize, 64 * 1024));
+    Decoder::new(reader)?
+        .take(u64::from(len) + 1)
+        .read_to_end(&mut decoded)?;
+    if decoded.len() != len as usize {
+        return Err(invalid_data_error!(
+            "Decoded length mismatched: value={}, expected={}",
+            decoded.len(),
+            len
+        ));
+    }
+    Ok(decoded)
+}
+
+/// Encodes `original` as a ZLIB stream, decodes it again in the strict window mode
+/// and returns whether the result equals `original`.
+///
+/// This is intended as a diagnostic utility (e.g., for fuzzers) to validate the round-trip fidelity
+/// of the encoder and the decoder.
+/// See [`Decoder::set_strict_window`] for the strict window mode.
+///
+/// # Examples
+/// ```
+/// assert!(libflate::verify_roundtrip(b"Hello World!").unwrap());
+/// ```
+pub fn verify_roundtrip(original: &[u8]) -> io::Result<bool> {
+    let mut encoder = Encoder::new(Vec::new())?;
+    encoder.write_all(original)?;
+    let encoded = encoder.finish().into_result()?;
+
+    let mut decoder = Decoder::new(&encoded[..])?;
+    decoder.set_strict_window(true);
+    let mut decoded = Vec::with_capacity(original.len());
+    decoder.read_to_end(&mut decoded)?;
+    Ok(decoded == original)
+}
+
 /// ZLIB decoder.
 #[derive(Debug)]
 pub struct Decoder<R> {
     header: Header,
     reader: deflate::Decoder<R>,
     adler32: checksum::Adler32,
+    checksum_mismatch_policy: ChecksumMismatchPolicy,
+    checksum_ok: Option<bool>,
     eos: bool,
 }
 impl<R> Decoder<R>
@@ -309,14 +498,69 @@ where
     ///
     /// assert_eq!(buf, b"Hello World!");
     /// ```
-    pub fn new(mut inner: R) -> io::Result<Self> {
-        let header = Header::read_from(&mut inner)?;
-        Ok(Decoder {
+    ///
+    /// # Errors
+    ///
+    /// The header is read from `inner` immediately, so this fails
+    /// if `inner` is too short to contain the 2-byte header
+    /// (the error kind is `UnexpectedEof` and the message is `"ZLIB stream too short: need at least 2 header bytes"`)
+    /// or if the header is invalid (the error kind is `InvalidData`).
+    ///
+    /// ```
+    /// use core2::io::ErrorKind;
+    /// use libflate::zlib::Decoder;
+    ///
+    /// let e = Decoder::new(&[120][..]).unwrap_err();
+    /// assert_eq!(e.kind(), ErrorKind::UnexpectedEof);
+    /// ```
+    ///
+    /// Note that decoding in place (i.e., `read_to_end` into the buffer which `inner` reads from) is unsupported.
+    /// The decoder reads `inner` lazily, so such usage typically results in a "failed to fill whole buffer" error.
+    pub fn new(inner: R) -> io::Result<Self> {
+        Self::with_strictness(inner, true)
+    }
+
+    /// Makes a new decoder instance with the specified strictness of header validation.
+    ///
+    /// If `strict` is `true`, this is equivalent to [`Decoder::new`].
+    /// Otherwise, a header with an out-of-spec CINFO (window size) above 7 is accepted
+    /// and decoded as if CINFO were 7, because DEFLATE never refers to data further than 32 KB.
+    /// Other violations (e.g., non-DEFLATE compression methods or inconsistent check bits)
+    /// are rejected in both modes.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::zlib::{Decoder, Lz77WindowSize};
+    ///
+    /// // CINFO is 8 (`0x88`)
+    /// let encoded_data = [0x88, 0x1C, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    /// assert!(Decoder::with_strictness(&encoded_data[..], true).is_err());
+    ///
+    /// let mut decoder = Decoder::with_strictness(&encoded_data[..], false).unwrap();
+    /// assert_eq!(decoder.header().window_size(), Lz77WindowSize::KB32);
+    ///
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn with_strictness(mut inner: R, strict: bool) -> io::Result<Self> {
+        let header = Header::read_from(&mut inner, strict)?;
+        header.check_no_preset_dictionary()?;
+        Ok(Self::with_reader(header, deflate::Decoder::new(inner)))
+    }
+
+    /// Makes a new decoder instance which uses `buffer` as the buffer of decoded data.
+    ///
+    /// See [`deflate::Decoder::with_buffer`] for details.
+    pub fn with_buffer(mut inner: R, buffer: Vec<u8>) -> io::Result<Self> {
+        let header = Header::read_from(&mut inner, true)?;
+        header.check_no_preset_dictionary()?;
+        Ok(Self::with_reader(
             header,
-            reader: deflate::Decoder::new(inner),
-            adler32: checksum::Adler32::new(),
-            eos: false,
-        })
+            deflate::Decoder::with_buffer(inner, buffer),
+        ))
     }
 
     /// Returns the header of the ZLIB stream.
@@ -346,6 +590,34 @@ where
         self.reader.as_inner_mut()
     }
 
+    /// Returns the immutable reference to the inner stream.
+    ///
+    /// This is an alias of [`Decoder::as_inner_ref`].
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Cursor, Read};
+    /// use libflate::zlib::Decoder;
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0, 28, 73, 4, 62];
+    /// let mut decoder = Decoder::new(Cursor::new(&encoded_data[..])).unwrap();
+    /// assert_eq!(decoder.get_ref().position(), 2);
+    ///
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(decoder.get_ref().position(), encoded_data.len() as u64);
+    /// ```
+    pub fn get_ref(&self) -> &R {
+        self.as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    ///
+    /// This is an alias of [`Decoder::as_inner_mut`].
+    pub fn get_mut(&mut self) -> &mut R {
+        self.as_inner_mut()
+    }
+
     /// Unwraps this `Decoder`, returning the underlying reader.
     ///
     /// # Examples
@@ -363,12 +635,85 @@ where
         self.reader.into_inner()
     }
 
+    /// Unwraps this `Decoder`, returning the buffer of decoded data.
+    ///
+    /// See [`deflate::Decoder::into_buffer`] for details.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.reader.into_buffer()
+    }
+
     /// Returns the data that has been decoded but has not yet been read.
     ///
     /// This method is useful to retrieve partial decoded data when the decoding process is failed.
     pub fn unread_decoded_data(&self) -> &[u8] {
         self.reader.unread_decoded_data()
     }
+
+    /// Specifies the behavior when the Adler-32 checksum of the decoded data is mismatched.
+    ///
+    /// The default value is `ChecksumMismatchPolicy::Error`.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::Read;
+    /// use libflate::ChecksumMismatchPolicy;
+    /// use libflate::zlib::Decoder;
+    ///
+    /// // The last byte of the Adler-32 checksum is broken
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 0];
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// decoder.on_checksum_mismatch(ChecksumMismatchPolicy::Ignore);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// assert_eq!(decoder.checksum_ok(), Some(false));
+    /// ```
+    pub fn on_checksum_mismatch(&mut self, policy: ChecksumMismatchPolicy) {
+        self.checksum_mismatch_policy = policy;
+    }
+
+    /// Enables or disables the windowed mode of the underlying DEFLATE decoder.
+    ///
+    /// See [`deflate::Decoder::set_windowed`] for details.
+    pub fn set_windowed(&mut self, windowed: bool) {
+        self.reader.set_windowed(windowed);
+    }
+
+    /// Enables or disables the strict window mode.
+    ///
+    /// If enabled, a back-reference farther than the LZ77 window size declared in the header
+    /// (i.e., [`Header::window_size`]) is rejected with an `InvalidData` error.
+    ///
+    /// See [`deflate::Decoder::set_max_distance`] for details.
+    pub fn set_strict_window(&mut self, strict: bool) {
+        let max_distance = if strict {
+            self.header.window_size().to_u16()
+        } else {
+            lz77::MAX_DISTANCE
+        };
+        self.reader.set_max_distance(max_distance);
+    }
+
+    /// Returns whether the Adler-32 checksum of the decoded data matched the expected value.
+    ///
+    /// `None` is returned if the end of the stream has not been reached yet.
+    pub fn checksum_ok(&self) -> Option<bool> {
+        self.checksum_ok
+    }
+
+    fn with_reader(header: Header, reader: deflate::Decoder<R>) -> Self {
+        Decoder {
+            header,
+            reader,
+            adler32: checksum::Adler32::new(),
+            checksum_mismatch_policy: ChecksumMismatchPolicy::default(),
+            checksum_ok: None,
+            eos: false,
+        }
+    }
 }
 impl<R> io::Read for Decoder<R>
 where
@@ -388,11 +733,16 @@ where
                 let mut buf = [0; 4];
                 self.reader.as_inner_mut().read_exact(&mut buf)?;
                 let adler32 = u32::from_be_bytes(buf);
+                let checksum_ok = adler32 == self.adler32.value();
+                self.checksum_ok = Some(checksum_ok);
 
                 // checksum verification is skipped during fuzzing
                 // so that random data from fuzzer can reach actually interesting code
                 // Compilation flag 'fuzzing' is automatically set by all 3 Rust fuzzers.
-                if cfg!(not(fuzzing)) && adler32 != self.adler32.value() {
+                if cfg!(not(fuzzing))
+                    && !checksum_ok
+                    && self.checksum_mismatch_policy == ChecksumMismatchPolicy::Error
+                {
                     Err(invalid_data_error!(
                         "Adler32 checksum mismatched: value={}, expected={}",
                         self.adler32.value(),
@@ -410,7 +760,7 @@ where
 }
 
 /// Options for a ZLIB encoder.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EncodeOptions<E>
 where
     E: lz77::Lz77Encode,
@@ -418,6 +768,9 @@ where
     header: Header,
     options: deflate::EncodeOptions<E>,
     flush_mode: FlushMode,
+
+    // The level specified by `flevel`, which takes precedence over the one in `header`
+    flevel: Option<CompressionLevel>,
 }
 impl Default for EncodeOptions<lz77::DefaultLz77Encoder> {
     fn default() -> Self {
@@ -425,6 +778,7 @@ impl Default for EncodeOptions<lz77::DefaultLz77Encoder> {
             header: Header::from_lz77(&lz77::DefaultLz77Encoder::new()),
             options: Default::default(),
             flush_mode: FlushMode::None,
+            flevel: None,
         }
     }
 }
@@ -461,6 +815,7 @@ where
             header: Header::from_lz77(&lz77),
             options: deflate::EncodeOptions::with_lz77(lz77),
             flush_mode: FlushMode::None,
+            flevel: None,
         }
     }
 
@@ -476,7 +831,7 @@ where
     /// ```
     pub fn no_compression(mut self) -> Self {
         self.options = self.options.no_compression();
-        self.header.compression_level = CompressionLevel::Fastest;
+        self.header = Header::new(self.header.window_size(), CompressionLevel::Fastest);
         self
     }
 
@@ -510,11 +865,69 @@ where
         self
     }
 
+    /// Specifies the compression strategy.
+    ///
+    /// See [`deflate::Strategy`] for details.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::deflate::Strategy;
+    /// use libflate::zlib::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().strategy(Strategy::HuffmanOnly);
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    pub fn strategy(mut self, strategy: deflate::Strategy) -> Self {
+        self.options = self.options.strategy(strategy);
+        self
+    }
+
     /// Specifies flush mode.
     pub fn flush_mode(mut self, mode: FlushMode) -> Self {
         self.flush_mode = mode;
         self
     }
+
+    /// Specifies the compression level advertised by the FLEVEL field of the ZLIB header.
+    ///
+    /// The field is purely informational, so this only changes the header (e.g., to mimic another encoder);
+    /// the actual compression still depends on the LZ77 encoder.
+    /// By default, the level is derived from the LZ77 encoder's `compression_level()`
+    /// (or `Fastest` if [`EncodeOptions::no_compression`] is specified).
+    /// The level specified by this method always takes precedence regardless of the order of the calls.
+    ///
+    /// # Example
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::lz77::HuffmanOnlyLz77Encoder;
+    /// use libflate::zlib::{CompressionLevel, Decoder, Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::with_lz77(HuffmanOnlyLz77Encoder::new())
+    ///     .flevel(CompressionLevel::Slowest);
+    /// let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// assert_eq!(encoder.header().compression_level(), CompressionLevel::Slowest);
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// assert_eq!(decoder.header().compression_level(), CompressionLevel::Slowest);
+    /// let mut decoded_data = Vec::new();
+    /// decoder.read_to_end(&mut decoded_data).unwrap();
+    /// assert_eq!(decoded_data, b"Hello World!");
+    /// ```
+    pub fn flevel(mut self, level: CompressionLevel) -> Self {
+        self.flevel = Some(level);
+        self
+    }
+
+    /// Returns the header written by the encoder and the options of the underlying DEFLATE encoder.
+    pub(crate) fn into_parts(self) -> (Header, deflate::EncodeOptions<E>) {
+        let header = match self.flevel {
+            Some(level) => Header::new(self.header.window_size(), level),
+            None => self.header,
+        };
+        (header, self.options)
+    }
 }
 
 /// ZLIB encoder.
@@ -575,11 +988,13 @@ where
     ///             114, 108, 100, 33, 28, 73, 4, 62]);
     /// ```
     pub fn with_options(mut inner: W, options: EncodeOptions<E>) -> io::Result<Self> {
-        options.header.write_to(&mut inner)?;
+        let flush_mode = options.flush_mode;
+        let (header, options) = options.into_parts();
+        header.write_to(&mut inner)?;
         Ok(Encoder {
-            header: options.header,
-            flush_mode: options.flush_mode,
-            writer: deflate::Encoder::with_options(inner, options.options),
+            header,
+            flush_mode,
+            writer: deflate::Encoder::with_options(inner, options),
             adler32: checksum::Adler32::new(),
         })
     }
@@ -597,6 +1012,20 @@ where
         &self.header
     }
 
+    /// Writes all the chunks in `chunks` in order.
+    ///
+    /// The output of the encoder is identical regardless of how the input is chunked.
+    /// See [`deflate::Encoder::write_all_chunks`] for more details.
+    pub fn write_all_chunks<'a, I>(&mut self, chunks: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        for chunk in chunks {
+            io::Write::write_all(self, chunk)?;
+        }
+        Ok(())
+    }
+
     /// Writes the ZLIB trailer and returns the inner stream.
     ///
     /// # Examples
@@ -638,6 +1067,40 @@ where
         }
     }
 
+    /// Flushes the buffered data like [`FlushMode::Partial`] regardless of the flush mode of this encoder.
+    ///
+    /// After this, a decoder can decode all the data written so far,
+    /// while the overhead is smaller than that of [`FlushMode::Sync`] as the stream is not byte-aligned.
+    ///
+    /// # Examples
+    /// ```
+    /// use core2::io::{Read, Write};
+    /// use libflate::zlib::{Decoder, Encoder};
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// encoder.partial_flush().unwrap();
+    ///
+    /// let mut decoder = Decoder::new(&encoder.as_inner_ref()[..]).unwrap();
+    /// let mut buf = [0; 12];
+    /// decoder.read_exact(&mut buf).unwrap();
+    /// assert_eq!(&buf, b"Hello World!");
+    /// ```
+    pub fn partial_flush(&mut self) -> io::Result<()> {
+        self.writer.partial_flush()
+    }
+
+    /// Registers `f` to be called whenever a DEFLATE block is flushed.
+    ///
+    /// The number of output bytes passed to `f` only counts the DEFLATE data (i.e., not the ZLIB header).
+    /// See [`deflate::Encoder::on_progress`] for more details.
+    pub fn on_progress<F>(&mut self, f: F)
+    where
+        F: FnMut(u64, u64) + Send + 'static,
+    {
+        self.writer.on_progress(f);
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &W {
         self.writer.as_inner_ref()
@@ -666,7 +1129,8 @@ where
     fn flush(&mut self) -> io::Result<()> {
         match self.flush_mode {
             FlushMode::None => self.writer.flush(),
-            FlushMode::Sync => self.writer.zlib_sync_flush(),
+            FlushMode::Partial => self.writer.partial_flush(),
+            FlushMode::Sync => self.writer.sync_flush(),
         }
     }
 }
@@ -684,8 +1148,8 @@ where
 mod tests {
     use super::*;
     use crate::finish::AutoFinish;
+    use crate::util::pseudo_random_bytes;
     use alloc::{borrow::ToOwned, string::ToString, vec, vec::Vec};
-    use core2::io::{Read as _, Write as _};
 
     fn decode_all(buf: &[u8]) -> io::Result<Vec<u8>> {
         let mut decoder = Decoder::new(buf).unwrap();
@@ -717,8 +1181,13 @@ mod tests {
             Header {
                 window_size: Lz77WindowSize::KB32,
                 compression_level: CompressionLevel::Default,
+                cmf: 120,
+                flg: 156,
+                dictionary_id: None,
             }
         );
+        assert_eq!(decoder.header().cmf(), 120);
+        assert_eq!(decoder.header().flg(), 156);
 
         let mut buf = Vec::new();
         decoder.read_to_end(&mut buf).unwrap();
@@ -727,6 +1196,35 @@ mod tests {
         assert_eq!(buf, expected);
     }
 
+    #[test]
+    fn checksum_mismatch_policy_works() {
+        let mut encoded = DECODE_WORKS_TESTDATA;
+        *encoded.last_mut().unwrap() ^= 0xFF;
+
+        // ChecksumMismatchPolicy::Error
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        let mut buf = Vec::new();
+        let e = decoder.read_to_end(&mut buf).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(decoder.checksum_ok(), Some(false));
+
+        // ChecksumMismatchPolicy::Ignore
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        decoder.on_checksum_mismatch(ChecksumMismatchPolicy::Ignore);
+        assert_eq!(decoder.checksum_ok(), None);
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"Hello World!");
+        assert_eq!(decoder.checksum_ok(), Some(false));
+
+        // Matched checksum
+        let mut decoder = Decoder::new(&DECODE_WORKS_TESTDATA[..]).unwrap();
+        decoder.on_checksum_mismatch(ChecksumMismatchPolicy::Ignore);
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(decoder.checksum_ok(), Some(true));
+    }
+
     #[test]
     fn default_encode_works() {
         let plain = b"Hello World! Hello ZLIB!!";
@@ -837,6 +1335,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unused_io_amount)]
     fn test_issues_27() {
         // See: https://github.com/sile/libflate/issues/27
 
@@ -904,6 +1403,7 @@ mod tests {
     #[test]
     #[cfg(feature = "std")]
     /// See: https://github.com/sile/libflate/issues/61
+    #[allow(clippy::unused_io_amount)]
     fn issue_61() {
         let data = default_encode(b"Hello World").unwrap();
         let mut decoder = Decoder::new(&data[..]).unwrap();
@@ -932,4 +1432,199 @@ mod tests {
         ];
         assert_eq!(buf, decoded_data);
     }
+
+    #[test]
+    fn empty_input_works() {
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().fixed_huffman_codes(),
+            EncodeOptions::new().no_compression(),
+        ] {
+            let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+            assert_eq!(encoded[encoded.len() - 4..], [0, 0, 0, 1]); // Adler-32 of empty data
+
+            let mut decoder = Decoder::new(&encoded[..]).unwrap();
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert!(decoded.is_empty());
+            assert_eq!(decoder.checksum_ok(), Some(true));
+        }
+    }
+
+    #[test]
+    fn preset_dictionary_header_works() {
+        // FDICT is set and DICTID is `0x01020304`
+        let encoded = [0x78, 0xBB, 1, 2, 3, 4, 3, 0, 0, 0, 0, 1];
+
+        let (header, body) = read_header(&encoded[..]).unwrap();
+        assert!(header.has_preset_dictionary());
+        assert_eq!(header.dictionary_id(), Some(0x01020304));
+        assert_eq!(body, &encoded[6..]);
+
+        let mut buf = Vec::new();
+        header.write_to(&mut buf).unwrap();
+        assert_eq!(buf, encoded[..6]);
+
+        let e = Decoder::new(&encoded[..]).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        #[cfg(feature = "std")]
+        assert_eq!(
+            e.to_string(),
+            "Preset dictionaries are not supported: dictionary_id=0x01020304"
+        );
+    }
+
+    #[test]
+    fn strict_window_works() {
+        // Only matches at the distance of 1000 are available
+        let block = pseudo_random_bytes(1000);
+        let plain = block.repeat(4);
+
+        let lz77 = lz77::DefaultLz77Encoder::with_window_size(1024);
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::with_lz77(lz77)).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        decoder.set_strict_window(true);
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, plain);
+
+        // Declares the 256 bytes window
+        let mut encoded = encoded;
+        encoded[0] = 0x08;
+        encoded[1] = 0x1D;
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        assert_eq!(decoder.header().window_size(), Lz77WindowSize::B256);
+        decoder.set_strict_window(true);
+        let e = decoder.read_to_end(&mut Vec::new()).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, plain);
+    }
+
+    #[test]
+    fn decode_with_length_prefix_works() {
+        let plain = include_bytes!("../README.md");
+        let encoded = default_encode(plain).unwrap();
+        let prefixed = |len: u32| {
+            let mut buf = len.to_be_bytes().to_vec();
+            buf.extend_from_slice(&encoded);
+            buf
+        };
+
+        let decoded = decode_with_length_prefix(&prefixed(plain.len() as u32)[..]).unwrap();
+        assert_eq!(decoded, plain);
+
+        for len in [0, plain.len() as u32 - 1, plain.len() as u32 + 1, u32::MAX] {
+            let e = decode_with_length_prefix(&prefixed(len)[..]).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        }
+        assert!(decode_with_length_prefix(&[0, 0][..]).is_err());
+    }
+
+    #[test]
+    fn partial_flush_works() {
+        let messages = [&b"Hello World!"[..], b"foo", b"", b"bar baz bar baz"];
+        let encode = |mode: FlushMode| {
+            let options = EncodeOptions::new().flush_mode(mode);
+            let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+            let mut sizes = Vec::new();
+            for (i, message) in messages.iter().enumerate() {
+                encoder.write_all(message).unwrap();
+                encoder.flush().unwrap();
+                sizes.push(encoder.as_inner_ref().len());
+
+                // All the messages written so far can be decoded
+                let expected = messages[..=i].concat();
+                let mut decoder = Decoder::new(&encoder.as_inner_ref()[..]).unwrap();
+                let mut decoded = vec![0; expected.len()];
+                decoder.read_exact(&mut decoded).unwrap();
+                assert_eq!(decoded, expected);
+            }
+            let encoded = encoder.finish().into_result().unwrap();
+            assert_eq!(decode_all(&encoded).unwrap(), messages.concat());
+            sizes
+        };
+
+        let partial = encode(FlushMode::Partial);
+        let sync = encode(FlushMode::Sync);
+        for (partial, sync) in partial.into_iter().zip(sync) {
+            assert!(partial < sync, "partial={partial}, sync={sync}");
+        }
+    }
+
+    #[test]
+    fn too_short_header_is_reported() {
+        for input in [&[][..], &[120]] {
+            let e = Decoder::new(input).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+            assert_eq!(e.to_string(), TRUNCATED_IN_HEADER);
+        }
+
+        // FDICT is set, but DICTID is missing
+        let e = Decoder::new(&[0x78, 0xBB, 0, 0][..]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(e.to_string(), TRUNCATED_IN_DICTID);
+    }
+
+    #[test]
+    fn flevel_works() {
+        use crate::lz77::{HuffmanOnlyLz77Encoder, Lz77Encode};
+
+        fn encode_options<E: Lz77Encode>(buf: &[u8], options: EncodeOptions<E>) -> Vec<u8> {
+            let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+            encoder.write_all(buf).unwrap();
+            encoder.finish().into_result().unwrap()
+        }
+
+        for level in [
+            CompressionLevel::Fastest,
+            CompressionLevel::Fast,
+            CompressionLevel::Default,
+            CompressionLevel::Slowest,
+        ] {
+            let options = EncodeOptions::new().flevel(level.clone());
+            let encoded = encode_options(b"Hello World!", options);
+            let fast =
+                EncodeOptions::with_lz77(HuffmanOnlyLz77Encoder::new()).flevel(level.clone());
+            let fast_encoded = encode_options(b"Hello World!", fast);
+            for encoded in [encoded, fast_encoded] {
+                let mut decoder = Decoder::new(&encoded[..]).unwrap();
+                assert_eq!(decoder.header().compression_level(), level);
+                let mut decoded = Vec::new();
+                decoder.read_to_end(&mut decoded).unwrap();
+                assert_eq!(decoded, b"Hello World!");
+            }
+        }
+
+        // The order of `flevel` and `no_compression` does not matter
+        for options in [
+            EncodeOptions::new()
+                .flevel(CompressionLevel::Slowest)
+                .no_compression(),
+            EncodeOptions::new()
+                .no_compression()
+                .flevel(CompressionLevel::Slowest),
+        ] {
+            let encoded = encode_options(b"Hello World!", options);
+            let decoder = Decoder::new(&encoded[..]).unwrap();
+            assert_eq!(
+                decoder.header().compression_level(),
+                CompressionLevel::Slowest
+            );
+        }
+    }
+
+    #[test]
+    fn verify_roundtrip_works() {
+        assert!(verify_roundtrip(&[]).unwrap());
+        assert!(verify_roundtrip(include_bytes!("../README.md")).unwrap());
+        assert!(verify_roundtrip(&[0; 100000]).unwrap());
+    }
 }